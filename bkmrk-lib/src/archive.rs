@@ -0,0 +1,413 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use eyre::{eyre, Result, WrapErr};
+use log::warn;
+use once_cell::sync::Lazy;
+use regex::{Captures, NoExpand, Regex};
+use url::Url;
+
+use crate::{fetch, utils};
+
+/// A saved copy of a bookmarked page.
+#[derive(Debug, Clone)]
+pub struct Archive {
+    pub path: PathBuf,
+    pub archived_at: i64,
+}
+
+/// Nesting limit for stylesheets that `@import` other stylesheets.
+const MAX_IMPORT_DEPTH: usize = 3;
+
+/// Attributes read or rewritten by the inliner.
+const ATTRIBUTES: &[&str] = &["href", "rel", "src", "srcset", "sizes"];
+
+static PATTERNS: Lazy<Patterns> = Lazy::new(Patterns::new);
+
+/// Tags, attributes and CSS references that the inliner rewrites.
+struct Patterns {
+    base: Regex,
+    script: Regex,
+    noscript: Regex,
+    style: Regex,
+    link: Regex,
+    source: Regex,
+    img: Regex,
+    head: Regex,
+    import: Regex,
+    url: Regex,
+    attributes: HashMap<&'static str, Regex>,
+}
+
+impl Patterns {
+    fn new() -> Self {
+        Self {
+            base: Regex::new(r#"(?is)<base\b[^>]*>"#).unwrap(),
+            script: Regex::new(r#"(?is)<script\b[^>]*>.*?</script\s*>"#).unwrap(),
+            noscript: Regex::new(r#"(?is)<noscript\b[^>]*>(.*?)</noscript\s*>"#).unwrap(),
+            style: Regex::new(r#"(?is)(<style\b[^>]*>)(.*?)(</style\s*>)"#).unwrap(),
+            link: Regex::new(r#"(?is)<link\b[^>]*>"#).unwrap(),
+            source: Regex::new(r#"(?is)<source\b[^>]*\bsrcset\s*=[^>]*>"#).unwrap(),
+            img: Regex::new(r#"(?is)<img\b[^>]*>"#).unwrap(),
+            head: Regex::new(r#"(?is)<head\b[^>]*>"#).unwrap(),
+            import: Regex::new(
+                r#"(?i)@import\s+(?:url\(\s*(?:"([^"]*)"|'([^']*)'|([^)\s]*))\s*\)|"([^"]*)"|'([^']*)')\s*([^;]*);"#,
+            )
+            .unwrap(),
+            url: Regex::new(r#"(?i)url\(\s*(?:"([^"]*)"|'([^']*)'|([^)\s]*))\s*\)"#).unwrap(),
+            attributes: ATTRIBUTES
+                .iter()
+                .map(|name| {
+                    let re = Regex::new(&format!(
+                        r#"(?is)\s{}\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#,
+                        regex::escape(name)
+                    ))
+                    .unwrap();
+                    (*name, re)
+                })
+                .collect(),
+        }
+    }
+
+    /// Matches the attribute `name`, one of [`ATTRIBUTES`], in a start tag.
+    fn attribute(&self, name: &str) -> &Regex {
+        &self.attributes[name]
+    }
+}
+
+/// Downloads `link` into `dir` and returns the path of the saved file.
+///
+/// HTML pages are saved as a single self-contained file: stylesheets are
+/// inlined, images, icons and fonts are embedded as `data:` URIs and scripts
+/// are removed. Other content is saved as downloaded.
+pub(crate) fn save(link: &str, dir: &Path) -> Result<Archive> {
+    let response = fetch::fetch(link).wrap_err("Couldn't download page")?;
+    if response.status >= 400 {
+        return Err(eyre!("{} returned HTTP {}", link, response.status));
+    }
+
+    let archived_at = Utc::now().timestamp();
+    let (contents, extension) = match response.content_type.as_deref() {
+        Some("text/html") | Some("application/xhtml+xml") | None => {
+            let mut inliner = Inliner::default();
+            let html = inliner.inline_page(&response.text(), &response.url);
+            (html.into_bytes(), "html")
+        }
+        Some(content_type) => (response.body, extension_for(content_type)),
+    };
+
+    fs::create_dir_all(dir).wrap_err_with(|| format!("Couldn't create {}", dir.display()))?;
+    let path = dir.join(format!("{}.{}", archived_at, extension));
+    fs::write(&path, contents).wrap_err_with(|| format!("Couldn't write {}", path.display()))?;
+
+    Ok(Archive { path, archived_at })
+}
+
+pub(crate) fn extension_for(content_type: &str) -> &'static str {
+    match content_type {
+        "application/pdf" => "pdf",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/avif" => "avif",
+        "image/svg+xml" => "svg",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        "text/plain" => "txt",
+        "application/json" => "json",
+        _ => "bin",
+    }
+}
+
+/// Embeds the resources a page depends on, fetching each URL at most once.
+#[derive(Default)]
+struct Inliner {
+    data_uris: HashMap<String, Option<String>>,
+}
+
+impl Inliner {
+    fn inline_page(&mut self, html: &str, page_url: &str) -> String {
+        let patterns = &*PATTERNS;
+        let base = patterns
+            .base
+            .find(html)
+            .and_then(|tag| attribute(tag.as_str(), "href"))
+            .and_then(|href| resolve(page_url, &href))
+            .unwrap_or_else(|| page_url.to_owned());
+
+        let html = patterns.script.replace_all(html, "");
+        let html = patterns.noscript.replace_all(&html, "$1");
+
+        let html = patterns.style.replace_all(&html, |caps: &Captures| {
+            format!(
+                "{}{}{}",
+                &caps[1],
+                self.inline_css(&caps[2], &base, 0),
+                &caps[3]
+            )
+        });
+
+        let html = patterns.link.replace_all(&html, |caps: &Captures| {
+            let tag = &caps[0];
+            let rel = attribute(tag, "rel").unwrap_or_default().to_lowercase();
+            let href = attribute(tag, "href").and_then(|href| resolve(&base, &href));
+            match (rel.split_whitespace().collect::<Vec<_>>(), href) {
+                (rel, Some(href)) if rel.contains(&"stylesheet") => {
+                    match self.fetch_css(&href, 0) {
+                        Some(css) => format!("<style>{}</style>", css),
+                        None => tag.to_owned(),
+                    }
+                }
+                (rel, Some(href)) if rel.contains(&"icon") => match self.data_uri(&href) {
+                    Some(uri) => set_attribute(tag, "href", &uri),
+                    None => tag.to_owned(),
+                },
+                _ => tag.to_owned(),
+            }
+        });
+
+        // Responsive image candidates would be fetched from the network, so
+        // only the plain `src` is kept.
+        let html = patterns.source.replace_all(&html, "");
+
+        let html = patterns.img.replace_all(&html, |caps: &Captures| {
+            let tag = remove_attribute(&remove_attribute(&caps[0], "srcset"), "sizes");
+            let src = attribute(&tag, "src").and_then(|src| resolve(&base, &src));
+            match src.and_then(|src| self.data_uri(&src)) {
+                Some(uri) => set_attribute(&tag, "src", &uri),
+                None => tag,
+            }
+        });
+
+        // Links that are still relative should point at the original site.
+        let html = patterns.base.replace_all(&html, "");
+        let base_tag = format!("<base href=\"{}\">", utils::escape_html(&base));
+        match patterns.head.find(&html) {
+            Some(head) => format!("{}{}{}", &html[..head.end()], base_tag, &html[head.end()..]),
+            None => format!("{}{}", base_tag, html),
+        }
+    }
+
+    fn fetch_css(&mut self, url: &str, depth: usize) -> Option<String> {
+        let response = match fetch::fetch(url) {
+            Ok(response) if response.status < 400 => response,
+            Ok(response) => {
+                warn!("Couldn't archive {}: HTTP {}", url, response.status);
+                return None;
+            }
+            Err(e) => {
+                warn!("Couldn't archive {}: {}", url, e);
+                return None;
+            }
+        };
+        Some(self.inline_css(&response.text(), &response.url, depth))
+    }
+
+    /// Inlines `@import`ed stylesheets and embeds `url()` references in `css`,
+    /// resolving relative URLs against `css_url`.
+    fn inline_css(&mut self, css: &str, css_url: &str, depth: usize) -> String {
+        let css = PATTERNS.import.replace_all(css, |caps: &Captures| {
+            let href = (1..=5).find_map(|i| caps.get(i)).map(|m| m.as_str());
+            let imported = match href.and_then(|href| resolve(css_url, href)) {
+                Some(href) if depth < MAX_IMPORT_DEPTH => self.fetch_css(&href, depth + 1),
+                _ => None,
+            };
+            match (imported, caps[6].trim()) {
+                (Some(imported), "") => imported,
+                (Some(imported), media) => format!("@media {} {{{}}}", media, imported),
+                (None, _) => caps[0].to_owned(),
+            }
+        });
+
+        PATTERNS
+            .url
+            .replace_all(&css, |caps: &Captures| {
+                let href = (1..=3).find_map(|i| caps.get(i)).map_or("", |m| m.as_str());
+                if href.starts_with("data:") || href.starts_with('#') {
+                    return caps[0].to_owned();
+                }
+                match resolve(css_url, href).and_then(|href| self.data_uri(&href)) {
+                    Some(uri) => format!("url(\"{}\")", uri),
+                    None => caps[0].to_owned(),
+                }
+            })
+            .into_owned()
+    }
+
+    fn data_uri(&mut self, url: &str) -> Option<String> {
+        if url.starts_with("data:") {
+            return Some(url.to_owned());
+        }
+        if let Some(uri) = self.data_uris.get(url) {
+            return uri.to_owned();
+        }
+
+        let uri = match fetch::fetch(url) {
+            Ok(response) if response.status < 400 => Some(format!(
+                "data:{};base64,{}",
+                response
+                    .content_type
+                    .as_deref()
+                    .unwrap_or("application/octet-stream"),
+                base64::encode(&response.body)
+            )),
+            Ok(response) => {
+                warn!("Couldn't archive {}: HTTP {}", url, response.status);
+                None
+            }
+            Err(e) => {
+                warn!("Couldn't archive {}: {}", url, e);
+                None
+            }
+        };
+        self.data_uris.insert(url.to_owned(), uri.to_owned());
+        uri
+    }
+}
+
+fn resolve(base: &str, href: &str) -> Option<String> {
+    let url = Url::parse(base).ok()?.join(href.trim()).ok()?;
+    match url.scheme() {
+        "http" | "https" | "data" => Some(url.to_string()),
+        _ => None,
+    }
+}
+
+/// Value of the attribute `name` of an HTML start tag.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let caps = PATTERNS.attribute(name).captures(tag)?;
+    let value = (1..=3).find_map(|i| caps.get(i))?.as_str();
+    Some(utils::unescape_html(value))
+}
+
+fn set_attribute(tag: &str, name: &str, value: &str) -> String {
+    PATTERNS
+        .attribute(name)
+        .replace(
+            tag,
+            NoExpand(&format!(" {}=\"{}\"", name, utils::escape_html(value))),
+        )
+        .into_owned()
+}
+
+fn remove_attribute(tag: &str, name: &str) -> String {
+    PATTERNS.attribute(name).replace_all(tag, "").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Response, TestServer};
+
+    const PAGE: &str = r#"<html><head>
+<base href="/assets/">
+<link rel="stylesheet" href="style.css">
+<link rel="icon" href="/favicon.ico">
+<style>body { background: url(bg.png) }</style>
+<script src="app.js"></script>
+<script>document.write("tracking")</script>
+</head><body>
+<noscript><p>No scripts here</p></noscript>
+<img src="logo.png" srcset="logo-2x.png 2x" sizes="100px">
+<img src='logo.png'>
+<img src="missing.png">
+<a href="other.html">Other</a>
+</body></html>"#;
+
+    fn server() -> TestServer {
+        TestServer::start(|_, path| match path {
+            "/page" => Response::ok("text/html", PAGE),
+            "/assets/style.css" => {
+                Response::ok("text/css", "@import \"print.css\" print; h1 { color: red }")
+            }
+            "/assets/print.css" => Response::ok("text/css", "p { font: url('font.woff') }"),
+            "/assets/bg.png" | "/assets/logo.png" => Response::ok("image/png", [1, 2, 3]),
+            "/assets/font.woff" => Response::ok("font/woff", "font"),
+            "/favicon.ico" => Response::ok("image/x-icon", "icon"),
+            "/report.pdf" => Response::ok("application/pdf", "%PDF-1.4"),
+            _ => Response::status(404),
+        })
+    }
+
+    fn archive(server: &TestServer, path: &str) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = save(&server.url(path), dir.path()).unwrap();
+        fs::read_to_string(archive.path).unwrap()
+    }
+
+    #[test]
+    fn removes_scripts() {
+        let html = archive(&server(), "/page");
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("tracking"));
+        assert!(html.contains("<p>No scripts here</p>"));
+        assert!(!html.contains("<noscript"));
+    }
+
+    #[test]
+    fn inlines_stylesheets() {
+        let html = archive(&server(), "/page");
+        assert!(!html.contains("stylesheet"));
+        assert!(html.contains("h1 { color: red }"));
+        assert!(html.contains("@media print {p { font: url(\"data:font/woff;base64,Zm9udA==\") }}"));
+    }
+
+    #[test]
+    fn embeds_images_and_css_urls() {
+        let server = server();
+        let html = archive(&server, "/page");
+        assert!(html.contains("background: url(\"data:image/png;base64,AQID\")"));
+        assert_eq!(
+            html.matches("src=\"data:image/png;base64,AQID\"").count(),
+            2
+        );
+        assert!(!html.contains("srcset"));
+        assert!(!html.contains("sizes"));
+        assert!(html.contains("href=\"data:image/x-icon;base64,aWNvbg==\""));
+
+        // Repeated images are downloaded once
+        let logo_requests = server
+            .requests()
+            .iter()
+            .filter(|request| request.ends_with("/logo.png"))
+            .count();
+        assert_eq!(logo_requests, 1);
+    }
+
+    #[test]
+    fn resolves_against_base() {
+        let server = server();
+        let html = archive(&server, "/page");
+        assert_eq!(html.matches("<base").count(), 1);
+        let base = format!("<head><base href=\"{}\">", server.url("/assets/"));
+        assert!(html.contains(&base));
+        assert!(html.contains("<a href=\"other.html\">"));
+    }
+
+    #[test]
+    fn keeps_references_that_failed_to_download() {
+        let html = archive(&server(), "/page");
+        assert!(html.contains("<img src=\"missing.png\">"));
+    }
+
+    #[test]
+    fn saves_other_content_as_downloaded() {
+        let server = server();
+        let dir = tempfile::tempdir().unwrap();
+        let archive = save(&server.url("/report.pdf"), dir.path()).unwrap();
+        assert_eq!(archive.path.extension().unwrap(), "pdf");
+        assert_eq!(fs::read(archive.path).unwrap(), b"%PDF-1.4");
+    }
+
+    #[test]
+    fn fails_for_missing_pages() {
+        let server = server();
+        let dir = tempfile::tempdir().unwrap();
+        assert!(save(&server.url("/gone"), dir.path()).is_err());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,307 @@
+use std::{io::ErrorKind, path::Path};
+
+use eyre::{eyre, Result, WrapErr};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{bookmark::Bookmark, utils};
+
+/// A rule as written in the rules file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    tags: Vec<String>,
+    #[serde(default)]
+    domains: Vec<String>,
+    url: Option<String>,
+    #[serde(default)]
+    title: Vec<String>,
+    #[serde(default)]
+    site_types: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<RuleConfig>,
+}
+
+/// Tags to add to bookmarks that match all of the rule's conditions. Within
+/// a condition, any of the values may match.
+#[derive(Debug, Clone)]
+pub struct AutoTagRule {
+    pub tags: Vec<String>,
+    /// Hosts, matching their subdomains too
+    pub domains: Vec<String>,
+    /// Pattern searched for in the link
+    pub url: Option<Regex>,
+    /// Words or phrases in the title, ignoring case
+    pub title: Vec<String>,
+    /// Site types like `video.movie`. A trailing `*` matches any type
+    /// starting with the rest, e.g. `video.*`.
+    pub site_types: Vec<String>,
+}
+
+impl AutoTagRule {
+    pub fn matches(&self, bookmark: &Bookmark) -> bool {
+        let host = utils::get_host(&bookmark.link).unwrap_or_default();
+        let title = bookmark.metadata.title.to_lowercase();
+        let site_type = bookmark.metadata.site_type.to_string();
+
+        (self.domains.is_empty()
+            || self.domains.iter().any(|domain| {
+                host == *domain
+                    || host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|rest| rest.ends_with('.'))
+            }))
+            && self
+                .url
+                .as_ref()
+                .is_none_or(|url| url.is_match(&bookmark.link))
+            && (self.title.is_empty() || self.title.iter().any(|word| title.contains(word)))
+            && (self.site_types.is_empty()
+                || self
+                    .site_types
+                    .iter()
+                    .any(|pattern| match pattern.strip_suffix('*') {
+                        Some(prefix) => site_type.starts_with(prefix),
+                        None => site_type == *pattern,
+                    }))
+    }
+}
+
+/// Adds tags to bookmarks by rules, read from a TOML file like
+///
+/// ```toml
+/// [[rule]]
+/// tags = ["rust"]
+/// domains = ["docs.rs", "crates.io"]
+///
+/// [[rule]]
+/// tags = ["video"]
+/// site_types = ["video.*"]
+///
+/// [[rule]]
+/// tags = ["ml", "papers"]
+/// url = "arxiv\\.org/abs/"
+/// title = ["learning", "neural"]
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AutoTagger {
+    rules: Vec<AutoTagRule>,
+}
+
+impl AutoTagger {
+    /// A tagger without rules, which adds no tags.
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(rules: &str) -> Result<Self> {
+        let file: RulesFile = toml::from_str(rules)?;
+        let rules = file
+            .rules
+            .into_iter()
+            .enumerate()
+            .map(|(idx, rule)| {
+                let rule = AutoTagRule {
+                    tags: rule.tags,
+                    domains: rule
+                        .domains
+                        .iter()
+                        .map(|domain| domain.trim().to_lowercase())
+                        .collect(),
+                    url: rule
+                        .url
+                        .as_deref()
+                        .map(Regex::new)
+                        .transpose()
+                        .wrap_err_with(|| format!("Invalid url pattern in rule {}", idx + 1))?,
+                    title: rule.title.iter().map(|word| word.to_lowercase()).collect(),
+                    site_types: rule.site_types,
+                };
+                if rule.tags.is_empty() {
+                    return Err(eyre!("Rule {} has no tags", idx + 1));
+                }
+                if rule.domains.is_empty()
+                    && rule.url.is_none()
+                    && rule.title.is_empty()
+                    && rule.site_types.is_empty()
+                {
+                    return Err(eyre!("Rule {} has no conditions", idx + 1));
+                }
+                Ok(rule)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Reads the rules in `path`. A missing file has no rules.
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(rules) => Self::parse(&rules)
+                .wrap_err_with(|| format!("Couldn't read tagging rules in {}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::empty()),
+            Err(e) => Err(e).wrap_err_with(|| format!("Couldn't open {}", path.display())),
+        }
+    }
+
+    pub fn rules(&self) -> &[AutoTagRule] {
+        &self.rules
+    }
+
+    /// Tags of all rules matching `bookmark`, without repeats. Tags the
+    /// bookmark already has are included.
+    pub fn tags_for(&self, bookmark: &Bookmark) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for rule in self.rules.iter().filter(|rule| rule.matches(bookmark)) {
+            for tag in &rule.tags {
+                if !tags.contains(tag) {
+                    tags.push(tag.to_owned());
+                }
+            }
+        }
+        tags
+    }
+
+    /// `bookmark` with the tags of matching rules added.
+    pub fn apply(&self, bookmark: &Bookmark) -> Bookmark {
+        let mut bookmark = bookmark.to_owned();
+        for tag in self.tags_for(&bookmark) {
+            if !bookmark.tags.0.contains(&tag) {
+                bookmark.tags.0.push(tag);
+            }
+        }
+        bookmark
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::site_metadata::SiteType;
+
+    const RULES: &str = r#"
+        [[rule]]
+        tags = ["rust"]
+        domains = ["docs.rs", "Crates.io "]
+
+        [[rule]]
+        tags = ["video"]
+        site_types = ["video.*"]
+
+        [[rule]]
+        tags = ["ml", "papers"]
+        url = "arxiv\\.org/abs/"
+        title = ["Learning", "neural"]
+    "#;
+
+    fn tags_with(tagger: &AutoTagger, link: &str, title: &str, site_type: SiteType) -> Vec<String> {
+        let mut bookmark = Bookmark::sample(link, title, &[]);
+        bookmark.metadata.site_type = site_type;
+        tagger.tags_for(&bookmark)
+    }
+
+    fn tags_for(link: &str, title: &str, site_type: SiteType) -> Vec<String> {
+        tags_with(&AutoTagger::parse(RULES).unwrap(), link, title, site_type)
+    }
+
+    #[test]
+    fn parses_rules() {
+        let tagger = AutoTagger::parse(RULES).unwrap();
+        let rules = tagger.rules();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[0].domains, ["docs.rs", "crates.io"]);
+        assert_eq!(rules[1].site_types, ["video.*"]);
+        assert_eq!(rules[2].tags, ["ml", "papers"]);
+        assert_eq!(rules[2].title, ["learning", "neural"]);
+        assert!(rules[2].url.is_some());
+    }
+
+    #[test]
+    fn matches_domains_and_subdomains() {
+        let website = SiteType::Website;
+        assert_eq!(tags_for("https://docs.rs/regex", "", website), ["rust"]);
+        assert_eq!(
+            tags_for("https://crates.io/crates/url", "", website),
+            ["rust"]
+        );
+        assert_eq!(tags_for("https://api.docs.rs/x", "", website), ["rust"]);
+        assert!(tags_for("https://notdocs.rs/", "", website).is_empty());
+        assert!(tags_for("https://docs.rs.example.com/", "", website).is_empty());
+    }
+
+    #[test]
+    fn matches_site_type_wildcards() {
+        let link = "https://example.com/watch";
+        assert_eq!(tags_for(link, "", SiteType::VideoMovie), ["video"]);
+        assert_eq!(tags_for(link, "", SiteType::VideoEpisode), ["video"]);
+        assert!(tags_for(link, "", SiteType::MusicSong).is_empty());
+
+        let rules = "[[rule]]\ntags = [\"movies\"]\nsite_types = [\"video.movie\"]";
+        let tagger = AutoTagger::parse(rules).unwrap();
+        assert_eq!(
+            tags_with(&tagger, link, "", SiteType::VideoMovie),
+            ["movies"]
+        );
+        assert!(tags_with(&tagger, link, "", SiteType::VideoOther).is_empty());
+    }
+
+    #[test]
+    fn requires_all_conditions_of_a_rule() {
+        let link = "https://arxiv.org/abs/1706.03762";
+        let website = SiteType::Website;
+        assert_eq!(
+            tags_for(link, "Deep Learning for Everyone", website),
+            ["ml", "papers"]
+        );
+        assert_eq!(tags_for(link, "Neural networks", website), ["ml", "papers"]);
+        assert!(tags_for(link, "Attention Is All You Need", website).is_empty());
+        assert!(tags_for("https://arxiv.org/list/cs", "Learning", website).is_empty());
+    }
+
+    #[test]
+    fn combines_matching_rules_without_repeats() {
+        let tagger = AutoTagger::parse(
+            r#"
+            [[rule]]
+            tags = ["rust", "web"]
+            domains = ["tokio.rs"]
+
+            [[rule]]
+            tags = ["rust", "async"]
+            title = ["tokio"]
+            "#,
+        )
+        .unwrap();
+        let mut bookmark = Bookmark::sample("https://tokio.rs/", "Tokio", &[]);
+        assert_eq!(tagger.tags_for(&bookmark), ["rust", "web", "async"]);
+
+        bookmark.tags.0 = vec!["web".to_owned(), "tutorial".to_owned()];
+        assert_eq!(
+            tagger.apply(&bookmark).tags.0,
+            ["web", "tutorial", "rust", "async"]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        // Unknown keys, e.g. misspelled conditions
+        assert!(AutoTagger::parse("[[rule]]\ntags = [\"a\"]\ndomain = [\"x.com\"]").is_err());
+        assert!(AutoTagger::parse("[[rules]]\ntags = [\"a\"]\ndomains = [\"x.com\"]").is_err());
+        // Rules without tags or conditions
+        assert!(AutoTagger::parse("[[rule]]\ntags = []\ndomains = [\"x.com\"]").is_err());
+        assert!(AutoTagger::parse("[[rule]]\ntags = [\"a\"]").is_err());
+        // Invalid URL patterns
+        assert!(AutoTagger::parse("[[rule]]\ntags = [\"a\"]\nurl = \"(\"").is_err());
+    }
+
+    #[test]
+    fn treats_a_missing_file_as_no_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let tagger = AutoTagger::load(&dir.path().join("autotag.toml")).unwrap();
+        assert!(tagger.rules().is_empty());
+    }
+}
@@ -1,9 +1,11 @@
 use crate::{site_metadata::SiteMetadata, utils};
-use std::fmt::Display;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, path::PathBuf};
 #[cfg(feature = "tables")]
 use tabled::*;
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct TagList(pub Vec<String>);
 
 impl Display for TagList {
@@ -18,7 +20,8 @@ impl From<Vec<String>> for TagList {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Bookmark {
     pub id: String,
     pub link: String,
@@ -26,6 +29,12 @@ pub struct Bookmark {
     pub last_modified: i64,
     pub metadata: SiteMetadata,
     pub tags: TagList,
+    /// Cached copy of the page's favicon, see [`crate::BkmrkMan::cache_assets`]
+    #[serde(skip)]
+    pub favicon_path: Option<PathBuf>,
+    /// Cached copy of the page's preview image
+    #[serde(skip)]
+    pub image_path: Option<PathBuf>,
 }
 
 #[cfg(feature = "tables")]
@@ -53,29 +62,81 @@ impl Tabled for Bookmark {
 }
 
 impl Bookmark {
+    /// Replaces the placeholders listed in the `list` command's help in
+    /// `format_string`. Unknown placeholders are kept as they are.
     pub fn format(&self, format_string: &str) -> String {
-        let result = String::from(format_string);
+        let date = |ts: Option<i64>| ts.map(utils::get_date_string).unwrap_or_default();
+        let text = |text: &Option<String>| text.to_owned().unwrap_or_default();
+        let path = |path: &Option<PathBuf>| {
+            path.as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default()
+        };
 
-        let result = result.replace("%n", &unescape::unescape(&self.metadata.title).unwrap());
-        let result = result.replace("%l", &unescape::unescape(&self.link).unwrap());
-        let result = result.replace(
-            "%a",
-            &unescape::unescape(&utils::get_date_string(self.added_at)).unwrap(),
-        );
-        let result = result.replace(
-            "%m",
-            &unescape::unescape(&utils::get_date_string(self.last_modified)).unwrap(),
-        );
-        let result = result.replace("%t", &unescape::unescape(&self.tags.0.join(", ")).unwrap());
-        let result = result.replace(
-            "%d",
-            &unescape::unescape(&self.metadata.description.to_owned().unwrap_or_default()).unwrap(),
-        );
+        let mut result = String::with_capacity(format_string.len());
+        let mut chars = format_string.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                result.push(c);
+                continue;
+            }
+
+            let value = match chars.next() {
+                Some('n') => self.metadata.title.to_owned(),
+                Some('l') => self.link.to_owned(),
+                Some('a') => utils::get_date_string(self.added_at),
+                Some('m') => utils::get_date_string(self.last_modified),
+                Some('t') => self.tags.0.join(", "),
+                Some('d') => text(&self.metadata.description),
+                Some('i') => text(&self.metadata.image_url),
+                Some('y') => self.metadata.site_type.to_string(),
+                Some('f') => text(&self.metadata.favicon_url),
+                Some('c') => text(&self.metadata.canonical_url),
+                Some('s') => text(&self.metadata.site_name),
+                Some('w') => text(&self.metadata.author),
+                Some('g') => text(&self.metadata.language),
+                Some('p') => date(self.metadata.published_at),
+                Some('u') => date(self.metadata.modified_at),
+                Some('k') => self.metadata.keywords.join(", "),
+                Some('r') => self.metadata.feeds.join(", "),
+                Some('F') => path(&self.favicon_path),
+                Some('I') => path(&self.image_path),
+                Some(other) => {
+                    result.push('%');
+                    result.push(other);
+                    continue;
+                }
+                None => {
+                    result.push('%');
+                    break;
+                }
+            };
+            result.push_str(&unescape::unescape(&value).unwrap_or(value));
+        }
 
         result
     }
 }
 
+#[cfg(test)]
+impl Bookmark {
+    /// A bookmark of `link` with `title` and `tags`, added and modified on
+    /// 2022-02-16, for tests.
+    pub(crate) fn sample(link: &str, title: &str, tags: &[&str]) -> Self {
+        Self {
+            link: link.to_owned(),
+            added_at: 1645000000,
+            last_modified: 1645000100,
+            metadata: SiteMetadata {
+                title: title.to_owned(),
+                ..Default::default()
+            },
+            tags: TagList(tags.iter().map(|tag| tag.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+}
+
 impl Display for Bookmark {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} - {} [{}]", self.metadata.title, self.link, self.tags,)
@@ -0,0 +1,168 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use eyre::{eyre, Result, WrapErr};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::{
+    archive, fetch, pool,
+    refresh::{HostLimiter, RefreshOptions},
+};
+
+/// Size in bytes the asset cache is trimmed to unless the caller picks another.
+pub const DEFAULT_MAX_SIZE: u64 = 200 * 1024 * 1024;
+
+/// What a cached asset is to the bookmark that uses it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    Favicon,
+    Image,
+}
+
+impl AssetKind {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            AssetKind::Favicon => "favicon",
+            AssetKind::Image => "image",
+        }
+    }
+}
+
+/// A downloaded favicon or preview image.
+///
+/// Files are named after the SHA-256 of their contents, so an icon shared by
+/// many pages of a site is stored once.
+#[derive(Debug, Clone)]
+pub struct Asset {
+    /// SHA-256 of the contents, in hex
+    pub hash: String,
+    pub path: PathBuf,
+    pub content_type: Option<String>,
+    /// Size in bytes
+    pub size: u64,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CacheStats {
+    pub assets: usize,
+    /// Total size in bytes
+    pub size: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct CacheReport {
+    /// URLs downloaded into the cache
+    pub downloaded: usize,
+    /// Assets of bookmarks found in the cache without downloading them again
+    pub reused: usize,
+    /// URLs that couldn't be downloaded, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Eviction {
+    pub removed: usize,
+    /// Bytes freed
+    pub freed: u64,
+}
+
+/// Downloads `urls` into `dir` on `options.parallelism` threads, calling
+/// `on_result` on the calling thread with each URL's index as soon as it has
+/// been downloaded.
+pub(crate) fn download_concurrently<F>(
+    urls: &[String],
+    dir: &Path,
+    options: &RefreshOptions,
+    on_result: F,
+) where
+    F: FnMut(usize, Result<Asset>),
+{
+    let limiter = HostLimiter::new(options.host_delay);
+    let timeout = options.timeout;
+    let dir = dir.to_owned();
+    pool::run_concurrently(
+        urls.to_vec(),
+        options.parallelism,
+        move |url| {
+            limiter.wait(&url);
+            download(&url, &dir, timeout)
+        },
+        on_result,
+    );
+}
+
+/// Downloads the image at `url` into `dir`.
+fn download(url: &str, dir: &Path, timeout: Duration) -> Result<Asset> {
+    let response = fetch::fetch_with_timeout(url, timeout).wrap_err("Couldn't download")?;
+    if response.status >= 400 {
+        return Err(eyre!("{} returned HTTP {}", url, response.status));
+    }
+    // Some servers don't label icons at all
+    let extension = match response.content_type.as_deref() {
+        Some(content_type) if content_type.starts_with("image/") => {
+            archive::extension_for(content_type)
+        }
+        Some("application/octet-stream") | None => extension_from_url(url),
+        Some(content_type) => return Err(eyre!("{} is {}, not an image", url, content_type)),
+    };
+    if response.body.is_empty() {
+        return Err(eyre!("{} is empty", url));
+    }
+
+    let hash = format!("{:x}", Sha256::digest(&response.body));
+    fs::create_dir_all(dir).wrap_err_with(|| format!("Couldn't create {}", dir.display()))?;
+    let path = dir.join(format!("{}.{}", hash, extension));
+    if !path.exists() {
+        fs::write(&path, &response.body)
+            .wrap_err_with(|| format!("Couldn't write {}", path.display()))?;
+    }
+
+    Ok(Asset {
+        hash,
+        path,
+        content_type: response.content_type,
+        size: response.body.len() as u64,
+    })
+}
+
+fn extension_from_url(url: &str) -> &'static str {
+    let extension = Url::parse(url).ok().and_then(|url| {
+        Path::new(url.path())
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+    });
+    match extension.as_deref() {
+        Some("ico") => "ico",
+        Some("png") => "png",
+        Some("jpg") | Some("jpeg") => "jpg",
+        Some("gif") => "gif",
+        Some("webp") => "webp",
+        Some("svg") => "svg",
+        _ => "bin",
+    }
+}
+
+/// Contents of a cached file as a `data:` URI, for exports that should work
+/// offline.
+pub(crate) fn data_uri(path: &Path) -> Option<String> {
+    let content_type = match path.extension()?.to_str()? {
+        "ico" => "image/x-icon",
+        "png" => "image/png",
+        "jpg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        _ => "application/octet-stream",
+    };
+    let contents = fs::read(path).ok()?;
+    Some(format!(
+        "data:{};base64,{}",
+        content_type,
+        base64::encode(contents)
+    ))
+}
@@ -0,0 +1,241 @@
+use itertools::Itertools;
+use url::Url;
+
+/// Query parameters added by analytics and ad platforms. A trailing `*`
+/// matches any parameter starting with the rest of the name.
+pub const DEFAULT_TRACKING_PARAMS: &[&str] = &[
+    "utm_*", "fbclid", "gclid", "dclid", "msclkid", "yclid", "igshid", "mc_cid", "mc_eid",
+    "mkt_tok", "_hsenc", "_hsmi", "ref_src", "ref_url",
+];
+
+/// Bumped whenever [`UrlNormalizer::normalize`] changes how links are
+/// rewritten, so that stored canonical links are recomputed.
+const NORMALIZATION_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentHandling {
+    /// Keep `#fragment` as part of the link
+    Keep,
+    /// Drop `#fragment` from the link
+    Strip,
+    /// Drop `#fragment` unless it looks like a client side route, e.g. `#/inbox` or `#!/inbox`
+    StripUnlessRoute,
+}
+
+/// Rewrites links to a canonical form so that links that point to the same
+/// page compare equal, e.g. `http://www.example.com/?utm_source=x#top` and
+/// `https://example.com`.
+///
+/// Hosts are always lowercased and converted to punycode, and default ports
+/// are dropped. The remaining rules can be turned off individually. Links that
+/// can't be parsed are only trimmed.
+#[derive(Debug, Clone)]
+pub struct UrlNormalizer {
+    /// Treat `http` links as `https`
+    pub upgrade_scheme: bool,
+    /// Drop a leading `www.` from the host
+    pub strip_www: bool,
+    /// Drop the trailing `/` from the path
+    pub strip_trailing_slash: bool,
+    /// Drop query parameters listed in `tracking_params`
+    pub strip_tracking_params: bool,
+    /// Query parameters to drop. See [`DEFAULT_TRACKING_PARAMS`].
+    pub tracking_params: Vec<String>,
+    /// Sort the remaining query parameters by name
+    pub sort_query: bool,
+    /// What to do with `#fragment`
+    pub fragments: FragmentHandling,
+}
+
+impl Default for UrlNormalizer {
+    fn default() -> Self {
+        Self {
+            upgrade_scheme: true,
+            strip_www: true,
+            strip_trailing_slash: true,
+            strip_tracking_params: true,
+            tracking_params: DEFAULT_TRACKING_PARAMS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            sort_query: true,
+            fragments: FragmentHandling::StripUnlessRoute,
+        }
+    }
+}
+
+impl UrlNormalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn normalize(&self, link: &str) -> String {
+        let link = link.trim();
+        let mut url = match Url::parse(link) {
+            Ok(url) => url,
+            Err(_) => return link.to_owned(),
+        };
+
+        if !matches!(url.scheme(), "http" | "https") {
+            return url.to_string();
+        }
+
+        if self.upgrade_scheme && url.scheme() == "http" {
+            url.set_scheme("https").ok();
+            if url.port() == Some(443) {
+                url.set_port(None).ok();
+            }
+        }
+
+        if self.strip_www {
+            if let Some(host) = url.host_str().and_then(|h| h.strip_prefix("www.")) {
+                if host.contains('.') {
+                    let host = host.to_owned();
+                    url.set_host(Some(&host)).ok();
+                }
+            }
+        }
+
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .into_owned()
+            .filter(|(key, _)| !(self.strip_tracking_params && self.is_tracking_param(key)))
+            .collect();
+        let pairs = if self.sort_query {
+            pairs.into_iter().sorted_by(|a, b| a.0.cmp(&b.0)).collect()
+        } else {
+            pairs
+        };
+        if pairs.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+
+        let keep_fragment = match self.fragments {
+            FragmentHandling::Keep => true,
+            FragmentHandling::Strip => false,
+            FragmentHandling::StripUnlessRoute => url
+                .fragment()
+                .is_some_and(|f| f.starts_with('/') || f.starts_with("!/")),
+        };
+        if !keep_fragment || url.fragment() == Some("") {
+            url.set_fragment(None);
+        }
+
+        if self.strip_trailing_slash && url.path().len() > 1 && url.path().ends_with('/') {
+            let path = url.path().trim_end_matches('/').to_owned();
+            url.set_path(&path);
+        }
+
+        let mut normalized = url.to_string();
+        if self.strip_trailing_slash && url.path() == "/" && normalized.ends_with('/') {
+            normalized.pop();
+        }
+        normalized
+    }
+
+    /// Describes everything that affects the links [`normalize`](Self::normalize)
+    /// returns. Links normalized with the same fingerprint compare equal.
+    pub fn fingerprint(&self) -> String {
+        format!(
+            "v{};upgrade_scheme={};strip_www={};strip_trailing_slash={};strip_tracking_params={};tracking_params={};sort_query={};fragments={:?}",
+            NORMALIZATION_VERSION,
+            self.upgrade_scheme,
+            self.strip_www,
+            self.strip_trailing_slash,
+            self.strip_tracking_params,
+            self.tracking_params.join(","),
+            self.sort_query,
+            self.fragments
+        )
+    }
+
+    fn is_tracking_param(&self, key: &str) -> bool {
+        self.tracking_params
+            .iter()
+            .any(|param| match param.strip_suffix('*') {
+                Some(prefix) => key.starts_with(prefix),
+                None => key == param,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_links() {
+        let cases = [
+            // Tracking parameters
+            (
+                "https://example.com/a?utm_source=x&utm_medium=y&id=1",
+                "https://example.com/a?id=1",
+            ),
+            ("https://example.com/a?fbclid=abc", "https://example.com/a"),
+            (
+                "https://example.com/a?b=2&a=1",
+                "https://example.com/a?a=1&b=2",
+            ),
+            (
+                "https://example.com/a?utmost=1",
+                "https://example.com/a?utmost=1",
+            ),
+            // Trailing slashes
+            ("https://example.com/", "https://example.com"),
+            ("https://example.com/docs/", "https://example.com/docs"),
+            ("https://example.com/docs//", "https://example.com/docs"),
+            // Schemes and default ports
+            ("http://example.com/a", "https://example.com/a"),
+            ("http://example.com:80/a", "https://example.com/a"),
+            ("https://example.com:443/a", "https://example.com/a"),
+            ("http://example.com:443/a", "https://example.com/a"),
+            ("https://example.com:8443/a", "https://example.com:8443/a"),
+            // Hosts
+            ("https://www.example.com/a", "https://example.com/a"),
+            ("https://www.com/a", "https://www.com/a"),
+            ("https://EXAMPLE.com/A", "https://example.com/A"),
+            ("https://bücher.example/", "https://xn--bcher-kva.example"),
+            // Fragments
+            ("https://example.com/a#top", "https://example.com/a"),
+            ("https://example.com/a#", "https://example.com/a"),
+            ("https://example.com/#/inbox", "https://example.com/#/inbox"),
+            (
+                "https://example.com/#!/inbox",
+                "https://example.com/#!/inbox",
+            ),
+            // Other links
+            ("  not a link ", "not a link"),
+            ("mailto:someone@example.com", "mailto:someone@example.com"),
+        ];
+
+        let normalizer = UrlNormalizer::new();
+        for (link, expected) in cases {
+            assert_eq!(normalizer.normalize(link), expected, "{}", link);
+        }
+    }
+
+    #[test]
+    fn normalizes_with_rules_turned_off() {
+        let normalizer = UrlNormalizer {
+            upgrade_scheme: false,
+            strip_www: false,
+            strip_trailing_slash: false,
+            strip_tracking_params: false,
+            tracking_params: Vec::new(),
+            sort_query: false,
+            fragments: FragmentHandling::Keep,
+        };
+        let cases = [
+            (
+                "http://www.example.com:80/a/?utm_source=x&b=2&a=1#top",
+                "http://www.example.com/a/?utm_source=x&b=2&a=1#top",
+            ),
+            ("https://example.com", "https://example.com/"),
+        ];
+        for (link, expected) in cases {
+            assert_eq!(normalizer.normalize(link), expected, "{}", link);
+        }
+    }
+}
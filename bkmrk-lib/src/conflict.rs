@@ -0,0 +1,41 @@
+use std::fmt::Display;
+
+/// What to do when an imported bookmark's link is already in the database.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ConflictStrategy {
+    /// Keep the existing bookmark untouched
+    #[default]
+    Skip,
+    /// Replace the existing bookmark's details and tags with the imported ones
+    Overwrite,
+    /// Add the imported bookmark's tags to the existing bookmark
+    MergeTags,
+    /// Overwrite only if the imported bookmark was modified more recently
+    KeepNewer,
+}
+
+#[derive(Debug, Clone)]
+pub enum ImportOutcome {
+    /// Added as a new bookmark
+    Added,
+    /// Its tags were added to an existing bookmark with the same link
+    Merged,
+    /// Replaced the details and tags of an existing bookmark with the same link
+    Overwritten,
+    /// Left out because a bookmark with the same link exists
+    Skipped,
+    /// Couldn't be imported
+    Errored(String),
+}
+
+impl Display for ImportOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportOutcome::Added => write!(f, "added"),
+            ImportOutcome::Merged => write!(f, "merged"),
+            ImportOutcome::Overwritten => write!(f, "overwritten"),
+            ImportOutcome::Skipped => write!(f, "skipped"),
+            ImportOutcome::Errored(_) => write!(f, "errored"),
+        }
+    }
+}
@@ -1,77 +1,160 @@
-use std::{collections::HashSet, fs::File, path::Path, rc::Rc};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use chrono::Utc;
-use eyre::{Result, WrapErr};
+use eyre::{eyre, Result, WrapErr};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use itertools::Itertools;
 use log::{error, info};
 use nanoid::nanoid;
-use rusqlite::{params, types::Value, Connection};
+use rusqlite::{
+    functions::FunctionFlags, params, params_from_iter, types::Value, Connection,
+    OptionalExtension, Row,
+};
 
 use crate::{
+    archive::Archive,
     bookmark::{Bookmark, TagList},
+    cache::{Asset, AssetKind, CacheStats},
+    canonical::UrlNormalizer,
+    conflict::{ConflictStrategy, ImportOutcome},
+    link_check::LinkStatus,
+    migrations,
+    query::BookmarkQuery,
+    search::{SearchResult, HIGHLIGHT_END, HIGHLIGHT_START},
     site_metadata::{SiteMetadata, SiteType},
+    tags::{self, TagChange, TagEdit, TagInfo, TagNormalizer},
+    utils,
 };
 
+/// Columns read by [`Database::bookmark_from_row`], over `Bookmark AS b` and
+/// `Metadata AS m`. Cached assets are only read while they match the current
+/// favicon and image URLs.
+const BOOKMARK_COLUMNS: &str = concat!(
+    "b.id, b.link, b.added_at, b.last_modified, m.title, m.description, m.image_url, m.site_type, m.favicon_url, m.canonical_url, m.site_name, m.author, m.language, m.published_at, m.modified_at, m.keywords, m.feeds, m.extra, ",
+    "(SELECT a.path FROM `BookmarkAsset` ba JOIN `Asset` a ON a.hash = ba.hash WHERE ba.bookmark_id = b.id AND ba.kind = 'favicon' AND ba.url = m.favicon_url), ",
+    "(SELECT a.path FROM `BookmarkAsset` ba JOIN `Asset` a ON a.hash = ba.hash WHERE ba.bookmark_id = b.id AND ba.kind = 'image' AND ba.url = m.image_url)"
+);
+const BOOKMARK_COLUMN_COUNT: usize = 20;
+
 #[derive(Debug)]
 pub struct Database {
     conn: Connection,
+    tag_normalizer: TagNormalizer,
 }
 
 impl Database {
-    pub fn create_tables(&self) -> Result<()> {
-        let query = "
-            CREATE TABLE IF NOT EXISTS `Bookmark`(
-                id CHAR(6) PRIMARY KEY,
-                link VARCHAR(300) NOT NULL UNIQUE,
-                added_at DATETIME NOT NULL,
-                last_modified DATETIME NOT NULL
-            );
+    pub fn connect(
+        path: &Path,
+        normalizer: UrlNormalizer,
+        tag_normalizer: TagNormalizer,
+    ) -> Result<Self> {
+        let mut conn = Connection::open(path).wrap_err("Couldn't connect to db")?;
+        let fingerprint = normalizer.fingerprint();
+        register_functions(&conn, normalizer).wrap_err("Couldn't register database functions")?;
+        conn.pragma_update(None, "foreign_keys", false)?;
+        migrations::migrate(&mut conn).wrap_err("Couldn't update database schema")?;
+        conn.pragma_update(None, "foreign_keys", true)
+            .wrap_err("Couldn't enable foreign keys")?;
 
-            CREATE TABLE IF NOT EXISTS `Tag`(
-                bookmark_id NOT NULL,
-                tag VARCHAR(100),
-                FOREIGN KEY (bookmark_id) REFERENCES `Bookmark`(id)
-            );
+        let db = Self {
+            conn,
+            tag_normalizer,
+        };
+        db.refresh_canonical_links(&fingerprint)
+            .wrap_err("Couldn't update canonical links")?;
+        Ok(db)
+    }
+
+    /// Recomputes canonical links if they were stored with different
+    /// normalizer settings, described by `fingerprint`.
+    fn refresh_canonical_links(&self, fingerprint: &str) -> Result<()> {
+        let stored: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT value FROM `Setting` WHERE key = 'url_normalizer'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if stored.as_deref() == Some(fingerprint) {
+            return Ok(());
+        }
 
-            CREATE TABLE IF NOT EXISTS `Metadata`(
-                bookmark_id NOT NULL,
-                title VARCHAR(300) NOT NULL,
-                description TEXT,
-                image_url TEXT,
-                site_type VARCHAR(20),
-                FOREIGN KEY (bookmark_id) REFERENCES `Bookmark`(id)
-            );";
-        self.conn.execute_batch(query)?;
+        let count = self.atomically(|| {
+            let mut count = self.conn.execute(
+                "UPDATE `Bookmark` SET canonical_link = canonical_url(link) WHERE canonical_link IS NOT canonical_url(link)",
+                [],
+            )?;
+            count += self.conn.execute(
+                "UPDATE `Bookmark` SET page_canonical_link = (SELECT canonical_url(canonical_url) FROM `Metadata` WHERE bookmark_id = id)
+                WHERE page_canonical_link IS NOT (SELECT canonical_url(canonical_url) FROM `Metadata` WHERE bookmark_id = id)",
+                [],
+            )?;
+            self.conn.execute(
+                "INSERT OR REPLACE INTO `Setting` (key, value) VALUES ('url_normalizer', ?1)",
+                [fingerprint],
+            )?;
+            Ok(count)
+        })?;
+        if count > 0 {
+            info!("Updated {} canonical links", count);
+        }
         Ok(())
     }
 
-    pub fn connect(path: &Path) -> Result<Self> {
-        let mut new_db = false;
-        if !path.exists() {
-            new_db = true;
-            File::create(path)
-                .wrap_err_with(|| format!("Couldn't create database file at {}", path.display()))?;
+    /// Runs `f` in a savepoint, undoing all of its changes if it fails.
+    ///
+    /// Outside a transaction this behaves like a transaction of its own. Inside
+    /// one, only the changes made by `f` are rolled back, so bulk operations can
+    /// skip failed items without losing the rest.
+    fn atomically<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.conn.execute_batch("SAVEPOINT bkmrk_op")?;
+        match f() {
+            Ok(value) => {
+                self.conn.execute_batch("RELEASE bkmrk_op")?;
+                Ok(value)
+            }
+            Err(e) => {
+                self.conn
+                    .execute_batch("ROLLBACK TO bkmrk_op; RELEASE bkmrk_op")?;
+                Err(e)
+            }
         }
-        let conn = Connection::open(path).wrap_err_with(|| format!("Couldn't connect to db"))?;
-        let db = Self { conn };
+    }
 
-        if new_db {
-            db.create_tables()
-                .wrap_err_with(|| format!("Couldn't create database tables"))?;
-        }
-        Ok(db)
+    /// Adds a bookmark, along with the readable text of its page if given.
+    pub fn add_one(&self, bookmark: &Bookmark, content: Option<&str>) -> Result<()> {
+        self.atomically(|| {
+            let id = self.insert(bookmark)?;
+            if let Some(text) = content {
+                self.save_content(&id, text)?;
+            }
+            Ok(())
+        })
     }
 
-    pub fn add_one(&self, bookmark: &Bookmark) -> Result<()> {
-        let id = nanoid!(
-            6,
-            &['1', '2', '3', '4', '5', '6', '7', '8', '9', '0', 'a', 'b', 'c', 'd', 'e', 'f']
-        );
+    /// Adds a bookmark, returning its id.
+    fn insert(&self, bookmark: &Bookmark) -> Result<String> {
+        if let Some(existing) = self.find_saved(bookmark)? {
+            return Err(eyre!(
+                "\"{}\" is already saved as \"{}\"",
+                bookmark.link,
+                existing.link
+            ));
+        }
+
+        let id = self.new_id()?;
         self.conn.execute(
-            "INSERT INTO `Bookmark` (id, link, added_at, last_modified) VALUES (?1, ?2, ?3, ?4)",
+            "INSERT INTO `Bookmark` (id, link, canonical_link, added_at, last_modified) VALUES (?1, ?2, canonical_url(?2), ?3, ?4)",
             params![id, bookmark.link, bookmark.added_at, bookmark.last_modified],
         )?;
 
-        for tag in &bookmark.tags.0 {
+        for tag in self.normalize_tags(&bookmark.tags.0)? {
             self.conn.execute(
                 "INSERT INTO `Tag` (bookmark_id, tag) VALUES (?1, ?2)",
                 params![id, tag],
@@ -82,220 +165,691 @@ impl Database {
             "INSERT INTO `Metadata` (bookmark_id, title, description, image_url, site_type) VALUES (?1, ?2, ?3, ?4, ?5)",
             params![id, bookmark.metadata.title, bookmark.metadata.description, bookmark.metadata.image_url, bookmark.metadata.site_type.to_string()],
         )?;
+        self.set_details(&id, &bookmark.metadata)?;
+
+        Ok(id)
+    }
 
+    /// Stores the metadata other than title, description, image and type.
+    fn set_details(&self, bookmark_id: &str, metadata: &SiteMetadata) -> Result<()> {
+        self.conn.execute(
+            "UPDATE `Metadata` SET favicon_url = ?1, canonical_url = ?2, site_name = ?3, author = ?4, language = ?5, published_at = ?6, modified_at = ?7, keywords = ?8, feeds = ?9, extra = ?10 WHERE bookmark_id = ?11",
+            params![
+                metadata.favicon_url,
+                metadata.canonical_url,
+                metadata.site_name,
+                metadata.author,
+                metadata.language,
+                metadata.published_at,
+                metadata.modified_at,
+                serde_json::to_string(&metadata.keywords)?,
+                serde_json::to_string(&metadata.feeds)?,
+                serde_json::to_string(&metadata.extra)?,
+                bookmark_id
+            ],
+        )?;
+        self.conn.execute(
+            "UPDATE `Bookmark` SET page_canonical_link = canonical_url(?1) WHERE id = ?2",
+            params![metadata.canonical_url, bookmark_id],
+        )?;
         Ok(())
     }
 
-    pub fn add_many(&self, bookmarks: &[Bookmark]) -> Result<(usize, usize)> {
-        let (mut succeeded, mut failed) = (0, 0);
-        for bookmark in bookmarks {
-            match self.add_one(bookmark) {
-                Ok(_) => {
-                    info!("Added bookmark '{}'", bookmark.metadata.title);
-                    succeeded += 1;
-                }
-                Err(e) => {
-                    error!("Couldn't add \"{}\": {}", bookmark.metadata.title, e);
-                    failed += 1;
-                }
-            };
+    /// Generates a bookmark id that isn't taken yet. There are about 16
+    /// million ids, so the loop only retries the rare collision.
+    fn new_id(&self) -> Result<String> {
+        loop {
+            let id = nanoid!(
+                6,
+                &['1', '2', '3', '4', '5', '6', '7', '8', '9', '0', 'a', 'b', 'c', 'd', 'e', 'f']
+            );
+            let taken: bool = self.conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM `Bookmark` WHERE id = ?1)",
+                [&id],
+                |row| row.get(0),
+            )?;
+            if !taken {
+                return Ok(id);
+            }
         }
-
-        Ok((succeeded, failed))
     }
 
-    pub fn delete_one(&self, bookmark_id: &str) -> Result<()> {
-        self.conn
-            .execute("DELETE FROM `Tag` WHERE bookmark_id LIKE ?1", [bookmark_id])
-            .wrap_err("Couldn't delete related tags")?;
-
-        self.conn
-            .execute(
-                "DELETE FROM `Metadata` WHERE bookmark_id LIKE ?1",
-                [bookmark_id],
-            )
-            .wrap_err("Couldn't delete bookmark metadata")?;
-
-        self.conn
-            .execute("DELETE FROM `Bookmark` WHERE id LIKE ?1", [bookmark_id])
-            .wrap_err("Couldn't delete bookmark")?;
+    pub fn add_many(
+        &self,
+        bookmarks: &[Bookmark],
+        strategy: ConflictStrategy,
+    ) -> Result<Vec<ImportOutcome>> {
+        self.atomically(|| {
+            let mut outcomes = Vec::with_capacity(bookmarks.len());
+            for bookmark in bookmarks {
+                let outcome = match self.atomically(|| self.import_one(bookmark, strategy)) {
+                    Ok(outcome) => {
+                        info!("{} bookmark '{}'", outcome, bookmark.metadata.title);
+                        outcome
+                    }
+                    Err(e) => {
+                        error!("Couldn't add \"{}\": {}", bookmark.metadata.title, e);
+                        ImportOutcome::Errored(e.to_string())
+                    }
+                };
+                outcomes.push(outcome);
+            }
 
-        Ok(())
+            Ok(outcomes)
+        })
     }
 
-    pub fn delete_many(&self, bookmarks: &[Bookmark]) -> Result<(usize, usize)> {
-        let (mut succeeded, mut failed) = (0, 0);
-        for bookmark in bookmarks {
-            match self.delete_one(&bookmark.id) {
-                Ok(_) => {
-                    info!("Deleted {}", bookmark.metadata.title);
-                    succeeded += 1;
-                }
-                Err(e) => {
-                    error!(
-                        "ERROR: Failed to delete bookmark: \"{}\"\n{}",
-                        bookmark.metadata.title, e
-                    );
-                    failed += 1;
+    fn import_one(&self, bookmark: &Bookmark, strategy: ConflictStrategy) -> Result<ImportOutcome> {
+        let existing = match self.find_saved(bookmark)? {
+            Some(existing) => existing,
+            None => {
+                self.insert(bookmark)?;
+                return Ok(ImportOutcome::Added);
+            }
+        };
+
+        match strategy {
+            ConflictStrategy::Skip => Ok(ImportOutcome::Skipped),
+            ConflictStrategy::KeepNewer if bookmark.last_modified <= existing.last_modified => {
+                Ok(ImportOutcome::Skipped)
+            }
+            ConflictStrategy::Overwrite | ConflictStrategy::KeepNewer => {
+                self.overwrite(&existing, bookmark)?;
+                Ok(ImportOutcome::Overwritten)
+            }
+            ConflictStrategy::MergeTags => {
+                let imported_tags = self.normalize_tags(&bookmark.tags.0)?;
+                let merged_tags = existing
+                    .tags
+                    .0
+                    .iter()
+                    .chain(imported_tags.iter())
+                    .unique()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if merged_tags.len() == existing.tags.0.len() {
+                    return Ok(ImportOutcome::Skipped);
                 }
+                self.replace_tags(&existing, &merged_tags)?;
+                Ok(ImportOutcome::Merged)
             }
         }
-        Ok((succeeded, failed))
     }
 
-    pub fn get_all(&self) -> Result<Vec<Bookmark>> {
-        let mut select_statement = self
-            .conn
-            .prepare("SELECT b.id, b.link, b.added_at, b.last_modified, m.title, m.description, m.image_url, m.site_type FROM `Bookmark` AS b, `Metadata` AS m WHERE b.id = m.bookmark_id")?;
-        let matches: Vec<Bookmark> = select_statement
-            .query_map([], |row| {
-                let id: String = row.get_unwrap(0);
-                let item = Bookmark {
-                    id: row.get_unwrap(0),
-                    link: row.get_unwrap(1),
-                    added_at: row.get_unwrap(2),
-                    last_modified: row.get_unwrap(3),
-                    metadata: SiteMetadata {
-                        title: row.get_unwrap(4),
-                        description: row.get_unwrap(5),
-                        image_url: row.get_unwrap(6),
-                        site_type: SiteType::from(&row.get_unwrap::<usize, String>(7)),
-                        ..Default::default()
-                    },
-                    tags: self
-                        .get_tags(&id)
-                        .map_err(|_| rusqlite::Error::QueryReturnedNoRows)?,
-                };
-                Ok(item)
-            })?
-            .collect::<Result<Vec<Bookmark>, _>>()?;
+    /// Replaces the details and tags of `existing` with those of `bookmark`.
+    fn overwrite(&self, existing: &Bookmark, bookmark: &Bookmark) -> Result<()> {
+        self.conn.execute(
+            "UPDATE `Metadata` SET title = ?1, description = ?2, image_url = ?3, site_type = ?4 WHERE bookmark_id = ?5",
+            params![
+                bookmark.metadata.title,
+                bookmark.metadata.description,
+                bookmark.metadata.image_url,
+                bookmark.metadata.site_type.to_string(),
+                existing.id
+            ],
+        )?;
+        self.set_details(&existing.id, &bookmark.metadata)?;
 
-        Ok(matches)
+        self.conn.execute(
+            "UPDATE `Bookmark` SET added_at = ?1, last_modified = ?2 WHERE id = ?3",
+            params![bookmark.added_at, bookmark.last_modified, existing.id],
+        )?;
+
+        self.replace_tags(existing, &bookmark.tags.0)?;
+
+        Ok(())
     }
 
-    pub fn get(&self, tags: &[String], domains: &[String]) -> Result<Vec<Bookmark>> {
-        let mut select_statement: String = "SELECT b.id, b.link, b.added_at, b.last_modified, m.title, m.description, m.image_url, m.site_type FROM `Bookmark` AS b, `Metadata` AS m WHERE b.id = m.bookmark_id AND ".into();
+    /// Finds a saved bookmark for the same page as `bookmark`: one whose link
+    /// or declared canonical URL has the same canonical form as the link or
+    /// declared canonical URL of `bookmark`.
+    fn find_saved(&self, bookmark: &Bookmark) -> Result<Option<Bookmark>> {
+        let mut select_statement = self.conn.prepare(&format!(
+            "SELECT {} FROM `Bookmark` AS b, `Metadata` AS m WHERE b.id = m.bookmark_id
+            AND (b.canonical_link IN (canonical_url(?1), canonical_url(?2)) OR b.page_canonical_link IN (canonical_url(?1), canonical_url(?2)))",
+            BOOKMARK_COLUMNS
+        ))?;
+        let bookmark = select_statement
+            .query_map(
+                params![bookmark.link, bookmark.metadata.canonical_url],
+                |row| self.bookmark_from_row(row),
+            )?
+            .next()
+            .transpose()?;
+        Ok(bookmark)
+    }
 
-        if !tags.is_empty() {
-            select_statement += &format!(
-                "id in (SELECT DISTINCT bookmark_id FROM `Tag` WHERE tag in ({}))",
-                tags.iter()
-                    .map(|x| format!("'{}'", x))
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            );
-        }
-        if !tags.is_empty() && !domains.is_empty() {
-            select_statement += " AND ";
-        }
-        if !domains.is_empty() {
-            let domains = domains
-                .iter()
-                .map(|x| format!("link LIKE '%{}%'", x))
-                .collect::<Vec<String>>();
-            select_statement += &format!("({})", domains.join(" OR "));
-        }
+    pub fn save_link_check(&self, bookmark_id: &str, status: &LinkStatus) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO `LinkCheck` (bookmark_id, status, final_url, error, checked_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                bookmark_id,
+                status.status,
+                status.final_url,
+                status.error,
+                status.checked_at
+            ],
+        )?;
+        Ok(())
+    }
 
-        let mut select_statement = self.conn.prepare(&select_statement)?;
+    /// Bookmarks matching `query` that have been checked, with their latest
+    /// check result.
+    pub fn link_checks(&self, query: &BookmarkQuery) -> Result<Vec<(Bookmark, LinkStatus)>> {
+        let (conditions, values) = self.compile(query)?;
+        let mut select_statement = self.conn.prepare(&format!(
+            "SELECT {}, c.status, c.final_url, c.error, c.checked_at FROM `Bookmark` AS b, `Metadata` AS m, `LinkCheck` AS c WHERE b.id = m.bookmark_id AND b.id = c.bookmark_id{}",
+            BOOKMARK_COLUMNS, conditions
+        ))?;
 
-        let matches: Vec<Bookmark> = select_statement
-            .query_map([], |row| {
-                let id: String = row.get_unwrap(0);
-                let item = Bookmark {
-                    id: row.get_unwrap(0),
-                    link: row.get_unwrap(1),
-                    added_at: row.get_unwrap(2),
-                    last_modified: row.get_unwrap(3),
-                    metadata: SiteMetadata {
-                        title: row.get_unwrap(4),
-                        description: row.get_unwrap(5),
-                        image_url: row.get_unwrap(6),
-                        site_type: SiteType::from(&row.get_unwrap::<usize, String>(7)),
-                        ..Default::default()
+        let checks = select_statement
+            .query_map(params_from_iter(values), |row| {
+                Ok((
+                    self.bookmark_from_row(row)?,
+                    LinkStatus {
+                        status: row.get(BOOKMARK_COLUMN_COUNT)?,
+                        final_url: row.get(BOOKMARK_COLUMN_COUNT + 1)?,
+                        error: row.get(BOOKMARK_COLUMN_COUNT + 2)?,
+                        checked_at: row.get(BOOKMARK_COLUMN_COUNT + 3)?,
                     },
-                    tags: self
-                        .get_tags(&id)
-                        .map_err(|_| rusqlite::Error::QueryReturnedNoRows)?,
-                };
-                Ok(item)
-            })
-            .unwrap()
-            .map(|x| x.unwrap())
-            .collect();
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
 
-        Ok(matches)
+        Ok(checks)
     }
 
-    pub fn update_name(&self, bookmark: &Bookmark, new_name: &str) -> Result<()> {
+    pub fn add_archive(&self, bookmark_id: &str, archive: &Archive) -> Result<()> {
         self.conn.execute(
-            "UPDATE `Metadata` SET title = ?1 WHERE bookmark_id LIKE ?2;",
-            [new_name, &bookmark.id],
+            "INSERT INTO `Archive` (bookmark_id, path, archived_at) VALUES (?1, ?2, ?3)",
+            params![
+                bookmark_id,
+                archive.path.to_string_lossy(),
+                archive.archived_at
+            ],
         )?;
+        Ok(())
+    }
 
-        self.conn.execute(
-            "UPDATE `Bookmark` SET last_modified = ?1 WHERE id LIKE ?2;",
-            [&Utc::now().timestamp().to_string(), &bookmark.id],
+    /// Saved copies of a bookmark, newest first.
+    pub fn archives(&self, bookmark_id: &str) -> Result<Vec<Archive>> {
+        let mut select_statement = self.conn.prepare(
+            "SELECT path, archived_at FROM `Archive` WHERE bookmark_id = ?1 ORDER BY archived_at DESC",
         )?;
+        let archives = select_statement
+            .query_map([bookmark_id], |row| {
+                Ok(Archive {
+                    path: PathBuf::from(row.get::<usize, String>(0)?),
+                    archived_at: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(archives)
+    }
 
-        Ok(())
+    /// URL and hash of the asset of `kind` cached for a bookmark.
+    pub fn bookmark_asset(
+        &self,
+        bookmark_id: &str,
+        kind: AssetKind,
+    ) -> Result<Option<(String, String)>> {
+        let asset = self
+            .conn
+            .query_row(
+                "SELECT url, hash FROM `BookmarkAsset` WHERE bookmark_id = ?1 AND kind = ?2",
+                [bookmark_id, kind.as_str()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(asset)
     }
 
-    pub fn update_link(&self, bookmark: &Bookmark, new_link: &str) -> Result<()> {
-        let query = "UPDATE `Bookmark` SET link = ?1, last_modified = ?2 WHERE id LIKE ?3;";
+    /// An asset downloaded from `url` for any bookmark.
+    pub fn asset_for_url(&self, url: &str) -> Result<Option<Asset>> {
+        let asset = self
+            .conn
+            .query_row(
+                "SELECT a.hash, a.path, a.content_type, a.size FROM `BookmarkAsset` ba JOIN `Asset` a ON a.hash = ba.hash WHERE ba.url = ?1 LIMIT 1",
+                [url],
+                asset_from_row,
+            )
+            .optional()?;
+        Ok(asset)
+    }
 
+    pub fn asset(&self, hash: &str) -> Result<Option<Asset>> {
+        let asset = self
+            .conn
+            .query_row(
+                "SELECT hash, path, content_type, size FROM `Asset` WHERE hash = ?1",
+                [hash],
+                asset_from_row,
+            )
+            .optional()?;
+        Ok(asset)
+    }
+
+    pub fn add_asset(&self, asset: &Asset) -> Result<()> {
         self.conn.execute(
-            query,
-            [new_link, &Utc::now().timestamp().to_string(), &bookmark.id],
+            "INSERT INTO `Asset` (hash, path, content_type, size, last_used) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                asset.hash,
+                asset.path.to_string_lossy(),
+                asset.content_type,
+                asset.size,
+                Utc::now().timestamp()
+            ],
         )?;
-
         Ok(())
     }
 
-    pub fn update_descr(&self, bookmark: &Bookmark, new_description: &str) -> Result<()> {
+    /// Makes the asset with `hash` the bookmark's asset of `kind`.
+    pub fn link_asset(
+        &self,
+        bookmark_id: &str,
+        kind: AssetKind,
+        url: &str,
+        hash: &str,
+    ) -> Result<()> {
         self.conn.execute(
-            "UPDATE `Metadata` SET description = ?1 WHERE bookmark_id LIKE ?2;",
-            [new_description, &bookmark.id],
+            "INSERT OR REPLACE INTO `BookmarkAsset` (bookmark_id, kind, url, hash) VALUES (?1, ?2, ?3, ?4)",
+            params![bookmark_id, kind.as_str(), url, hash],
         )?;
+        self.touch_asset(hash)
+    }
 
+    /// Marks an asset as used now, so it's evicted after those used before.
+    pub fn touch_asset(&self, hash: &str) -> Result<()> {
         self.conn.execute(
-            "UPDATE `Bookmark` SET last_modified = ?1 WHERE id LIKE ?2;",
-            [&Utc::now().timestamp().to_string(), &bookmark.id],
+            "UPDATE `Asset` SET last_used = ?1 WHERE hash = ?2",
+            params![Utc::now().timestamp(), hash],
+        )?;
+        Ok(())
+    }
+
+    /// All cached assets in eviction order: those no bookmark uses any more
+    /// first, then the least recently used, larger files first. Each comes
+    /// with whether a bookmark uses it.
+    pub fn assets_by_last_use(&self) -> Result<Vec<(Asset, bool)>> {
+        let mut select_statement = self.conn.prepare(
+            "SELECT a.hash, a.path, a.content_type, a.size, EXISTS (SELECT 1 FROM `BookmarkAsset` ba WHERE ba.hash = a.hash) AS used
+            FROM `Asset` a ORDER BY used, a.last_used, a.size DESC",
         )?;
+        let assets = select_statement
+            .query_map([], |row| Ok((asset_from_row(row)?, row.get(4)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(assets)
+    }
 
+    /// Removes an asset and its links to bookmarks. The file is left to the
+    /// caller.
+    pub fn delete_asset(&self, hash: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM `Asset` WHERE hash = ?1", [hash])?;
         Ok(())
     }
 
-    pub fn update_image_url(&self, bookmark: &Bookmark, new_image_url: &str) -> Result<()> {
-        self.conn.execute(
-            "UPDATE `Metadata` SET image_url = ?1 WHERE bookmark_id LIKE ?2;",
-            [new_image_url, &bookmark.id],
+    pub fn cache_stats(&self) -> Result<CacheStats> {
+        let stats = self.conn.query_row(
+            "SELECT count(*), coalesce(sum(size), 0) FROM `Asset`",
+            [],
+            |row| {
+                Ok(CacheStats {
+                    assets: row.get(0)?,
+                    size: row.get(1)?,
+                })
+            },
         )?;
+        Ok(stats)
+    }
+
+    /// Stores the readable text of a bookmark's page and indexes it for search.
+    pub fn save_content(&self, bookmark_id: &str, text: &str) -> Result<()> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text.as_bytes())?;
+        let compressed = encoder.finish()?;
 
         self.conn.execute(
-            "UPDATE `Bookmark` SET last_modified = ?1 WHERE id LIKE ?2;",
-            [&Utc::now().timestamp().to_string(), &bookmark.id],
+            "INSERT INTO `Content` (bookmark_id, text, fetched_at) VALUES (?1, ?2, ?3)
+            ON CONFLICT (bookmark_id) DO UPDATE SET text = excluded.text, fetched_at = excluded.fetched_at",
+            params![bookmark_id, compressed, Utc::now().timestamp()],
         )?;
-
         Ok(())
     }
 
-    pub fn update_site_type(&self, bookmark: &Bookmark, new_site_type: &str) -> Result<()> {
-        self.conn.execute(
-            "UPDATE `Metadata` SET site_type = ?1 WHERE bookmark_id LIKE ?2;",
-            [new_site_type, &bookmark.id],
-        )?;
+    /// The readable text of a bookmark's page, if it has been fetched.
+    pub fn content(&self, bookmark_id: &str) -> Result<Option<String>> {
+        let compressed: Option<Vec<u8>> = self
+            .conn
+            .query_row(
+                "SELECT text FROM `Content` WHERE bookmark_id = ?1",
+                [bookmark_id],
+                |row| row.get(0),
+            )
+            .optional()?;
 
-        self.conn.execute(
-            "UPDATE `Bookmark` SET last_modified = ?1 WHERE id LIKE ?2;",
-            [&Utc::now().timestamp().to_string(), &bookmark.id],
+        compressed
+            .map(|compressed| decompress(&compressed).wrap_err("Couldn't decompress page content"))
+            .transpose()
+    }
+
+    /// Groups of bookmarks for the same page, oldest first. Bookmarks are for
+    /// the same page if their links have the same canonical form, or if one's
+    /// link or declared canonical URL matches the other's declared canonical
+    /// URL.
+    pub fn duplicates(&self) -> Result<Vec<(String, Vec<Bookmark>)>> {
+        let mut link_statement = self.conn.prepare(
+            "SELECT id, canonical_link, page_canonical_link FROM `Bookmark` ORDER BY added_at",
         )?;
+        let rows = link_statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<usize, String>(0)?,
+                    row.get::<usize, Option<String>>(1)?,
+                    row.get::<usize, Option<String>>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        // Union-find over bookmarks, joining those that share a link
+        let mut parents: Vec<usize> = (0..rows.len()).collect();
+        fn root(parents: &mut [usize], mut idx: usize) -> usize {
+            while parents[idx] != idx {
+                parents[idx] = parents[parents[idx]];
+                idx = parents[idx];
+            }
+            idx
+        }
+        let mut owners: HashMap<&str, usize> = HashMap::new();
+        for (idx, (_, link, page_link)) in rows.iter().enumerate() {
+            for key in [link, page_link].into_iter().flatten() {
+                match owners.get(key.as_str()) {
+                    Some(&owner) => {
+                        let (a, b) = (root(&mut parents, owner), root(&mut parents, idx));
+                        // Keep the oldest bookmark as the root
+                        parents[a.max(b)] = a.min(b);
+                    }
+                    None => {
+                        owners.insert(key, idx);
+                    }
+                }
+            }
+        }
+
+        let mut members: Vec<Vec<usize>> = vec![Vec::new(); rows.len()];
+        for idx in 0..rows.len() {
+            let root = root(&mut parents, idx);
+            members[root].push(idx);
+        }
+
+        let mut select_statement = self.conn.prepare(&format!(
+            "SELECT {} FROM `Bookmark` AS b, `Metadata` AS m WHERE b.id = m.bookmark_id AND b.id = ?1",
+            BOOKMARK_COLUMNS
+        ))?;
+        let mut groups = Vec::new();
+        for (root, group) in members.iter().enumerate() {
+            if group.len() < 2 {
+                continue;
+            }
+            let (_, link, page_link) = &rows[root];
+            let link = page_link
+                .to_owned()
+                .or_else(|| link.to_owned())
+                .unwrap_or_default();
+            let bookmarks = group
+                .iter()
+                .map(|&idx| {
+                    select_statement.query_row([&rows[idx].0], |row| self.bookmark_from_row(row))
+                })
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            groups.push((link, bookmarks));
+        }
+
+        Ok(groups)
+    }
+
+    /// Adds the tags of `duplicates` to `keep` and deletes them.
+    pub fn merge(&self, keep: &Bookmark, duplicates: &[Bookmark]) -> Result<()> {
+        self.atomically(|| {
+            let tags = keep
+                .tags
+                .0
+                .iter()
+                .chain(duplicates.iter().flat_map(|b| b.tags.0.iter()))
+                .unique()
+                .cloned()
+                .collect::<Vec<_>>();
+            self.replace_tags(keep, &tags)?;
+
+            for duplicate in duplicates.iter().filter(|b| b.id != keep.id) {
+                self.delete_one(&duplicate.id)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Deletes a bookmark. Its tags and metadata are removed along with it by
+    /// the `ON DELETE CASCADE` foreign keys.
+    pub fn delete_one(&self, bookmark_id: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM `Bookmark` WHERE id = ?1", [bookmark_id])
+            .wrap_err("Couldn't delete bookmark")?;
 
         Ok(())
     }
 
+    pub fn delete_many(&self, bookmarks: &[Bookmark]) -> Result<(usize, usize)> {
+        self.atomically(|| {
+            let (mut succeeded, mut failed) = (0, 0);
+            for bookmark in bookmarks {
+                match self.atomically(|| self.delete_one(&bookmark.id)) {
+                    Ok(_) => {
+                        info!("Deleted {}", bookmark.metadata.title);
+                        succeeded += 1;
+                    }
+                    Err(e) => {
+                        error!(
+                            "ERROR: Failed to delete bookmark: \"{}\"\n{}",
+                            bookmark.metadata.title, e
+                        );
+                        failed += 1;
+                    }
+                }
+            }
+            Ok((succeeded, failed))
+        })
+    }
+
+    pub fn get(&self, query: &BookmarkQuery) -> Result<Vec<Bookmark>> {
+        let (conditions, values) = self.compile(query)?;
+        let mut select_statement = self.conn.prepare(&format!(
+            "SELECT {} FROM `Bookmark` AS b, `Metadata` AS m WHERE b.id = m.bookmark_id{}",
+            BOOKMARK_COLUMNS, conditions
+        ))?;
+
+        let matches = select_statement
+            .query_map(params_from_iter(values), |row| self.bookmark_from_row(row))?
+            .collect::<Result<Vec<Bookmark>, _>>()?;
+
+        Ok(matches)
+    }
+
+    pub fn search(&self, query: &str, limit: Option<usize>) -> Result<Vec<SearchResult>> {
+        let mut select_statement = self.conn.prepare(&format!(
+            "SELECT {},
+                snippet(`BookmarkSearch`, -1, ?2, ?3, '…', 12),
+                bm25(`BookmarkSearch`, 0.0, 10.0, 4.0, 2.0, 6.0, 1.0) AS rank
+            FROM `BookmarkSearch` AS s, `Bookmark` AS b, `Metadata` AS m
+            WHERE `BookmarkSearch` MATCH ?1 AND s.rowid = b.seq AND b.id = m.bookmark_id
+            ORDER BY rank LIMIT ?4",
+            BOOKMARK_COLUMNS
+        ))?;
+        let limit = limit.map(|l| l as i64).unwrap_or(-1);
+
+        let results = select_statement
+            .query_map(
+                params![query, HIGHLIGHT_START, HIGHLIGHT_END, limit],
+                |row| {
+                    Ok(SearchResult {
+                        bookmark: self.bookmark_from_row(row)?,
+                        snippet: row.get(BOOKMARK_COLUMN_COUNT)?,
+                        rank: row.get(BOOKMARK_COLUMN_COUNT + 1)?,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()
+            .wrap_err("Invalid search query")?;
+
+        Ok(results)
+    }
+
+    /// Builds a bookmark from a row that starts with [`BOOKMARK_COLUMNS`].
+    fn bookmark_from_row(&self, row: &Row) -> rusqlite::Result<Bookmark> {
+        let id: String = row.get(0)?;
+        let json_list = |idx: usize| -> rusqlite::Result<Vec<String>> {
+            Ok(row
+                .get::<usize, Option<String>>(idx)?
+                .and_then(|list| serde_json::from_str(&list).ok())
+                .unwrap_or_default())
+        };
+        Ok(Bookmark {
+            link: row.get(1)?,
+            added_at: row.get(2)?,
+            last_modified: row.get(3)?,
+            metadata: SiteMetadata {
+                title: row.get(4)?,
+                description: row.get(5)?,
+                image_url: row.get(6)?,
+                site_type: SiteType::from(&row.get::<usize, String>(7)?),
+                favicon_url: row.get(8)?,
+                canonical_url: row.get(9)?,
+                site_name: row.get(10)?,
+                author: row.get(11)?,
+                language: row.get(12)?,
+                published_at: row.get(13)?,
+                modified_at: row.get(14)?,
+                keywords: json_list(15)?,
+                feeds: json_list(16)?,
+                extra: row
+                    .get::<usize, Option<String>>(17)?
+                    .and_then(|extra| serde_json::from_str(&extra).ok())
+                    .unwrap_or_default(),
+            },
+            favicon_path: row.get::<usize, Option<String>>(18)?.map(PathBuf::from),
+            image_path: row.get::<usize, Option<String>>(19)?.map(PathBuf::from),
+            tags: self
+                .get_tags(&id)
+                .map_err(|_| rusqlite::Error::QueryReturnedNoRows)?,
+            id,
+        })
+    }
+
+    pub fn update_name(&self, bookmark: &Bookmark, new_name: &str) -> Result<()> {
+        self.atomically(|| {
+            self.conn.execute(
+                "UPDATE `Metadata` SET title = ?1 WHERE bookmark_id = ?2;",
+                [new_name, &bookmark.id],
+            )?;
+
+            self.conn.execute(
+                "UPDATE `Bookmark` SET last_modified = ?1 WHERE id = ?2;",
+                [&Utc::now().timestamp().to_string(), &bookmark.id],
+            )?;
+
+            Ok(())
+        })
+    }
+
+    pub fn update_link(&self, bookmark: &Bookmark, new_link: &str) -> Result<()> {
+        let query = "UPDATE `Bookmark` SET link = ?1, canonical_link = canonical_url(?1), last_modified = ?2 WHERE id = ?3;";
+
+        self.atomically(|| {
+            self.conn.execute(
+                query,
+                [new_link, &Utc::now().timestamp().to_string(), &bookmark.id],
+            )?;
+
+            // The last check was of the old link
+            self.conn.execute(
+                "DELETE FROM `LinkCheck` WHERE bookmark_id = ?1",
+                [&bookmark.id],
+            )?;
+
+            Ok(())
+        })
+    }
+
+    pub fn update_descr(&self, bookmark: &Bookmark, new_description: &str) -> Result<()> {
+        self.atomically(|| {
+            self.conn.execute(
+                "UPDATE `Metadata` SET description = ?1 WHERE bookmark_id = ?2;",
+                [new_description, &bookmark.id],
+            )?;
+
+            self.conn.execute(
+                "UPDATE `Bookmark` SET last_modified = ?1 WHERE id = ?2;",
+                [&Utc::now().timestamp().to_string(), &bookmark.id],
+            )?;
+
+            Ok(())
+        })
+    }
+
+    pub fn update_image_url(&self, bookmark: &Bookmark, new_image_url: &str) -> Result<()> {
+        self.atomically(|| {
+            self.conn.execute(
+                "UPDATE `Metadata` SET image_url = ?1 WHERE bookmark_id = ?2;",
+                [new_image_url, &bookmark.id],
+            )?;
+
+            self.conn.execute(
+                "UPDATE `Bookmark` SET last_modified = ?1 WHERE id = ?2;",
+                [&Utc::now().timestamp().to_string(), &bookmark.id],
+            )?;
+
+            Ok(())
+        })
+    }
+
+    pub fn update_site_type(&self, bookmark: &Bookmark, new_site_type: &str) -> Result<()> {
+        self.atomically(|| {
+            self.conn.execute(
+                "UPDATE `Metadata` SET site_type = ?1 WHERE bookmark_id = ?2;",
+                [new_site_type, &bookmark.id],
+            )?;
+
+            self.conn.execute(
+                "UPDATE `Bookmark` SET last_modified = ?1 WHERE id = ?2;",
+                [&Utc::now().timestamp().to_string(), &bookmark.id],
+            )?;
+
+            Ok(())
+        })
+    }
+
+    /// Replaces the metadata other than title, description, image and type.
+    pub fn update_details(&self, bookmark: &Bookmark, metadata: &SiteMetadata) -> Result<()> {
+        self.atomically(|| {
+            self.set_details(&bookmark.id, metadata)?;
+
+            self.conn.execute(
+                "UPDATE `Bookmark` SET last_modified = ?1 WHERE id = ?2;",
+                [&Utc::now().timestamp().to_string(), &bookmark.id],
+            )?;
+
+            Ok(())
+        })
+    }
+
     pub fn update_tags(&self, bookmark: &Bookmark, new_tags: &[String]) -> Result<(usize, usize)> {
+        self.atomically(|| self.replace_tags(bookmark, new_tags))
+    }
+
+    fn replace_tags(&self, bookmark: &Bookmark, new_tags: &[String]) -> Result<(usize, usize)> {
         rusqlite::vtab::array::load_module(&self.conn)?;
 
+        let new_tags = self.normalize_tags(new_tags)?;
         let old_tags: HashSet<_> = bookmark.tags.0.iter().collect();
         let new_tags: HashSet<_> = new_tags.iter().collect();
 
@@ -311,35 +865,32 @@ impl Database {
 
         let mut delete_query = self
             .conn
-            .prepare("DELETE FROM `Tag` WHERE bookmark_id LIKE ?1 AND tag IN rarray(?)")?;
+            .prepare("DELETE FROM `Tag` WHERE bookmark_id = ?1 AND tag IN rarray(?)")?;
 
         let delete_count = delete_query.execute(params![bookmark.id, values])?;
 
-        let mut add_count = 0;
-        for tag in added_tags {
-            match self.conn.execute(
-                "INSERT INTO `Tag` (bookmark_id, tag) VALUES (?1, ?2)",
-                params![bookmark.id, tag],
-            ) {
-                Ok(_) => {
-                    info!("Added tag '{}' to '{}'", tag, bookmark.metadata.title);
-                    add_count += 1;
-                }
-                Err(_) => error!(
-                    "Failed to add tag '{}' to '{}'",
-                    tag, bookmark.metadata.title
-                ),
-            };
-            add_count += 1;
+        for tag in &added_tags {
+            self.conn
+                .execute(
+                    "INSERT INTO `Tag` (bookmark_id, tag) VALUES (?1, ?2)",
+                    params![bookmark.id, tag],
+                )
+                .wrap_err_with(|| {
+                    format!(
+                        "Failed to add tag '{}' to '{}'",
+                        tag, bookmark.metadata.title
+                    )
+                })?;
+            info!("Added tag '{}' to '{}'", tag, bookmark.metadata.title);
         }
 
-        Ok((add_count, delete_count))
+        Ok((added_tags.len(), delete_count))
     }
 
     fn get_tags(&self, bookmark_id: &str) -> Result<TagList> {
         let mut tag_query = self
             .conn
-            .prepare("SELECT tag FROM `Tag` WHERE bookmark_id LIKE ?1")?;
+            .prepare("SELECT tag FROM `Tag` WHERE bookmark_id = ?1")?;
         let tags = tag_query
             .query_map([bookmark_id], |x| x.get(0))?
             .collect::<Result<Vec<_>, _>>()?;
@@ -361,15 +912,667 @@ impl Database {
         Ok(results)
     }
 
+    /// Every tag of every bookmark, as `(bookmark id, tag)`.
+    pub fn tag_pairs(&self) -> Result<Vec<(String, String)>> {
+        let mut tag_query = self
+            .conn
+            .prepare("SELECT bookmark_id, tag FROM `Tag` WHERE tag IS NOT NULL")?;
+        let pairs = tag_query
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(pairs)
+    }
+
+    /// Deletes `tag` along with its descendants.
     pub fn tag_delete(&self, tag: &str) -> Result<usize> {
-        let query = "DELETE FROM `Tag` WHERE tag LIKE ?1";
-        let count = self.conn.execute(query, [tag])?;
-        Ok(count)
+        let params = params![tag, tag.chars().count() + 1, tags::SEPARATOR.to_string()];
+        self.atomically(|| {
+            let count = self.conn.execute(
+                "DELETE FROM `Tag` WHERE tag = ?1 OR substr(tag, 1, ?2) = ?1 || ?3",
+                params,
+            )?;
+            self.conn.execute(
+                "DELETE FROM `TagInfo` WHERE tag = ?1 OR substr(tag, 1, ?2) = ?1 || ?3",
+                params,
+            )?;
+            Ok(count)
+        })
+    }
+
+    /// Moves the info of `old` and its descendants to `new`. Tags that
+    /// already have info keep theirs.
+    pub fn move_tag_infos(&self, old: &str, new: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE OR IGNORE `TagInfo` SET tag = ?1 || substr(tag, ?3) WHERE tag = ?2 OR substr(tag, 1, ?3) = ?2 || ?4",
+            params![
+                new,
+                old,
+                old.chars().count() + 1,
+                tags::SEPARATOR.to_string()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn tag_infos(&self) -> Result<HashMap<String, TagInfo>> {
+        let mut info_query = self
+            .conn
+            .prepare("SELECT tag, description, color, icon, pinned FROM `TagInfo`")?;
+        let infos = info_query
+            .query_map([], |row| {
+                Ok(TagInfo {
+                    tag: row.get(0)?,
+                    description: row.get(1)?,
+                    color: row.get(2)?,
+                    icon: row.get(3)?,
+                    pinned: row.get(4)?,
+                })
+            })?
+            .map(|info| info.map(|info| (info.tag.to_owned(), info)))
+            .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+        Ok(infos)
+    }
+
+    /// Saves `info`, replacing the previous info of the tag. Info with nothing
+    /// set is removed.
+    pub fn save_tag_info(&self, info: &TagInfo) -> Result<()> {
+        if info.is_empty() {
+            self.conn
+                .execute("DELETE FROM `TagInfo` WHERE tag = ?1", [&info.tag])?;
+            return Ok(());
+        }
+
+        self.conn.execute(
+            "INSERT OR REPLACE INTO `TagInfo` (tag, description, color, icon, pinned) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![info.tag, info.description, info.color, info.icon, info.pinned],
+        )?;
+        Ok(())
+    }
+
+    /// Applies `edits`, all or none of them.
+    pub fn apply_tag_edits(&self, edits: &[TagEdit]) -> Result<()> {
+        self.atomically(|| {
+            for edit in edits {
+                for tag in &edit.removed {
+                    self.conn.execute(
+                        "DELETE FROM `Tag` WHERE bookmark_id = ?1 AND tag = ?2",
+                        params![edit.bookmark.id, tag],
+                    )?;
+                }
+                for tag in &edit.added {
+                    self.conn.execute(
+                        // Not `OR IGNORE`, which would leave the search index
+                        // without the bookmark
+                        "INSERT INTO `Tag` (bookmark_id, tag) SELECT ?1, ?2
+                        WHERE NOT EXISTS (SELECT 1 FROM `Tag` WHERE bookmark_id = ?1 AND tag = ?2)",
+                        params![edit.bookmark.id, tag],
+                    )?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Applies the normalization rules and aliases to `tag`. Aliases also
+    /// apply to descendants: with `js` an alias of `javascript`, `js/react`
+    /// becomes `javascript/react`. Returns `None` if nothing is left of the tag.
+    pub fn normalize_tag(&self, tag: &str) -> Result<Option<String>> {
+        let tag = self.tag_normalizer.normalize(tag);
+        if tag.is_empty() {
+            return Ok(None);
+        }
+
+        let mut alias_query = self
+            .conn
+            .prepare_cached("SELECT tag FROM `TagAlias` WHERE alias = ?1")?;
+        let prefixes: Vec<_> = tags::prefixes(&tag).collect();
+        for prefix in prefixes.into_iter().rev() {
+            let target: Option<String> = alias_query
+                .query_row([prefix], |row| row.get(0))
+                .optional()?;
+            if let Some(target) = target {
+                return Ok(Some(format!("{}{}", target, &tag[prefix.len()..])));
+            }
+        }
+        Ok(Some(tag))
+    }
+
+    /// Normalizes `tags`, dropping empty and repeated ones.
+    fn normalize_tags(&self, tags: &[String]) -> Result<Vec<String>> {
+        let mut normalized = Vec::with_capacity(tags.len());
+        for tag in tags {
+            if let Some(tag) = self.normalize_tag(tag)? {
+                normalized.push(tag);
+            }
+        }
+        Ok(normalized.into_iter().unique().collect())
+    }
+
+    /// Compiles `query` with its tags normalized like stored tags.
+    fn compile(&self, query: &BookmarkQuery) -> Result<(String, Vec<Value>)> {
+        let query =
+            query.map_tags(|tag| Ok(self.normalize_tag(tag)?.unwrap_or_else(|| tag.to_owned())))?;
+        Ok(query.compile())
+    }
+
+    /// Saved aliases as `(alias, tag)`, ordered by alias.
+    pub fn tag_aliases(&self) -> Result<Vec<(String, String)>> {
+        let mut alias_query = self
+            .conn
+            .prepare("SELECT alias, tag FROM `TagAlias` ORDER BY alias")?;
+        let aliases = alias_query
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(aliases)
+    }
+
+    /// Makes `alias` a synonym of `tag`. Aliases of `alias` and its
+    /// descendants are pointed at `tag` too, so aliases never chain. Returns
+    /// the normalized alias and tag.
+    pub fn add_tag_alias(&self, alias: &str, tag: &str) -> Result<(String, String)> {
+        let alias = self.tag_normalizer.normalize(alias);
+        if alias.is_empty() {
+            return Err(eyre!("Alias can't be empty"));
+        }
+        let tag = self
+            .normalize_tag(tag)?
+            .ok_or_else(|| eyre!("\"{}\" isn't a valid tag", tag))?;
+        if tags::is_within(&tag, &alias) {
+            return Err(eyre!(
+                "\"{}\" can't be an alias of \"{}\", which it contains",
+                alias,
+                tag
+            ));
+        }
+
+        self.atomically(|| {
+            self.conn.execute(
+                "INSERT OR REPLACE INTO `TagAlias` (alias, tag) VALUES (?1, ?2)",
+                params![alias, tag],
+            )?;
+            self.conn.execute(
+                "UPDATE `TagAlias` SET tag = ?1 || substr(tag, ?3) WHERE tag = ?2 OR substr(tag, 1, ?3) = ?2 || ?4",
+                params![
+                    tag,
+                    alias,
+                    alias.chars().count() + 1,
+                    tags::SEPARATOR.to_string()
+                ],
+            )?;
+            Ok(())
+        })?;
+        Ok((alias, tag))
+    }
+
+    /// Removes the alias `alias`. Returns whether it existed.
+    pub fn remove_tag_alias(&self, alias: &str) -> Result<bool> {
+        let count = self.conn.execute(
+            "DELETE FROM `TagAlias` WHERE alias = ?1",
+            [self.tag_normalizer.normalize(alias)],
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Stored tags that differ from their normalized form.
+    pub fn tag_changes(&self) -> Result<Vec<TagChange>> {
+        let mut changes = Vec::new();
+        for (tag, count) in self.tag_counts()? {
+            let to = self.normalize_tag(&tag)?;
+            if to.as_deref() != Some(tag.as_str()) {
+                changes.push(TagChange {
+                    from: tag,
+                    to,
+                    count,
+                });
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Rewrites the tags in `changes`. Bookmarks that already have the
+    /// normalized tag just lose the old one. Returns the number of rows
+    /// changed.
+    pub fn apply_tag_changes(&self, changes: &[TagChange]) -> Result<usize> {
+        self.atomically(|| {
+            let mut count = 0;
+            for change in changes {
+                if let Some(to) = &change.to {
+                    count += self.conn.execute(
+                        "UPDATE `Tag` SET tag = ?2 WHERE tag = ?1 AND bookmark_id NOT IN (SELECT bookmark_id FROM `Tag` WHERE tag = ?2)",
+                        params![change.from, to],
+                    )?;
+                }
+                count += self
+                    .conn
+                    .execute("DELETE FROM `Tag` WHERE tag = ?1", [&change.from])?;
+            }
+            Ok(count)
+        })
+    }
+}
+
+/// Registers the application defined SQL functions used by queries.
+fn register_functions(conn: &Connection, normalizer: UrlNormalizer) -> Result<()> {
+    conn.create_scalar_function(
+        "url_host",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| Ok(utils::get_host(&ctx.get::<String>(0)?)),
+    )?;
+    conn.create_scalar_function(
+        "canonical_url",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        move |ctx| {
+            Ok(ctx
+                .get::<Option<String>>(0)?
+                .map(|url| normalizer.normalize(&url)))
+        },
+    )?;
+    conn.create_scalar_function(
+        "page_text",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            ctx.get::<Option<Vec<u8>>>(0)?
+                .map(|compressed| decompress(&compressed))
+                .transpose()
+                .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))
+        },
+    )?;
+    Ok(())
+}
+
+/// Decompresses page content stored by [`Database::save_content`].
+fn decompress(compressed: &[u8]) -> std::io::Result<String> {
+    let mut text = String::new();
+    ZlibDecoder::new(compressed).read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Builds an asset from a row that starts with its hash, path, content type
+/// and size.
+fn asset_from_row(row: &Row) -> rusqlite::Result<Asset> {
+    Ok(Asset {
+        hash: row.get(0)?,
+        path: PathBuf::from(row.get::<usize, String>(1)?),
+        content_type: row.get(2)?,
+        size: row.get(3)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::TagOperation;
+
+    /// An empty database that stores tags as written.
+    fn database() -> Database {
+        let tag_normalizer = TagNormalizer {
+            fold_case: false,
+            hyphenate: false,
+        };
+        Database::connect(Path::new(":memory:"), UrlNormalizer::new(), tag_normalizer).unwrap()
+    }
+
+    fn add(db: &Database, link: &str, tags: &[&str]) {
+        db.add_one(&Bookmark::sample(link, link, tags), None)
+            .unwrap();
+    }
+
+    fn tags(db: &Database) -> Vec<String> {
+        db.tag_counts()
+            .unwrap()
+            .into_iter()
+            .map(|(tag, _)| tag)
+            .sorted()
+            .collect()
+    }
+
+    #[test]
+    fn deletes_tag_with_descendants() {
+        let db = database();
+        add(&db, "https://a.example", &["dev", "dev/rust", "devops"]);
+        add(&db, "https://b.example", &["dev/go/tools", "misc"]);
+
+        assert_eq!(db.tag_delete("dev").unwrap(), 3);
+        assert_eq!(tags(&db), ["devops", "misc"]);
+    }
+
+    #[test]
+    fn deletes_tags_matching_exactly() {
+        let db = database();
+        add(
+            &db,
+            "https://a.example",
+            &["ca", "cd/sub", "c_", "Misc", "50%"],
+        );
+
+        assert_eq!(db.tag_delete("%").unwrap(), 0);
+        assert_eq!(db.tag_delete("c_").unwrap(), 1);
+        assert_eq!(db.tag_delete("misc").unwrap(), 0);
+        assert_eq!(tags(&db), ["50%", "Misc", "ca", "cd/sub"]);
+    }
+
+    #[test]
+    fn deletes_info_of_deleted_tags_only() {
+        let db = database();
+        add(
+            &db,
+            "https://a.example",
+            &["dev/rust", "devops", "c_", "ca"],
+        );
+        for tag in ["dev", "dev/rust", "devops", "c_", "ca"] {
+            let mut info = TagInfo::new(tag);
+            info.pinned = true;
+            db.save_tag_info(&info).unwrap();
+        }
+
+        db.tag_delete("%").unwrap();
+        db.tag_delete("c_").unwrap();
+        db.tag_delete("dev").unwrap();
+        let infos: Vec<String> = db.tag_infos().unwrap().into_keys().sorted().collect();
+        assert_eq!(infos, ["ca", "devops"]);
+    }
+
+    /// Fails if the search index differs from one rebuilt from the indexed
+    /// tables.
+    fn check_search_index(db: &Database) {
+        db.conn
+            .execute_batch(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS temp.`SearchTerms` USING fts5vocab(main, 'BookmarkSearch', 'instance')",
+            )
+            .unwrap();
+        let entries = || -> Vec<(String, i64, String, i64)> {
+            db.conn
+                .prepare(
+                    "SELECT term, doc, col, offset FROM temp.`SearchTerms` ORDER BY 1, 2, 3, 4",
+                )
+                .unwrap()
+                .query_map([], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                })
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+
+        let indexed = entries();
+        db.conn
+            .execute(
+                "INSERT INTO `BookmarkSearch` (`BookmarkSearch`) VALUES ('rebuild')",
+                [],
+            )
+            .unwrap();
+        assert_eq!(indexed, entries());
+    }
+
+    fn search(db: &Database, query: &str) -> Vec<String> {
+        db.search(query, None)
+            .unwrap()
+            .into_iter()
+            .map(|result| result.bookmark.link)
+            .sorted()
+            .collect()
+    }
+
+    /// The only saved bookmark.
+    fn only(db: &Database) -> Bookmark {
+        let mut bookmarks = db.get(&BookmarkQuery::new()).unwrap();
+        assert_eq!(bookmarks.len(), 1);
+        bookmarks.remove(0)
+    }
+
+    #[test]
+    fn searches_titles_tags_and_links() {
+        let db = database();
+        let mut bookmark = Bookmark::sample(
+            "https://blog.example/posts/fearless",
+            "Concurrency without data races",
+            &["lang/rust"],
+        );
+        bookmark.metadata.description = Some("Threads and channels".to_owned());
+        db.add_one(&bookmark, None).unwrap();
+        add(&db, "https://other.example", &["go"]);
+        check_search_index(&db);
+
+        let hit = ["https://blog.example/posts/fearless"];
+        assert_eq!(search(&db, "concurrency"), hit);
+        assert_eq!(search(&db, "channels"), hit);
+        assert_eq!(search(&db, "rust"), hit);
+        assert_eq!(search(&db, "fearless"), hit);
+        assert_eq!(search(&db, "tags:rust"), hit);
+        assert!(search(&db, "title:rust").is_empty());
+        assert_eq!(search(&db, "example").len(), 2);
+    }
+
+    #[test]
+    fn search_index_follows_edits() {
+        let db = database();
+        add(&db, "https://a.example/old-link", &["draft", "lang/go"]);
+
+        db.update_name(&only(&db), "Gardening notes").unwrap();
+        db.update_descr(&only(&db), "Seasonal planting").unwrap();
+        db.update_link(&only(&db), "https://a.example/new-link")
+            .unwrap();
+        check_search_index(&db);
+        assert_eq!(search(&db, "gardening"), ["https://a.example/new-link"]);
+        assert_eq!(search(&db, "seasonal"), ["https://a.example/new-link"]);
+        assert!(search(&db, "old").is_empty());
+
+        db.update_tags(
+            &only(&db),
+            &["draft".to_owned(), "lang/go".to_owned(), "todo".to_owned()],
+        )
+        .unwrap();
+        check_search_index(&db);
+        assert_eq!(search(&db, "todo"), ["https://a.example/new-link"]);
+
+        let rename = TagOperation::Merge {
+            sources: vec!["lang".to_owned()],
+            into: "golang".to_owned(),
+        };
+        let bookmark = only(&db);
+        let edit = TagEdit::new(&bookmark, &rename.apply(&bookmark.tags.0)).unwrap();
+        db.apply_tag_edits(&[edit]).unwrap();
+        check_search_index(&db);
+        assert!(search(&db, "lang").is_empty());
+        assert_eq!(search(&db, "golang"), ["https://a.example/new-link"]);
+
+        db.tag_delete("draft").unwrap();
+        check_search_index(&db);
+        assert!(search(&db, "draft").is_empty());
+    }
+
+    #[test]
+    fn search_forgets_deleted_bookmarks() {
+        let db = database();
+        add(&db, "https://a.example", &["recipes"]);
+        add(&db, "https://b.example", &["recipes"]);
+        let bookmarks = db.get(&BookmarkQuery::new()).unwrap();
+        db.save_content(&bookmarks[0].id, "Slow cooked beans")
+            .unwrap();
+
+        db.delete_many(&bookmarks).unwrap();
+        check_search_index(&db);
+        assert!(search(&db, "recipes").is_empty());
+        assert!(search(&db, "beans").is_empty());
+        let rows: usize = db
+            .conn
+            .query_row("SELECT count(*) FROM `BookmarkSearch_docsize`", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(rows, 0);
+    }
+
+    #[test]
+    fn indexes_page_content_without_copying_it() {
+        let db = database();
+        add(&db, "https://a.example", &[]);
+        add(&db, "https://b.example", &[]);
+        let id = db.get(&BookmarkQuery::new()).unwrap()[0].id.clone();
+
+        db.save_content(&id, "Green tomatoes ripen slowly").unwrap();
+        check_search_index(&db);
+        assert_eq!(search(&db, "tomatoes"), ["https://a.example"]);
+        assert_eq!(
+            db.content(&id).unwrap().unwrap(),
+            "Green tomatoes ripen slowly"
+        );
+
+        db.save_content(&id, "Red peppers").unwrap();
+        check_search_index(&db);
+        assert!(search(&db, "tomatoes").is_empty());
+        assert_eq!(search(&db, "peppers"), ["https://a.example"]);
+
+        let copies: usize = db
+            .conn
+            .query_row(
+                "SELECT count(*) FROM sqlite_master WHERE name = 'BookmarkSearch_content'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(copies, 0);
+
+        db.delete_many(&db.get(&BookmarkQuery::new()).unwrap()[..1])
+            .unwrap();
+        check_search_index(&db);
+        assert!(search(&db, "peppers").is_empty());
+        assert_eq!(search(&db, "example"), ["https://b.example"]);
+    }
+
+    #[test]
+    fn adds_page_content_with_bookmark() {
+        let db = database();
+        let bookmark = Bookmark::sample("https://a.example", "A", &[]);
+        db.add_one(&bookmark, Some("Basil grows well indoors"))
+            .unwrap();
+
+        let id = db.get(&BookmarkQuery::new()).unwrap()[0].id.clone();
+        assert_eq!(
+            db.content(&id).unwrap().unwrap(),
+            "Basil grows well indoors"
+        );
+        assert_eq!(search(&db, "basil"), ["https://a.example"]);
+        check_search_index(&db);
+    }
+
+    #[test]
+    fn applies_aliases_to_descendants() {
+        let mut db = database();
+        db.tag_normalizer = TagNormalizer::new();
+        db.add_tag_alias("JS", "javascript").unwrap();
+        db.add_tag_alias("js/old", "legacy").unwrap();
+
+        let normalize = |tag| db.normalize_tag(tag).unwrap();
+        assert_eq!(normalize("js").as_deref(), Some("javascript"));
+        assert_eq!(normalize("JS/React").as_deref(), Some("javascript/react"));
+        assert_eq!(normalize("js/old/jquery").as_deref(), Some("legacy/jquery"));
+        assert_eq!(normalize("jsx").as_deref(), Some("jsx"));
+        assert_eq!(normalize(" / "), None);
+    }
+
+    #[test]
+    fn repoints_aliases_so_they_never_chain() {
+        let db = database();
+        db.add_tag_alias("js", "javascript").unwrap();
+        db.add_tag_alias("react", "javascript/react").unwrap();
+        db.add_tag_alias("javascript", "ecmascript").unwrap();
+        assert_eq!(
+            db.add_tag_alias("es", "js").unwrap(),
+            ("es".to_owned(), "ecmascript".to_owned())
+        );
+
+        let aliases: Vec<_> = db
+            .tag_aliases()
+            .unwrap()
+            .into_iter()
+            .map(|(alias, tag)| format!("{} -> {}", alias, tag))
+            .collect();
+        assert_eq!(
+            aliases,
+            [
+                "es -> ecmascript",
+                "javascript -> ecmascript",
+                "js -> ecmascript",
+                "react -> ecmascript/react",
+            ]
+        );
+        assert!(db.add_tag_alias("web", "web/css").is_err());
+    }
+
+    #[test]
+    fn normalizes_tags_a_bookmark_already_has() {
+        let mut db = database();
+        add(&db, "https://a.example", &["Rust", "rust", "Web Dev"]);
+        add(&db, "https://b.example", &["Rust"]);
+        db.tag_normalizer = TagNormalizer::new();
+
+        let changes = db.tag_changes().unwrap();
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|change| (change.from.as_str(), change.to.as_deref(), change.count))
+            .collect();
+        assert_eq!(
+            summary,
+            [("Rust", Some("rust"), 2), ("Web Dev", Some("web-dev"), 1)]
+        );
+
+        assert_eq!(db.apply_tag_changes(&changes).unwrap(), 3);
+        assert_eq!(
+            db.tag_counts()
+                .unwrap()
+                .into_iter()
+                .sorted()
+                .collect::<Vec<_>>(),
+            [("rust".to_owned(), 2), ("web-dev".to_owned(), 1)]
+        );
+        assert!(db.tag_changes().unwrap().is_empty());
+        check_search_index(&db);
     }
 
-    pub fn tag_rename(&self, old: &str, new: &str) -> Result<usize> {
-        let query = "UPDATE `Tag` SET tag = ?1 WHERE tag LIKE ?2";
-        let count = self.conn.execute(query, [new, old])?;
-        Ok(count)
+    #[test]
+    fn groups_duplicates_oldest_first() {
+        let db = database();
+        let links = [
+            ("https://example.com/a", None),
+            (
+                "https://m.example.com/a",
+                Some("http://www.example.com/a/?utm_source=feed"),
+            ),
+            (
+                "https://amp.example.com/a",
+                Some("https://example.com/a#top"),
+            ),
+            ("https://other.example/", None),
+        ];
+        for (idx, (link, _)) in links.iter().enumerate() {
+            let mut bookmark = Bookmark::sample(link, "", &[]);
+            bookmark.added_at = [100, 98, 99, 97][idx];
+            db.add_one(&bookmark, None).unwrap();
+        }
+        assert!(db.duplicates().unwrap().is_empty());
+
+        // Pages found to declare another page as canonical after being saved
+        for bookmark in db.get(&BookmarkQuery::new()).unwrap() {
+            let canonical_url = links
+                .iter()
+                .find(|(link, _)| *link == bookmark.link)
+                .and_then(|(_, canonical_url)| *canonical_url);
+            let metadata = SiteMetadata {
+                canonical_url: canonical_url.map(str::to_owned),
+                ..Default::default()
+            };
+            db.update_details(&bookmark, &metadata).unwrap();
+        }
+
+        let groups = db.duplicates().unwrap();
+        assert_eq!(groups.len(), 1);
+        let (link, bookmarks) = &groups[0];
+        assert_eq!(link, "https://example.com/a");
+        let grouped: Vec<_> = bookmarks.iter().map(|b| b.link.as_str()).collect();
+        assert_eq!(grouped, [links[1].0, links[2].0, links[0].0]);
     }
 }
@@ -0,0 +1,155 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use serde_json::json;
+use url::Url;
+
+use super::{MetadataExtractor, Page};
+use crate::{
+    site_metadata::{self, SiteMetadata, SiteType},
+    utils,
+};
+
+/// Abstract pages of arXiv papers: authors, abstract, dates and PDF link.
+pub struct ArxivExtractor;
+
+impl ArxivExtractor {
+    fn paper_id(url: &Url) -> Option<String> {
+        if !matches!(
+            url.host_str()?,
+            "arxiv.org" | "www.arxiv.org" | "export.arxiv.org"
+        ) {
+            return None;
+        }
+        let id = url.path().strip_prefix("/abs/")?.trim_end_matches('/');
+        (!id.is_empty()).then(|| id.to_owned())
+    }
+}
+
+impl MetadataExtractor for ArxivExtractor {
+    fn name(&self) -> &'static str {
+        "arxiv"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        Self::paper_id(url).is_some()
+    }
+
+    fn extract(&self, page: &Page, metadata: &mut SiteMetadata) {
+        let paper_id = match Self::paper_id(page.url()) {
+            Some(id) => page.meta("citation_arxiv_id").unwrap_or(id),
+            None => return,
+        };
+
+        // Authors are listed as "Last, First"
+        let authors: Vec<String> = page
+            .meta_all("citation_author")
+            .iter()
+            .map(|author| match author.split_once(',') {
+                Some((last, first)) => format!("{} {}", first.trim(), last.trim()),
+                None => author.to_owned(),
+            })
+            .collect();
+        let abstract_text = page.meta("citation_abstract").or_else(|| {
+            page.by_class("abstract").next().map(|element| {
+                let text = element.text();
+                text.trim_start_matches("Abstract:").trim().to_owned()
+            })
+        });
+        let published_at = page
+            .meta("citation_date")
+            .or_else(|| page.meta("citation_online_date"))
+            .and_then(|date| parse_date(&date));
+        let subject = page
+            .by_class("primary-subject")
+            .next()
+            .map(|element| element.text());
+
+        if let Some(title) = page.meta("citation_title") {
+            metadata.title = utils::squeeze_whitespaces(&title);
+        }
+        if let Some(abstract_text) = abstract_text {
+            metadata.description = Some(utils::squeeze_whitespaces(&abstract_text));
+        }
+        if !authors.is_empty() {
+            metadata.author = Some(authors.join(", "));
+        }
+        if published_at.is_some() {
+            metadata.published_at = published_at;
+        }
+        metadata.site_type = SiteType::Article;
+        metadata.site_name = Some("arXiv".to_owned());
+        metadata
+            .extra
+            .insert("arxiv_id".to_owned(), json!(paper_id));
+        if !authors.is_empty() {
+            metadata.extra.insert("authors".to_owned(), json!(authors));
+        }
+        if let Some(pdf_url) = page.meta("citation_pdf_url") {
+            metadata.extra.insert("pdf_url".to_owned(), json!(pdf_url));
+        }
+        if let Some(subject) = subject {
+            metadata.extra.insert("subject".to_owned(), json!(subject));
+        }
+    }
+}
+
+/// Citation dates are written as `2017/06/12`.
+fn parse_date(date: &str) -> Option<i64> {
+    match NaiveDate::parse_from_str(date.trim(), "%Y/%m/%d") {
+        Ok(day) => Some(
+            Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0)?)
+                .timestamp(),
+        ),
+        Err(_) => site_metadata::parse_timestamp(date),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::extractors::ExtractorRegistry;
+
+    const PAPER: &str = include_str!("../../tests/fixtures/arxiv.html");
+
+    #[test]
+    fn reads_paper_details() {
+        let metadata = SiteMetadata::parse(
+            PAPER,
+            "https://arxiv.org/abs/1706.03762v7",
+            &ExtractorRegistry::default(),
+        )
+        .unwrap();
+        assert_eq!(metadata.title, "Attention Is All You Need");
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("The dominant sequence transduction models are based on complex recurrent or convolutional neural networks.")
+        );
+        assert_eq!(
+            metadata.author.as_deref(),
+            Some("Ashish Vaswani, Noam Shazeer, Niki Parmar")
+        );
+        assert_eq!(metadata.published_at, Some(1497225600));
+        assert_eq!(metadata.site_type, SiteType::Article);
+        assert_eq!(metadata.site_name.as_deref(), Some("arXiv"));
+
+        let extra = &metadata.extra;
+        assert_eq!(extra["arxiv_id"], json!("1706.03762"));
+        assert_eq!(
+            extra["authors"],
+            json!(["Ashish Vaswani", "Noam Shazeer", "Niki Parmar"])
+        );
+        assert_eq!(extra["pdf_url"], json!("https://arxiv.org/pdf/1706.03762"));
+        assert_eq!(extra["subject"], json!("Computation and Language (cs.CL)"));
+    }
+
+    #[test]
+    fn only_handles_abstract_pages() {
+        let extractor = ArxivExtractor;
+        let matches = |url: &str| extractor.matches(&Url::parse(url).unwrap());
+        assert!(matches("https://arxiv.org/abs/1706.03762"));
+        assert!(matches("https://export.arxiv.org/abs/hep-th/9901001"));
+        assert!(!matches("https://arxiv.org/pdf/1706.03762"));
+        assert!(!matches("https://arxiv.org/list/cs.CL/recent"));
+    }
+}
@@ -0,0 +1,185 @@
+use serde_json::json;
+use url::Url;
+
+use super::{parse_count, MetadataExtractor, Page};
+use crate::site_metadata::SiteMetadata;
+
+/// First path segments of github.com pages that aren't repositories.
+const RESERVED_PATHS: &[&str] = &[
+    "about",
+    "collections",
+    "enterprise",
+    "explore",
+    "features",
+    "marketplace",
+    "notifications",
+    "orgs",
+    "pricing",
+    "pulls",
+    "search",
+    "settings",
+    "sponsors",
+    "topics",
+    "trending",
+];
+
+/// Repository pages on github.com: description, stars, language and topics.
+pub struct GithubExtractor;
+
+impl GithubExtractor {
+    fn repository(url: &Url) -> Option<(String, String)> {
+        if url.host_str()? != "github.com" {
+            return None;
+        }
+        let mut segments = url.path_segments()?.filter(|segment| !segment.is_empty());
+        let owner = segments.next()?;
+        let repo = segments.next()?;
+        if RESERVED_PATHS.contains(&owner) {
+            return None;
+        }
+        Some((owner.to_owned(), repo.trim_end_matches(".git").to_owned()))
+    }
+}
+
+impl MetadataExtractor for GithubExtractor {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        Self::repository(url).is_some()
+    }
+
+    fn extract(&self, page: &Page, metadata: &mut SiteMetadata) {
+        let (owner, repo) = match Self::repository(page.url()) {
+            Some(repository) => repository,
+            None => return,
+        };
+
+        // Titles look like "GitHub - owner/repo: description"
+        if let Some(title) = metadata.title.strip_prefix("GitHub - ") {
+            metadata.title = title.to_owned();
+        }
+        if metadata.description.is_none() {
+            metadata.description = page.meta("og:description");
+        }
+        // Repositories without a description get a generic one
+        let generic = metadata
+            .description
+            .as_deref()
+            .is_some_and(|description| description.starts_with("Contribute to "));
+        if generic {
+            metadata.description = None;
+        }
+        if let Some(description) = &metadata.description {
+            let suffix = format!(" - {}/{}", owner, repo);
+            if let Some(description) = description.strip_suffix(&suffix) {
+                metadata.description = Some(description.to_owned());
+            }
+        }
+
+        let stars = page
+            .by_id("repo-stars-counter-star")
+            .and_then(|counter| {
+                counter
+                    .attr("title")
+                    .or_else(|| counter.attr("aria-label"))
+                    .and_then(|label| parse_count(label.split_whitespace().next()?))
+                    .or_else(|| parse_count(&counter.text()))
+            })
+            .or_else(|| {
+                page.elements()
+                    .filter(|element| element.tag() == "a")
+                    .find(|element| {
+                        element
+                            .attr("href")
+                            .is_some_and(|href| href.ends_with("/stargazers"))
+                    })
+                    .and_then(|link| link.text().split_whitespace().find_map(parse_count))
+            });
+        let language = page.itemprop("programmingLanguage").or_else(|| {
+            page.elements()
+                .find(|element| {
+                    element.has_class("color-fg-default") && element.has_class("text-bold")
+                })
+                .map(|element| element.text())
+                .filter(|language| !language.is_empty())
+        });
+        let mut topics: Vec<String> = Vec::new();
+        for topic in page.by_class("topic-tag").map(|element| element.text()) {
+            if !topic.is_empty() && !topics.contains(&topic) {
+                topics.push(topic);
+            }
+        }
+
+        if metadata.keywords.is_empty() {
+            metadata.keywords = topics.to_owned();
+        }
+        metadata.site_name = Some("GitHub".to_owned());
+        metadata.extra.insert("owner".to_owned(), json!(owner));
+        metadata.extra.insert("repo".to_owned(), json!(repo));
+        if let Some(stars) = stars {
+            metadata.extra.insert("stars".to_owned(), json!(stars));
+        }
+        if let Some(language) = language {
+            metadata
+                .extra
+                .insert("language".to_owned(), json!(language));
+        }
+        if !topics.is_empty() {
+            metadata.extra.insert("topics".to_owned(), json!(topics));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{extractors::ExtractorRegistry, site_metadata::SiteMetadata};
+
+    const REPOSITORY: &str = include_str!("../../tests/fixtures/github.html");
+
+    fn parse(html: &str, url: &str) -> SiteMetadata {
+        SiteMetadata::parse(html, url, &ExtractorRegistry::default()).unwrap()
+    }
+
+    #[test]
+    fn reads_repository_details() {
+        let metadata = parse(REPOSITORY, "https://github.com/tokio-rs/tokio");
+        assert_eq!(
+            metadata.title,
+            "tokio-rs/tokio: A runtime for writing reliable asynchronous applications with Rust."
+        );
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("A runtime for writing reliable asynchronous applications with Rust.")
+        );
+        assert_eq!(metadata.keywords, ["rust", "async", "networking"]);
+        assert_eq!(metadata.site_name.as_deref(), Some("GitHub"));
+
+        let extra = &metadata.extra;
+        assert_eq!(extra["owner"], json!("tokio-rs"));
+        assert_eq!(extra["repo"], json!("tokio"));
+        assert_eq!(extra["stars"], json!(27123));
+        assert_eq!(extra["language"], json!("Rust"));
+        assert_eq!(extra["topics"], json!(["rust", "async", "networking"]));
+    }
+
+    #[test]
+    fn drops_generic_descriptions() {
+        let html = REPOSITORY.replace(
+            "A runtime for writing reliable asynchronous applications with Rust. - tokio-rs/tokio",
+            "Contribute to tokio-rs/tokio development by creating an account on GitHub.",
+        );
+        let metadata = parse(&html, "https://github.com/tokio-rs/tokio");
+        assert_eq!(metadata.description, None);
+    }
+
+    #[test]
+    fn ignores_pages_other_than_repositories() {
+        let metadata = parse(REPOSITORY, "https://github.com/topics/rust");
+        assert!(metadata.extra.is_empty());
+        assert!(metadata.title.starts_with("GitHub - "));
+    }
+}
@@ -0,0 +1,254 @@
+use eyre::{Result, WrapErr};
+use html5ever::{parse_document, tendril::TendrilSink};
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use url::Url;
+
+use crate::{site_metadata::SiteMetadata, utils};
+
+pub mod arxiv;
+pub mod github;
+pub mod stackoverflow;
+pub mod youtube;
+
+/// Reads site specific details that generic HTML metadata misses.
+///
+/// Extractors work on an already parsed [`Page`], so they can be tried
+/// against saved HTML without going through the network.
+pub trait MetadataExtractor: Send + Sync {
+    /// Short name used in logs, e.g. `github`
+    fn name(&self) -> &'static str;
+
+    /// Whether this extractor handles pages at `url`.
+    fn matches(&self, url: &Url) -> bool;
+
+    /// Improves `metadata`, which already holds what generic parsing found,
+    /// with details from `page`. Site specific values without a field of their
+    /// own go into `metadata.extra`.
+    fn extract(&self, page: &Page, metadata: &mut SiteMetadata);
+}
+
+/// Extractors to try on fetched pages. The first one whose
+/// [`MetadataExtractor::matches`] accepts the page's URL is used.
+pub struct ExtractorRegistry {
+    extractors: Vec<Box<dyn MetadataExtractor>>,
+}
+
+impl ExtractorRegistry {
+    /// A registry without any extractors.
+    pub fn empty() -> Self {
+        Self {
+            extractors: Vec::new(),
+        }
+    }
+
+    /// Adds `extractor`, to be tried before those already registered.
+    pub fn register(&mut self, extractor: Box<dyn MetadataExtractor>) {
+        self.extractors.insert(0, extractor);
+    }
+
+    pub fn find(&self, url: &Url) -> Option<&dyn MetadataExtractor> {
+        self.extractors
+            .iter()
+            .find(|extractor| extractor.matches(url))
+            .map(|extractor| extractor.as_ref())
+    }
+
+    /// Runs the extractor for `page`'s URL, if any.
+    pub fn extract(&self, page: &Page, metadata: &mut SiteMetadata) {
+        if let Some(extractor) = self.find(page.url()) {
+            log::info!(
+                "Using the {} extractor for {}",
+                extractor.name(),
+                page.url()
+            );
+            extractor.extract(page, metadata);
+        }
+    }
+}
+
+impl Default for ExtractorRegistry {
+    /// A registry with the built-in extractors.
+    fn default() -> Self {
+        Self {
+            extractors: vec![
+                Box::new(github::GithubExtractor),
+                Box::new(youtube::YoutubeExtractor),
+                Box::new(arxiv::ArxivExtractor),
+                Box::new(stackoverflow::StackOverflowExtractor),
+            ],
+        }
+    }
+}
+
+/// A parsed HTML page, with helpers for finding elements in it.
+pub struct Page {
+    url: Url,
+    elements: Vec<PageElement>,
+}
+
+impl Page {
+    /// Parses `html` fetched from `url`.
+    pub fn parse(html: &str, url: &str) -> Result<Self> {
+        let dom = parse_document(RcDom::default(), Default::default())
+            .from_utf8()
+            .read_from(&mut html.as_bytes())
+            .wrap_err("Couldn't parse page")?;
+        Self::from_document(&dom.document, url)
+    }
+
+    pub(crate) fn from_document(document: &Handle, url: &str) -> Result<Self> {
+        let url = Url::parse(url).wrap_err_with(|| format!("Invalid URL {}", url))?;
+        let mut elements = Vec::new();
+        collect_elements(document, &mut elements);
+        Ok(Self { url, elements })
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// All elements of the page in document order.
+    pub fn elements(&self) -> impl Iterator<Item = &PageElement> {
+        self.elements.iter()
+    }
+
+    /// The first element with an `id` of `id`.
+    pub fn by_id(&self, id: &str) -> Option<&PageElement> {
+        self.elements()
+            .find(|element| element.attr("id").as_deref() == Some(id))
+    }
+
+    /// Elements that have `class` among their classes.
+    pub fn by_class<'a>(&'a self, class: &'a str) -> impl Iterator<Item = &'a PageElement> {
+        self.elements()
+            .filter(move |element| element.has_class(class))
+    }
+
+    /// The first element with an `itemprop` of `name`.
+    pub fn by_itemprop(&self, name: &str) -> Option<&PageElement> {
+        self.elements()
+            .find(|element| element.attr("itemprop").as_deref() == Some(name))
+    }
+
+    /// Content of the first `<meta>` named `name` through its `name`,
+    /// `property` or `itemprop` attribute.
+    pub fn meta(&self, name: &str) -> Option<String> {
+        self.meta_all(name).into_iter().next()
+    }
+
+    /// Contents of all `<meta>` elements named `name`, for properties that
+    /// repeat like `citation_author`.
+    pub fn meta_all(&self, name: &str) -> Vec<String> {
+        self.elements()
+            .filter(|element| element.tag() == "meta")
+            .filter(|element| {
+                ["name", "property", "itemprop"]
+                    .iter()
+                    .any(|key| element.attr(key).as_deref() == Some(name))
+            })
+            .filter_map(|element| element.attr("content"))
+            .map(|content| content.trim().to_owned())
+            .filter(|content| !content.is_empty())
+            .collect()
+    }
+
+    /// Value of the microdata property `name`: the `content` or `href` of the
+    /// first element with that `itemprop`, or its text.
+    pub fn itemprop(&self, name: &str) -> Option<String> {
+        self.by_itemprop(name).and_then(PageElement::value)
+    }
+}
+
+/// An element of a [`Page`].
+#[derive(Clone)]
+pub struct PageElement {
+    handle: Handle,
+}
+
+impl PageElement {
+    /// Lowercase tag name
+    pub fn tag(&self) -> String {
+        match &self.handle.data {
+            NodeData::Element { name, .. } => name.local.to_string(),
+            _ => String::new(),
+        }
+    }
+
+    pub fn attr(&self, name: &str) -> Option<String> {
+        match &self.handle.data {
+            NodeData::Element { attrs, .. } => attrs
+                .borrow()
+                .iter()
+                .find(|attr| attr.name.local.as_ref() == name)
+                .map(|attr| attr.value.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn has_class(&self, class: &str) -> bool {
+        self.attr("class")
+            .is_some_and(|classes| classes.split_whitespace().any(|c| c == class))
+    }
+
+    /// Text inside the element with whitespace collapsed.
+    pub fn text(&self) -> String {
+        fn walk(node: &Handle, text: &mut String) {
+            match &node.data {
+                NodeData::Text { contents } => text.push_str(&contents.borrow()),
+                NodeData::Element { name, .. }
+                    if matches!(name.local.as_ref(), "script" | "style") => {}
+                _ => {
+                    for child in node.children.borrow().iter() {
+                        walk(child, text);
+                    }
+                }
+            }
+        }
+
+        let mut text = String::new();
+        walk(&self.handle, &mut text);
+        utils::squeeze_whitespaces(&text).trim().to_owned()
+    }
+
+    /// The element's `content` or `href`, or its text.
+    pub fn value(&self) -> Option<String> {
+        let value = self
+            .attr("content")
+            .or_else(|| self.attr("href"))
+            .unwrap_or_else(|| self.text());
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_owned())
+    }
+
+    /// Elements nested in this one, in document order.
+    pub fn descendants(&self) -> Vec<PageElement> {
+        let mut elements = Vec::new();
+        for child in self.handle.children.borrow().iter() {
+            collect_elements(child, &mut elements);
+        }
+        elements
+    }
+}
+
+fn collect_elements(node: &Handle, elements: &mut Vec<PageElement>) {
+    if let NodeData::Element { .. } = node.data {
+        elements.push(PageElement {
+            handle: node.clone(),
+        });
+    }
+    for child in node.children.borrow().iter() {
+        collect_elements(child, elements);
+    }
+}
+
+/// Parses counts like `1,234`, `27.1k` or `2M`.
+fn parse_count(text: &str) -> Option<u64> {
+    let text = text.trim().replace(',', "").to_lowercase();
+    let (number, multiplier) = match text.chars().last()? {
+        'k' => (&text[..text.len() - 1], 1_000.0),
+        'm' => (&text[..text.len() - 1], 1_000_000.0),
+        _ => (text.as_str(), 1.0),
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    Some((number * multiplier).round() as u64)
+}
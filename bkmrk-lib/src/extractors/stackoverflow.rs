@@ -0,0 +1,154 @@
+use serde_json::json;
+use url::Url;
+
+use super::{parse_count, MetadataExtractor, Page, PageElement};
+use crate::site_metadata::SiteMetadata;
+
+/// Stack Exchange sites not under stackexchange.com.
+const SITES: &[&str] = &[
+    "stackoverflow.com",
+    "superuser.com",
+    "serverfault.com",
+    "askubuntu.com",
+    "mathoverflow.net",
+    "stackapps.com",
+];
+
+/// Questions on Stack Overflow and other Stack Exchange sites: score,
+/// answers and tags.
+pub struct StackOverflowExtractor;
+
+impl StackOverflowExtractor {
+    fn question_id(url: &Url) -> Option<u64> {
+        let host = url.host_str()?.trim_start_matches("www.");
+        let host = host.strip_prefix("meta.").unwrap_or(host);
+        if !SITES.contains(&host) && !host.ends_with(".stackexchange.com") {
+            return None;
+        }
+        let mut segments = url.path_segments()?;
+        match segments.next()? {
+            "questions" | "q" => segments.next()?.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl MetadataExtractor for StackOverflowExtractor {
+    fn name(&self) -> &'static str {
+        "stackoverflow"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        Self::question_id(url).is_some()
+    }
+
+    fn extract(&self, page: &Page, metadata: &mut SiteMetadata) {
+        let question_id = match Self::question_id(page.url()) {
+            Some(id) => id,
+            None => return,
+        };
+
+        // Prefer what's inside the question over answers and sidebars
+        let question: Vec<PageElement> = match page.by_id("question") {
+            Some(question) => question.descendants(),
+            None => page.elements().cloned().collect(),
+        };
+
+        let title = page
+            .by_class("question-hyperlink")
+            .next()
+            .map(|element| element.text())
+            .filter(|title| !title.is_empty());
+        let score = question
+            .iter()
+            .find(|element| element.attr("itemprop").as_deref() == Some("upvoteCount"))
+            .and_then(|element| {
+                element
+                    .attr("data-value")
+                    .and_then(|value| value.trim().parse::<i64>().ok())
+                    .or_else(|| element.value()?.trim().parse().ok())
+            });
+        let answer_count = page
+            .itemprop("answerCount")
+            .and_then(|count| parse_count(&count));
+        let accepted = page.by_itemprop("acceptedAnswer").is_some();
+        let mut tags: Vec<String> = Vec::new();
+        let tag_elements = question
+            .iter()
+            .filter(|element| element.has_class("post-tag"));
+        for tag in tag_elements.map(PageElement::text) {
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+
+        if let Some(title) = title {
+            metadata.title = title;
+        }
+        if !tags.is_empty() {
+            metadata.keywords = tags.to_owned();
+        }
+        metadata
+            .extra
+            .insert("question_id".to_owned(), json!(question_id));
+        if let Some(score) = score {
+            metadata.extra.insert("score".to_owned(), json!(score));
+        }
+        if let Some(answer_count) = answer_count {
+            metadata
+                .extra
+                .insert("answer_count".to_owned(), json!(answer_count));
+        }
+        metadata
+            .extra
+            .insert("accepted".to_owned(), json!(accepted));
+        if !tags.is_empty() {
+            metadata.extra.insert("tags".to_owned(), json!(tags));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extractors::ExtractorRegistry;
+
+    const QUESTION: &str = include_str!("../../tests/fixtures/stackoverflow.html");
+
+    #[test]
+    fn reads_question_details() {
+        let metadata = SiteMetadata::parse(
+            QUESTION,
+            "https://stackoverflow.com/questions/28909583/how-do-i-iterate-over-a-hashmap",
+            &ExtractorRegistry::default(),
+        )
+        .unwrap();
+        assert_eq!(metadata.title, "How do I iterate over a HashMap?");
+        // Tags of the question replace the site's keywords, and the sidebar's
+        // are left out
+        assert_eq!(metadata.keywords, ["rust", "hashmap"]);
+
+        let extra = &metadata.extra;
+        assert_eq!(extra["question_id"], json!(28909583));
+        assert_eq!(extra["score"], json!(42));
+        assert_eq!(extra["answer_count"], json!(3));
+        assert_eq!(extra["accepted"], json!(true));
+        assert_eq!(extra["tags"], json!(["rust", "hashmap"]));
+    }
+
+    #[test]
+    fn handles_stack_exchange_sites() {
+        let id = |url: &str| StackOverflowExtractor::question_id(&Url::parse(url).unwrap());
+        assert_eq!(id("https://superuser.com/q/123"), Some(123));
+        assert_eq!(
+            id("https://unix.stackexchange.com/questions/456/title"),
+            Some(456)
+        );
+        assert_eq!(
+            id("https://meta.stackoverflow.com/questions/789"),
+            Some(789)
+        );
+        assert_eq!(id("https://stackoverflow.com/users/1"), None);
+        assert_eq!(id("https://example.com/questions/1"), None);
+    }
+}
@@ -0,0 +1,178 @@
+use serde_json::json;
+use url::Url;
+
+use super::{MetadataExtractor, Page};
+use crate::site_metadata::{self, SiteMetadata, SiteType};
+
+/// YouTube videos: duration, channel and upload date.
+pub struct YoutubeExtractor;
+
+impl YoutubeExtractor {
+    fn video_id(url: &Url) -> Option<String> {
+        let host = url.host_str()?;
+        let mut segments = url.path_segments()?;
+        let id = match host {
+            "youtu.be" => segments.next()?.to_owned(),
+            "youtube.com" | "www.youtube.com" | "m.youtube.com" => match segments.next()? {
+                "watch" => url
+                    .query_pairs()
+                    .find(|(key, _)| key == "v")
+                    .map(|(_, id)| id.into_owned())?,
+                "shorts" | "embed" | "live" => segments.next()?.to_owned(),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        (!id.is_empty()).then_some(id)
+    }
+}
+
+impl MetadataExtractor for YoutubeExtractor {
+    fn name(&self) -> &'static str {
+        "youtube"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        Self::video_id(url).is_some()
+    }
+
+    fn extract(&self, page: &Page, metadata: &mut SiteMetadata) {
+        let video_id = match Self::video_id(page.url()) {
+            Some(id) => id,
+            None => return,
+        };
+
+        // The channel is described by nested properties of the author
+        let channel = page.by_itemprop("author").map(|author| {
+            let property = |name: &str| {
+                author
+                    .descendants()
+                    .into_iter()
+                    .find(|element| element.attr("itemprop").as_deref() == Some(name))
+                    .and_then(|element| element.value())
+            };
+            (property("name"), property("url"))
+        });
+        let (channel, channel_url) = channel.unwrap_or_default();
+        let duration = page
+            .itemprop("duration")
+            .and_then(|duration| parse_duration(&duration));
+        let published_at = page
+            .itemprop("datePublished")
+            .or_else(|| page.itemprop("uploadDate"))
+            .and_then(|date| site_metadata::parse_timestamp(&date));
+
+        if let Some(title) = page.itemprop("name").or_else(|| page.meta("og:title")) {
+            metadata.title = title;
+        }
+        if metadata.site_type == SiteType::Website {
+            metadata.site_type = SiteType::VideoOther;
+        }
+        if let Some(channel) = &channel {
+            metadata.author = Some(channel.to_owned());
+        }
+        if published_at.is_some() {
+            metadata.published_at = published_at;
+        }
+        metadata
+            .extra
+            .insert("video_id".to_owned(), json!(video_id));
+        if let Some(duration) = duration {
+            metadata
+                .extra
+                .insert("duration".to_owned(), json!(duration));
+        }
+        if let Some(channel) = channel {
+            metadata.extra.insert("channel".to_owned(), json!(channel));
+        }
+        if let Some(channel_url) = channel_url {
+            metadata
+                .extra
+                .insert("channel_url".to_owned(), json!(channel_url));
+        }
+    }
+}
+
+/// Parses ISO 8601 durations like `PT1H2M3S` into seconds.
+fn parse_duration(duration: &str) -> Option<u64> {
+    let duration = duration.trim().strip_prefix('P')?;
+    let mut seconds = 0;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in duration.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => in_time = true,
+            _ => {
+                let value: u64 = number.parse().ok()?;
+                number.clear();
+                seconds += value
+                    * match (c, in_time) {
+                        ('W', false) => 7 * 24 * 3600,
+                        ('D', false) => 24 * 3600,
+                        ('H', true) => 3600,
+                        ('M', true) => 60,
+                        ('S', true) => 1,
+                        _ => return None,
+                    };
+            }
+        }
+    }
+    number.is_empty().then_some(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::extractors::ExtractorRegistry;
+
+    const VIDEO: &str = include_str!("../../tests/fixtures/youtube.html");
+
+    #[test]
+    fn reads_video_details() {
+        let metadata = SiteMetadata::parse(
+            VIDEO,
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            &ExtractorRegistry::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            metadata.title,
+            "Rick Astley - Never Gonna Give You Up (Official Music Video)"
+        );
+        assert_eq!(metadata.site_type, SiteType::VideoOther);
+        assert_eq!(metadata.author.as_deref(), Some("Rick Astley"));
+        assert_eq!(metadata.published_at, Some(1256453853));
+
+        let extra = &metadata.extra;
+        assert_eq!(extra["video_id"], json!("dQw4w9WgXcQ"));
+        assert_eq!(extra["duration"], json!(213));
+        assert_eq!(extra["channel"], json!("Rick Astley"));
+        assert_eq!(
+            extra["channel_url"],
+            json!("http://www.youtube.com/@RickAstleyYT")
+        );
+    }
+
+    #[test]
+    fn finds_video_ids() {
+        let id = |url: &str| YoutubeExtractor::video_id(&Url::parse(url).unwrap());
+        assert_eq!(id("https://youtu.be/abc123").as_deref(), Some("abc123"));
+        assert_eq!(
+            id("https://m.youtube.com/shorts/abc123").as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(id("https://www.youtube.com/watch?list=x"), None);
+        assert_eq!(id("https://www.youtube.com/@RickAstleyYT"), None);
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("PT1H2M3S"), Some(3723));
+        assert_eq!(parse_duration("P1DT1S"), Some(86401));
+        assert_eq!(parse_duration("PT5"), None);
+        assert_eq!(parse_duration("3:33"), None);
+    }
+}
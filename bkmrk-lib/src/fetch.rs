@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use curl::easy::Easy;
+use eyre::Result;
+
+/// A fetched resource.
+pub struct Response {
+    /// URL the response came from, after redirects
+    pub url: String,
+    pub status: u32,
+    /// Media type without parameters, e.g. `text/html`
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// Time allowed for a request unless the caller picks another.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Downloads `url`, following redirects. Responses with error statuses are
+/// returned like any other.
+pub fn fetch(url: &str) -> Result<Response> {
+    fetch_with_timeout(url, DEFAULT_TIMEOUT)
+}
+
+/// Like [`fetch`], giving up after `timeout`.
+pub fn fetch_with_timeout(url: &str, timeout: Duration) -> Result<Response> {
+    let mut easy = Easy::new();
+    easy.url(url)?;
+    easy.ssl_verify_peer(false)?;
+    easy.ssl_verify_host(false)?;
+    easy.follow_location(true)?;
+    easy.max_redirections(5)?;
+    easy.timeout(timeout)?;
+    easy.useragent(concat!("bkmrk/", env!("CARGO_PKG_VERSION")))?;
+
+    let mut body = Vec::new();
+    {
+        let mut transfer = easy.transfer();
+        transfer.write_function(|data| {
+            body.extend_from_slice(data);
+            Ok(data.len())
+        })?;
+        transfer.perform()?;
+    }
+
+    Ok(Response {
+        url: easy.effective_url()?.unwrap_or(url).to_owned(),
+        status: easy.response_code()?,
+        content_type: easy
+            .content_type()?
+            .and_then(|t| t.split(';').next())
+            .map(|t| t.trim().to_lowercase()),
+        body,
+    })
+}
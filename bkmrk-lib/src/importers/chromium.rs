@@ -0,0 +1,155 @@
+use chrono::Utc;
+use eyre::{Result, WrapErr};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{bookmark::TagList, site_metadata::SiteMetadata, tags, Bookmark};
+
+/// Seconds between the WebKit epoch (1601-01-01) and the Unix epoch.
+const WEBKIT_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+#[derive(Deserialize)]
+struct BookmarksFile {
+    roots: serde_json::Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct Node {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    url: Option<String>,
+    date_added: Option<String>,
+    date_modified: Option<String>,
+    #[serde(default)]
+    children: Vec<Node>,
+}
+
+/// Reads bookmarks from the `Bookmarks` JSON file of Chromium based browsers
+/// like Chrome, Edge and Brave.
+///
+/// With `append_folder_tags`, the path of the folder a bookmark is in, starting
+/// with its root such as "Bookmarks bar", is added as a hierarchical tag like
+/// `Bookmarks bar/Dev/Rust`.
+pub fn parse_str(contents: &str, append_folder_tags: bool) -> Result<Vec<Bookmark>> {
+    let file: BookmarksFile =
+        serde_json::from_str(contents).wrap_err("Couldn't parse Chromium bookmarks file")?;
+
+    let mut bookmarks = Vec::new();
+    for root in file.roots.into_iter().map(|(_, root)| root) {
+        // Besides the bookmark roots, older versions keep a sync version here.
+        if let Ok(node) = serde_json::from_value::<Node>(root) {
+            collect_bookmarks(&node, &mut Vec::new(), append_folder_tags, &mut bookmarks);
+        }
+    }
+
+    Ok(bookmarks)
+}
+
+fn collect_bookmarks(
+    node: &Node,
+    folders: &mut Vec<String>,
+    append_folder_tags: bool,
+    bookmarks: &mut Vec<Bookmark>,
+) {
+    match (node.kind.as_str(), &node.url) {
+        ("url", Some(url)) => {
+            let added_at = webkit_to_unix(node.date_added.as_deref())
+                .unwrap_or_else(|| Utc::now().timestamp());
+            let tags = match append_folder_tags {
+                true => tags::from_path(folders.iter()).into_iter().collect(),
+                false => Vec::new(),
+            };
+
+            bookmarks.push(Bookmark {
+                link: url.to_owned(),
+                added_at,
+                last_modified: webkit_to_unix(node.date_modified.as_deref())
+                    .unwrap_or(added_at)
+                    .max(added_at),
+                metadata: SiteMetadata {
+                    title: match node.name.is_empty() {
+                        true => url.to_owned(),
+                        false => node.name.to_owned(),
+                    },
+                    ..Default::default()
+                },
+                tags: TagList(tags),
+                ..Default::default()
+            });
+        }
+        ("folder", _) => {
+            folders.push(node.name.to_owned());
+            for child in &node.children {
+                collect_bookmarks(child, folders, append_folder_tags, bookmarks);
+            }
+            folders.pop();
+        }
+        _ => {}
+    }
+}
+
+/// Converts a WebKit timestamp, in microseconds since 1601-01-01, to a Unix
+/// timestamp in seconds.
+fn webkit_to_unix(timestamp: Option<&str>) -> Option<i64> {
+    match timestamp?.parse::<i64>().ok()? {
+        ts if ts > 0 => Some(ts / 1_000_000 - WEBKIT_EPOCH_OFFSET),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOKMARKS: &str = include_str!("../../tests/fixtures/chromium_bookmarks.json");
+
+    fn find<'a>(bookmarks: &'a [Bookmark], link: &str) -> &'a Bookmark {
+        bookmarks.iter().find(|b| b.link == link).unwrap()
+    }
+
+    #[test]
+    fn reads_bookmarks_in_all_roots() {
+        let before = Utc::now().timestamp();
+        let bookmarks = parse_str(BOOKMARKS, false).unwrap();
+        assert_eq!(bookmarks.len(), 3);
+
+        let tokio = find(&bookmarks, "https://tokio.rs/");
+        assert_eq!(tokio.metadata.title, "Tokio");
+        assert_eq!(tokio.added_at, 1609459200);
+        assert_eq!(tokio.last_modified, 1609545600);
+        assert!(tokio.tags.0.is_empty());
+
+        let async_std = find(&bookmarks, "https://async.rs/");
+        assert_eq!(async_std.added_at, 1612137600);
+        assert_eq!(async_std.last_modified, 1612137600);
+
+        let untitled = find(&bookmarks, "https://example.com/");
+        assert_eq!(untitled.metadata.title, "https://example.com/");
+        assert!(untitled.added_at >= before);
+    }
+
+    #[test]
+    fn tags_bookmarks_with_their_folders() {
+        let bookmarks = parse_str(BOOKMARKS, true).unwrap();
+
+        assert_eq!(
+            find(&bookmarks, "https://tokio.rs/").tags.0,
+            ["Bookmarks bar"]
+        );
+        assert_eq!(
+            find(&bookmarks, "https://async.rs/").tags.0,
+            ["Bookmarks bar/Dev/Rust-Async"]
+        );
+        assert_eq!(
+            find(&bookmarks, "https://example.com/").tags.0,
+            ["Other bookmarks"]
+        );
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(parse_str("{\"bookmarks\": []}", false).is_err());
+    }
+}
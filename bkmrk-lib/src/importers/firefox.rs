@@ -0,0 +1,273 @@
+use std::{collections::HashMap, path::Path};
+
+use chrono::Utc;
+use eyre::{eyre, Result, WrapErr};
+use itertools::Itertools;
+use rusqlite::{Connection, OpenFlags};
+use url::Url;
+
+use crate::{bookmark::TagList, site_metadata::SiteMetadata, tags, Bookmark};
+
+const ROOT_GUID: &str = "root________";
+const TAGS_GUID: &str = "tags________";
+
+const TYPE_BOOKMARK: i64 = 1;
+const TYPE_FOLDER: i64 = 2;
+
+struct Folder {
+    parent: i64,
+    title: String,
+    guid: String,
+}
+
+/// Reads bookmarks from a Firefox profile's `places.sqlite`.
+///
+/// Firefox tags and keywords both become tags. With `append_folder_tags`, the
+/// path of the folder a bookmark is in is added as a hierarchical tag like
+/// `Bookmarks Menu/Dev/Rust`.
+pub fn parse_file(file_path: &Path, append_folder_tags: bool) -> Result<Vec<Bookmark>> {
+    let conn = open(file_path)?;
+
+    let folders = get_folders(&conn)?;
+    let tags_root = folders
+        .iter()
+        .find(|(_, folder)| folder.guid == TAGS_GUID)
+        .map(|(&id, _)| id);
+
+    let mut place_tags = get_place_tags(&conn, tags_root)?;
+    for (place_id, keyword) in get_keywords(&conn)? {
+        place_tags.entry(place_id).or_default().push(keyword);
+    }
+
+    let description_column = match conn.prepare("SELECT description FROM moz_places LIMIT 0") {
+        Ok(_) => "p.description",
+        Err(_) => "NULL",
+    };
+    let mut statement = conn.prepare(&format!(
+        "SELECT b.parent, b.title, b.dateAdded, b.lastModified, p.id, p.url, p.title, {}
+        FROM moz_bookmarks AS b, moz_places AS p
+        WHERE b.fk = p.id AND b.type = ?1
+        ORDER BY b.parent, b.position",
+        description_column
+    ))?;
+
+    let mut rows = statement.query([TYPE_BOOKMARK])?;
+    let mut bookmarks = Vec::new();
+    while let Some(row) = rows.next()? {
+        let parent: i64 = row.get(0)?;
+        let place_id: i64 = row.get(4)?;
+        let link: String = row.get(5)?;
+
+        // Entries inside tag folders only mark their place as tagged, and
+        // `place:` links are saved searches rather than bookmarks.
+        let in_tag_folder = folders
+            .get(&parent)
+            .map(|folder| Some(folder.parent) == tags_root)
+            .unwrap_or(false);
+        if in_tag_folder || link.starts_with("place:") {
+            continue;
+        }
+
+        let mut tags = place_tags.get(&place_id).cloned().unwrap_or_default();
+        if append_folder_tags {
+            tags.extend(tags::from_path(folder_path(&folders, parent)));
+        }
+        let tags = tags.into_iter().unique().collect();
+
+        let title: Option<String> = row.get(1)?;
+        let place_title: Option<String> = row.get(6)?;
+        // Firefox stores times in microseconds.
+        let added_at = match row.get::<_, Option<i64>>(2)? {
+            Some(ts) if ts > 0 => ts / 1_000_000,
+            _ => Utc::now().timestamp(),
+        };
+        let last_modified = match row.get::<_, Option<i64>>(3)? {
+            Some(ts) if ts > 0 => ts / 1_000_000,
+            _ => added_at,
+        };
+
+        bookmarks.push(Bookmark {
+            metadata: SiteMetadata {
+                title: title
+                    .filter(|t| !t.is_empty())
+                    .or(place_title)
+                    .unwrap_or_else(|| link.to_owned()),
+                description: row.get(7)?,
+                ..Default::default()
+            },
+            link,
+            added_at,
+            last_modified: last_modified.max(added_at),
+            tags: TagList(tags),
+            ..Default::default()
+        });
+    }
+
+    Ok(bookmarks)
+}
+
+/// Opens the database read only. `immutable` lets us read it while Firefox
+/// is running and holding its lock.
+fn open(file_path: &Path) -> Result<Connection> {
+    let file_path = file_path
+        .canonicalize()
+        .wrap_err_with(|| format!("ERROR: Couldn't open file {}", file_path.display()))?;
+    let mut uri = Url::from_file_path(&file_path)
+        .map_err(|_| eyre!("ERROR: Couldn't open file {}", file_path.display()))?;
+    uri.set_query(Some("immutable=1"));
+
+    Connection::open_with_flags(
+        uri.as_str(),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+    )
+    .wrap_err_with(|| format!("ERROR: Couldn't open database {}", file_path.display()))
+}
+
+fn get_folders(conn: &Connection) -> Result<HashMap<i64, Folder>> {
+    let mut statement =
+        conn.prepare("SELECT id, parent, title, guid FROM moz_bookmarks WHERE type = ?1")?;
+    let folders = statement
+        .query_map([TYPE_FOLDER], |row| {
+            Ok((
+                row.get(0)?,
+                Folder {
+                    parent: row.get(1)?,
+                    title: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    guid: row.get(3)?,
+                },
+            ))
+        })?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .wrap_err("Couldn't read bookmark folders. Is this a places.sqlite file?")?;
+    Ok(folders)
+}
+
+fn get_place_tags(conn: &Connection, tags_root: Option<i64>) -> Result<HashMap<i64, Vec<String>>> {
+    let mut place_tags: HashMap<i64, Vec<String>> = HashMap::new();
+    let tags_root = match tags_root {
+        Some(id) => id,
+        None => return Ok(place_tags),
+    };
+
+    let mut statement = conn.prepare(
+        "SELECT b.fk, t.title FROM moz_bookmarks AS b, moz_bookmarks AS t
+        WHERE b.parent = t.id AND t.parent = ?1 AND b.type = ?2",
+    )?;
+    let mut rows = statement.query([tags_root, TYPE_BOOKMARK])?;
+    while let Some(row) = rows.next()? {
+        if let Some(tag) = row.get::<_, Option<String>>(1)? {
+            place_tags.entry(row.get(0)?).or_default().push(tag);
+        }
+    }
+
+    Ok(place_tags)
+}
+
+fn get_keywords(conn: &Connection) -> Result<Vec<(i64, String)>> {
+    let mut statement = match conn.prepare("SELECT place_id, keyword FROM moz_keywords") {
+        Ok(statement) => statement,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let keywords = statement
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(keywords)
+}
+
+/// Names of the folders containing `folder_id`, outermost first.
+fn folder_path(folders: &HashMap<i64, Folder>, folder_id: i64) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut current = folders.get(&folder_id);
+
+    while let Some(folder) = current {
+        if folder.guid == ROOT_GUID {
+            break;
+        }
+        let name = match folder.guid.as_str() {
+            "menu________" => "Bookmarks Menu",
+            "toolbar_____" => "Bookmarks Toolbar",
+            "unfiled_____" => "Other Bookmarks",
+            "mobile______" => "Mobile Bookmarks",
+            _ => &folder.title,
+        };
+        path.push(name.to_owned());
+        current = folders.get(&folder.parent);
+    }
+
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLACES: &str = include_str!("../../tests/fixtures/places.sql");
+
+    /// Parses a `places.sqlite` built from the fixture.
+    fn parse(append_folder_tags: bool) -> Vec<Bookmark> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("places.sqlite");
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(PLACES)
+            .unwrap();
+        parse_file(&path, append_folder_tags).unwrap()
+    }
+
+    #[test]
+    fn reads_bookmarks_with_tags_and_keywords() {
+        let bookmarks = parse(false);
+        let links: Vec<_> = bookmarks.iter().map(|b| b.link.as_str()).collect();
+        assert_eq!(
+            links,
+            ["https://doc.rust-lang.org/book/", "https://tokio.rs/"]
+        );
+
+        let book = &bookmarks[0];
+        assert_eq!(book.metadata.title, "The Rust Programming Language");
+        assert_eq!(book.metadata.description, None);
+        assert_eq!(
+            (book.added_at, book.last_modified),
+            (1612137600, 1612137600)
+        );
+        assert!(book.tags.0.is_empty());
+
+        let tokio = &bookmarks[1];
+        assert_eq!(tokio.metadata.title, "Tokio");
+        assert_eq!(
+            tokio.metadata.description.as_deref(),
+            Some("Build reliable network applications")
+        );
+        assert_eq!(
+            (tokio.added_at, tokio.last_modified),
+            (1609459200, 1609545600)
+        );
+        let mut tags = tokio.tags.0.clone();
+        tags.sort();
+        assert_eq!(tags, ["async", "rust", "tk"]);
+    }
+
+    #[test]
+    fn tags_bookmarks_with_their_folders() {
+        let bookmarks = parse(true);
+
+        assert_eq!(bookmarks[0].tags.0, ["Bookmarks Menu"]);
+        assert!(bookmarks[1]
+            .tags
+            .0
+            .contains(&"Bookmarks Toolbar/Dev".to_owned()));
+        assert_eq!(bookmarks[1].tags.0.len(), 4);
+    }
+
+    #[test]
+    fn rejects_other_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("other.sqlite");
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch("CREATE TABLE things (id INTEGER);")
+            .unwrap();
+        assert!(parse_file(&path, false).is_err());
+    }
+}
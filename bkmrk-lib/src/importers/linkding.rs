@@ -0,0 +1,119 @@
+use eyre::{Result, WrapErr};
+use serde::Deserialize;
+
+use super::{parse_date_or_now, TO_READ_TAG};
+use crate::{bookmark::TagList, site_metadata::SiteMetadata, Bookmark};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Export {
+    Page { results: Vec<Entry> },
+    List(Vec<Entry>),
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    url: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: String,
+    website_title: Option<String>,
+    website_description: Option<String>,
+    #[serde(default)]
+    tag_names: Vec<String>,
+    date_added: Option<String>,
+    date_modified: Option<String>,
+    #[serde(default)]
+    unread: bool,
+}
+
+/// Reads Linkding bookmarks as returned by its `/api/bookmarks/` endpoint,
+/// either the paginated response or a plain array of bookmarks. Unread
+/// bookmarks are tagged `toread`.
+pub fn parse_str(contents: &str) -> Result<Vec<Bookmark>> {
+    let entries = match serde_json::from_str(contents).wrap_err("Couldn't parse Linkding export")? {
+        Export::Page { results } => results,
+        Export::List(entries) => entries,
+    };
+
+    let bookmarks = entries
+        .into_iter()
+        .map(|entry| {
+            let mut tags = entry.tag_names;
+            if entry.unread {
+                tags.push(TO_READ_TAG.into());
+            }
+            let added_at = parse_date_or_now(entry.date_added.as_deref());
+
+            Bookmark {
+                metadata: SiteMetadata {
+                    title: Some(entry.title)
+                        .filter(|t| !t.is_empty())
+                        .or(entry.website_title)
+                        .filter(|t| !t.is_empty())
+                        .unwrap_or_else(|| entry.url.to_owned()),
+                    description: Some(entry.description)
+                        .filter(|d| !d.is_empty())
+                        .or(entry.website_description)
+                        .filter(|d| !d.is_empty()),
+                    ..Default::default()
+                },
+                link: entry.url,
+                added_at,
+                last_modified: match entry.date_modified {
+                    Some(date) => parse_date_or_now(Some(&date)).max(added_at),
+                    None => added_at,
+                },
+                tags: TagList(tags),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    Ok(bookmarks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = include_str!("../../tests/fixtures/linkding.json");
+
+    #[test]
+    fn reads_bookmarks() {
+        let bookmarks = parse_str(EXPORT).unwrap();
+        assert_eq!(bookmarks.len(), 2);
+
+        let tokio = &bookmarks[0];
+        assert_eq!(tokio.link, "https://tokio.rs/");
+        assert_eq!(tokio.metadata.title, "Tokio - An asynchronous Rust runtime");
+        assert_eq!(
+            tokio.metadata.description.as_deref(),
+            Some("My favourite runtime")
+        );
+        assert_eq!(
+            (tokio.added_at, tokio.last_modified),
+            (1614600000, 1673344800)
+        );
+        assert_eq!(tokio.tags.0, ["rust", "async"]);
+
+        let queues = &bookmarks[1];
+        assert_eq!(queues.metadata.title, "Queues and channels");
+        assert_eq!(queues.metadata.description, None);
+        // Modified before it was added, as far as the dates go
+        assert_eq!(
+            (queues.added_at, queues.last_modified),
+            (1656658800, 1656658800)
+        );
+        assert_eq!(queues.tags.0, [TO_READ_TAG]);
+    }
+
+    #[test]
+    fn reads_plain_lists() {
+        let page: serde_json::Value = serde_json::from_str(EXPORT).unwrap();
+        let bookmarks = parse_str(&page["results"].to_string()).unwrap();
+        let links: Vec<_> = bookmarks.iter().map(|b| b.link.as_str()).collect();
+        assert_eq!(links, ["https://tokio.rs/", "https://example.com/queues"]);
+    }
+}
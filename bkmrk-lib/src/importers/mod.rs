@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+
+pub mod chromium;
+pub mod firefox;
+pub mod linkding;
+pub mod pinboard;
+pub mod pocket;
+pub mod raindrop;
+
+/// Tag given to bookmarks that a service marks as unread.
+const TO_READ_TAG: &str = "toread";
+
+/// Parses an RFC 3339 date like `2021-03-01T12:00:00Z` into a Unix timestamp,
+/// falling back to the current time.
+fn parse_date_or_now(date: Option<&str>) -> i64 {
+    date.and_then(|date| DateTime::parse_from_rfc3339(date.trim()).ok())
+        .map(|date| date.timestamp())
+        .unwrap_or_else(|| Utc::now().timestamp())
+}
+
+/// Splits `tags` on `separator`, dropping empty entries.
+fn split_tags(tags: &str, separator: char) -> Vec<String> {
+    tags.split(separator)
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+        .collect()
+}
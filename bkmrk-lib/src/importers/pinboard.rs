@@ -0,0 +1,84 @@
+use eyre::{Result, WrapErr};
+use serde::Deserialize;
+
+use super::{parse_date_or_now, split_tags, TO_READ_TAG};
+use crate::{bookmark::TagList, site_metadata::SiteMetadata, Bookmark};
+
+#[derive(Deserialize)]
+struct Post {
+    href: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    extended: String,
+    time: Option<String>,
+    #[serde(default)]
+    toread: String,
+    #[serde(default)]
+    tags: String,
+}
+
+/// Reads a Pinboard JSON export. Unread posts are tagged `toread`.
+pub fn parse_str(contents: &str) -> Result<Vec<Bookmark>> {
+    let posts: Vec<Post> =
+        serde_json::from_str(contents).wrap_err("Couldn't parse Pinboard export")?;
+
+    let bookmarks = posts
+        .into_iter()
+        .map(|post| {
+            let mut tags = split_tags(&post.tags, ' ');
+            if post.toread == "yes" {
+                tags.push(TO_READ_TAG.into());
+            }
+            let added_at = parse_date_or_now(post.time.as_deref());
+
+            Bookmark {
+                metadata: SiteMetadata {
+                    title: match post.description.is_empty() {
+                        true => post.href.to_owned(),
+                        false => post.description,
+                    },
+                    description: Some(post.extended).filter(|d| !d.is_empty()),
+                    ..Default::default()
+                },
+                link: post.href,
+                added_at,
+                last_modified: added_at,
+                tags: TagList(tags),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    Ok(bookmarks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_posts() {
+        let bookmarks = parse_str(include_str!("../../tests/fixtures/pinboard.json")).unwrap();
+        assert_eq!(bookmarks.len(), 2);
+
+        let tokio = &bookmarks[0];
+        assert_eq!(tokio.link, "https://tokio.rs/");
+        assert_eq!(tokio.metadata.title, "Tokio - An asynchronous Rust runtime");
+        assert_eq!(
+            tokio.metadata.description.as_deref(),
+            Some("Build reliable network applications without compromising speed.")
+        );
+        assert_eq!(
+            (tokio.added_at, tokio.last_modified),
+            (1614600000, 1614600000)
+        );
+        assert_eq!(tokio.tags.0, ["rust", "async", "networking"]);
+
+        let later = &bookmarks[1];
+        assert_eq!(later.metadata.title, "https://example.com/later");
+        assert_eq!(later.metadata.description, None);
+        assert_eq!(later.added_at, 1655281800);
+        assert_eq!(later.tags.0, [TO_READ_TAG]);
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use eyre::{Result, WrapErr};
+use regex::Regex;
+use serde::Deserialize;
+
+use super::{split_tags, TO_READ_TAG};
+use crate::{bookmark::TagList, site_metadata::SiteMetadata, utils, Bookmark};
+
+#[derive(Deserialize)]
+struct Row {
+    #[serde(default)]
+    title: String,
+    url: String,
+    time_added: Option<i64>,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    status: String,
+}
+
+/// Reads a Pocket export, either the older `ril_export.html` or the CSV
+/// export. Unread items are tagged `toread`.
+pub fn parse_str(contents: &str) -> Result<Vec<Bookmark>> {
+    match contents.trim_start().starts_with('<') {
+        true => parse_html(contents),
+        false => parse_csv(contents),
+    }
+}
+
+fn parse_csv(contents: &str) -> Result<Vec<Bookmark>> {
+    let mut reader = csv::Reader::from_reader(contents.as_bytes());
+
+    let mut bookmarks = Vec::new();
+    for row in reader.deserialize() {
+        let row: Row = row.wrap_err("Couldn't parse Pocket export")?;
+        let mut tags = split_tags(&row.tags, '|');
+        if row.status == "unread" {
+            tags.push(TO_READ_TAG.into());
+        }
+        bookmarks.push(make_bookmark(row.url, row.title, row.time_added, tags));
+    }
+
+    Ok(bookmarks)
+}
+
+/// The HTML export lists items as `<a href time_added tags>` under an
+/// `<h1>Unread</h1>` and an `<h1>Read Archive</h1>` section.
+fn parse_html(contents: &str) -> Result<Vec<Bookmark>> {
+    let items = Regex::new(r"(?is)<h1>(.*?)</h1>|<a\s([^>]*)>(.*?)</a>").unwrap();
+    let attributes = Regex::new(r#"(?is)([a-z_]+)\s*=\s*"([^"]*)""#).unwrap();
+
+    let mut unread = false;
+    let mut bookmarks = Vec::new();
+    for item in items.captures_iter(contents) {
+        if let Some(heading) = item.get(1) {
+            unread = heading.as_str().trim().eq_ignore_ascii_case("unread");
+            continue;
+        }
+
+        let attrs: HashMap<String, String> = attributes
+            .captures_iter(&item[2])
+            .map(|attr| (attr[1].to_lowercase(), utils::unescape_html(&attr[2])))
+            .collect();
+        let link = match attrs.get("href") {
+            Some(link) => link.to_owned(),
+            None => continue,
+        };
+
+        let mut tags = split_tags(attrs.get("tags").map(String::as_str).unwrap_or(""), ',');
+        if unread {
+            tags.push(TO_READ_TAG.into());
+        }
+        bookmarks.push(make_bookmark(
+            link,
+            utils::unescape_html(item[3].trim()),
+            attrs.get("time_added").and_then(|ts| ts.parse().ok()),
+            tags,
+        ));
+    }
+
+    Ok(bookmarks)
+}
+
+fn make_bookmark(
+    link: String,
+    title: String,
+    time_added: Option<i64>,
+    tags: Vec<String>,
+) -> Bookmark {
+    let added_at = time_added.unwrap_or_else(|| Utc::now().timestamp());
+    Bookmark {
+        metadata: SiteMetadata {
+            title: match title.is_empty() {
+                true => link.to_owned(),
+                false => title,
+            },
+            ..Default::default()
+        },
+        link,
+        added_at,
+        last_modified: added_at,
+        tags: TagList(tags),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Both exports hold the same items, in a different order.
+    fn check(bookmarks: &[Bookmark]) {
+        assert_eq!(bookmarks.len(), 3);
+        let find = |link: &str| bookmarks.iter().find(|b| b.link == link).unwrap();
+
+        let tokio = find("https://tokio.rs/");
+        assert_eq!(tokio.metadata.title, "Tokio - An asynchronous Rust runtime");
+        assert_eq!(
+            (tokio.added_at, tokio.last_modified),
+            (1614600000, 1614600000)
+        );
+        assert_eq!(tokio.tags.0, ["rust", "async"]);
+
+        let queues = find("https://example.com/queues");
+        assert_eq!(queues.added_at, 1655281800);
+        assert_eq!(queues.tags.0, [TO_READ_TAG]);
+
+        let untitled = find("https://example.com/untitled");
+        assert_eq!(untitled.metadata.title, "https://example.com/untitled");
+        assert_eq!(untitled.added_at, 1656658800);
+        assert_eq!(untitled.tags.0, ["reading"]);
+    }
+
+    #[test]
+    fn reads_csv_export() {
+        let bookmarks = parse_str(include_str!("../../tests/fixtures/pocket.csv")).unwrap();
+        check(&bookmarks);
+        assert_eq!(bookmarks[1].metadata.title, "Queues, locks, and channels");
+    }
+
+    #[test]
+    fn reads_html_export() {
+        let bookmarks = parse_str(include_str!("../../tests/fixtures/pocket.html")).unwrap();
+        check(&bookmarks);
+        assert_eq!(bookmarks[0].metadata.title, "Queues, locks & channels");
+    }
+}
@@ -0,0 +1,107 @@
+use eyre::{Result, WrapErr};
+use serde::Deserialize;
+
+use super::{parse_date_or_now, split_tags};
+use crate::{bookmark::TagList, site_metadata::SiteMetadata, tags, Bookmark};
+
+#[derive(Deserialize)]
+struct Row {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    note: String,
+    #[serde(default)]
+    excerpt: String,
+    url: String,
+    #[serde(default)]
+    folder: String,
+    #[serde(default)]
+    tags: String,
+    created: Option<String>,
+    #[serde(default)]
+    cover: String,
+}
+
+/// Reads a Raindrop.io CSV export. With `append_folder_tags`, the collection
+/// a bookmark is in is added as a tag, with nested collections as a
+/// hierarchical tag like `Dev/Rust`.
+pub fn parse_str(contents: &str, append_folder_tags: bool) -> Result<Vec<Bookmark>> {
+    let mut reader = csv::Reader::from_reader(contents.as_bytes());
+
+    let mut bookmarks = Vec::new();
+    for row in reader.deserialize() {
+        let row: Row = row.wrap_err("Couldn't parse Raindrop export")?;
+
+        let mut tags = split_tags(&row.tags, ',');
+        if append_folder_tags {
+            tags.extend(tags::from_path(row.folder.split('/')));
+        }
+        let added_at = parse_date_or_now(row.created.as_deref());
+
+        bookmarks.push(Bookmark {
+            metadata: SiteMetadata {
+                title: match row.title.is_empty() {
+                    true => row.url.to_owned(),
+                    false => row.title,
+                },
+                description: [row.note, row.excerpt].into_iter().find(|d| !d.is_empty()),
+                image_url: Some(row.cover).filter(|c| !c.is_empty()),
+                ..Default::default()
+            },
+            link: row.url,
+            added_at,
+            last_modified: added_at,
+            tags: TagList(tags),
+            ..Default::default()
+        });
+    }
+
+    Ok(bookmarks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = include_str!("../../tests/fixtures/raindrop.csv");
+
+    #[test]
+    fn reads_bookmarks() {
+        let bookmarks = parse_str(EXPORT, false).unwrap();
+        assert_eq!(bookmarks.len(), 2);
+
+        let tokio = &bookmarks[0];
+        assert_eq!(tokio.link, "https://tokio.rs/");
+        assert_eq!(tokio.metadata.title, "Tokio - An asynchronous Rust runtime");
+        assert_eq!(
+            tokio.metadata.description.as_deref(),
+            Some("My favourite runtime")
+        );
+        assert_eq!(
+            tokio.metadata.image_url.as_deref(),
+            Some("https://tokio.rs/img/tokio-social.png")
+        );
+        assert_eq!(
+            (tokio.added_at, tokio.last_modified),
+            (1614600000, 1614600000)
+        );
+        assert_eq!(tokio.tags.0, ["rust", "async"]);
+
+        let queues = &bookmarks[1];
+        assert_eq!(queues.metadata.title, "https://example.com/queues");
+        assert_eq!(
+            queues.metadata.description.as_deref(),
+            Some("Locks, queues and channels compared.")
+        );
+        assert_eq!(queues.metadata.image_url, None);
+        assert_eq!(queues.added_at, 1655281800);
+        assert!(queues.tags.0.is_empty());
+    }
+
+    #[test]
+    fn tags_bookmarks_with_their_collections() {
+        let bookmarks = parse_str(EXPORT, true).unwrap();
+        assert_eq!(bookmarks[0].tags.0, ["rust", "async", "Dev/Rust"]);
+        assert_eq!(bookmarks[1].tags.0, ["Unsorted"]);
+    }
+}
@@ -0,0 +1,64 @@
+use chrono::Utc;
+use eyre::{eyre, Result, WrapErr};
+
+use crate::Bookmark;
+
+/// Writes bookmarks as a pretty-printed JSON array.
+pub fn write_json(bookmarks: &[Bookmark]) -> Result<String> {
+    let mut out = serde_json::to_string_pretty(bookmarks)?;
+    out.push('\n');
+    Ok(out)
+}
+
+/// Writes bookmarks as JSON Lines, one bookmark object per line.
+pub fn write_jsonl(bookmarks: &[Bookmark]) -> Result<String> {
+    let mut out = String::new();
+    for bookmark in bookmarks {
+        out += &serde_json::to_string(bookmark)?;
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+pub fn parse_json(contents: &str) -> Result<Vec<Bookmark>> {
+    let bookmarks: Vec<Bookmark> =
+        serde_json::from_str(contents).wrap_err("Couldn't parse JSON bookmarks")?;
+    bookmarks
+        .into_iter()
+        .enumerate()
+        .map(|(idx, bookmark)| {
+            validate(bookmark).wrap_err_with(|| format!("Invalid bookmark at index {}", idx))
+        })
+        .collect()
+}
+
+pub fn parse_jsonl(contents: &str) -> Result<Vec<Bookmark>> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            serde_json::from_str(line)
+                .map_err(eyre::Report::from)
+                .and_then(validate)
+                .wrap_err_with(|| format!("Couldn't parse bookmark on line {}", idx + 1))
+        })
+        .collect()
+}
+
+/// Checks required fields and fills in missing timestamps, which would
+/// otherwise deserialize to 0 and date bookmarks to 1970.
+fn validate(mut bookmark: Bookmark) -> Result<Bookmark> {
+    if bookmark.link.is_empty() {
+        return Err(eyre!("Bookmark has no link"));
+    }
+
+    let time_now = Utc::now().timestamp();
+    if bookmark.added_at == 0 {
+        bookmark.added_at = time_now;
+    }
+    if bookmark.last_modified == 0 {
+        bookmark.last_modified = bookmark.added_at;
+    }
+    Ok(bookmark)
+}
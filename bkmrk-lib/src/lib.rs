@@ -1,76 +1,470 @@
-use std::path::Path;
+use std::{collections::HashMap, io::ErrorKind, path::Path};
+
+use url::Url;
 
-use bookmark::Bookmark;
 use chrono::Utc;
-use eyre::{Result, WrapErr};
+use eyre::{eyre, Result, WrapErr};
+use once_cell::unsync::OnceCell;
 
 use crate::db::Database;
+use archive::Archive;
+use autotag::{AutoTagRule, AutoTagger};
+use bookmark::Bookmark;
+use cache::{Asset, AssetKind, CacheReport, CacheStats, Eviction};
+use canonical::UrlNormalizer;
+use conflict::{ConflictStrategy, ImportOutcome};
+use link_check::{CheckOptions, LinkStatus};
+use query::BookmarkQuery;
+use refresh::RefreshOptions;
+use search::SearchResult;
+use site_metadata::SiteMetadata;
+use suggest::TagSuggestion;
+use tags::{TagChange, TagEdit, TagInfo, TagNode, TagNormalizer, TagOperation};
 
+pub mod archive;
+pub mod autotag;
 pub mod bookmark;
+pub mod cache;
+pub mod canonical;
+pub mod conflict;
 mod db;
 mod element;
+pub mod extractors;
+mod fetch;
+mod importers;
+mod json;
+pub mod link_check;
+mod migrations;
 mod netscape_parser;
+mod netscape_writer;
+mod pool;
+pub mod query;
+mod query_parser;
+mod readability;
+pub mod refresh;
+pub mod search;
 pub mod site_metadata;
+pub mod suggest;
+pub mod tags;
+#[cfg(test)]
+mod test_server;
 mod utils;
 
 pub struct BkmrkMan {
     db: Database,
+    /// Read from the rules file the first time bookmarks are tagged
+    auto_tagger: OnceCell<AutoTagger>,
 }
 
 impl BkmrkMan {
-    pub fn new() -> Self {
-        let db_path = utils::files::get_db_path().unwrap();
-        let db = Database::connect(&db_path).unwrap();
-        Self { db }
+    pub fn new() -> Result<Self> {
+        Self::with_normalizers(UrlNormalizer::default(), TagNormalizer::default())
+    }
+
+    /// Opens the bookmark database, using `normalizer` to decide which links
+    /// point to the same page.
+    pub fn with_normalizer(normalizer: UrlNormalizer) -> Result<Self> {
+        Self::with_normalizers(normalizer, TagNormalizer::default())
+    }
+
+    /// Opens the bookmark database, using `normalizer` to decide which links
+    /// point to the same page and `tag_normalizer` to decide how tags are
+    /// stored. Tagging rules are read from `~/.bkmrk/autotag.toml` when they
+    /// are first needed.
+    pub fn with_normalizers(
+        normalizer: UrlNormalizer,
+        tag_normalizer: TagNormalizer,
+    ) -> Result<Self> {
+        let db_path = utils::files::get_db_path()?;
+        let db = Database::connect(&db_path, normalizer, tag_normalizer)
+            .wrap_err("Couldn't open bookmark database")?;
+        Ok(Self {
+            db,
+            auto_tagger: OnceCell::new(),
+        })
+    }
+
+    /// Replaces the tagging rules read from the rules file.
+    pub fn with_auto_tagger(mut self, auto_tagger: AutoTagger) -> Self {
+        self.auto_tagger = OnceCell::with_value(auto_tagger);
+        self
+    }
+
+    fn auto_tagger(&self) -> Result<&AutoTagger> {
+        self.auto_tagger
+            .get_or_try_init(|| AutoTagger::load(&utils::files::get_auto_tag_rules_path()?))
     }
 
     pub fn import_bookmark_file(
         &self,
         file_path: &Path,
+        file_format: &str,
         append_folder_tags: bool,
-    ) -> Result<(usize, usize)> {
-        let bookmarks =
-            netscape_parser::parse_str(&utils::files::read_file(file_path)?, append_folder_tags)
-                .wrap_err("Failed to parse bookmark file")?;
+        strategy: ConflictStrategy,
+    ) -> Result<Vec<(Bookmark, ImportOutcome)>> {
+        let bookmarks = self.read_bookmark_file(file_path, file_format, append_folder_tags)?;
 
-        let (succeeded, failed) = self.add_bookmarks(&bookmarks)?;
-        Ok((succeeded, failed))
+        let outcomes = self.add_bookmarks(&bookmarks, strategy)?;
+        Ok(bookmarks.into_iter().zip(outcomes).collect())
     }
 
     pub fn read_bookmark_file(
         &self,
         file_path: &Path,
+        file_format: &str,
         append_folder_tags: bool,
     ) -> Result<Vec<Bookmark>> {
-        netscape_parser::parse_str(&utils::files::read_file(file_path)?, append_folder_tags)
-            .wrap_err("Failed to parse bookmark file")
+        let bookmarks = match file_format {
+            "netscape" => {
+                netscape_parser::parse_str(&utils::files::read_file(file_path)?, append_folder_tags)
+            }
+            "firefox" => importers::firefox::parse_file(file_path, append_folder_tags),
+            "chromium" | "chrome" | "brave" => importers::chromium::parse_str(
+                &utils::files::read_file(file_path)?,
+                append_folder_tags,
+            ),
+            "pinboard" => importers::pinboard::parse_str(&utils::files::read_file(file_path)?),
+            "pocket" => importers::pocket::parse_str(&utils::files::read_file(file_path)?),
+            "raindrop" => importers::raindrop::parse_str(
+                &utils::files::read_file(file_path)?,
+                append_folder_tags,
+            ),
+            "linkding" => importers::linkding::parse_str(&utils::files::read_file(file_path)?),
+            "json" => json::parse_json(&utils::files::read_file(file_path)?),
+            "jsonl" => json::parse_jsonl(&utils::files::read_file(file_path)?),
+            _ => {
+                return Err(eyre!(
+                    "ERROR: File format {} invalid / unsupported.",
+                    file_format
+                ))
+            }
+        };
+
+        bookmarks.wrap_err("Failed to parse bookmark file")
     }
 
-    pub fn get_bookmarks(&self, tags: &[String], domains: &[String]) -> Result<Vec<Bookmark>> {
-        let items = match (tags.len(), domains.len()) {
-            (0, 0) => self.db.get_all(),
-            _ => self.db.get(tags, domains),
-        };
+    pub fn export_bookmarks(
+        &self,
+        bookmarks: &[Bookmark],
+        file_format: &str,
+        tag_folders: bool,
+    ) -> Result<String> {
+        match file_format {
+            "netscape" => Ok(netscape_writer::write_string(
+                bookmarks,
+                tag_folders,
+                &self.db.tag_infos()?,
+            )),
+            "json" => json::write_json(bookmarks),
+            "jsonl" => json::write_jsonl(bookmarks),
+            _ => Err(eyre!(
+                "ERROR: File format {} invalid / unsupported.",
+                file_format
+            )),
+        }
+    }
 
-        items.wrap_err("Failed to get bookmarks from database")
+    pub fn get_bookmarks(&self, query: &BookmarkQuery) -> Result<Vec<Bookmark>> {
+        self.db
+            .get(query)
+            .wrap_err("Failed to get bookmarks from database")
     }
 
+    pub fn search(&self, query: &str, limit: Option<usize>) -> Result<Vec<SearchResult>> {
+        self.db.search(query, limit)
+    }
+
+    /// Adds a bookmark, with the tags of matching tagging rules.
     pub fn add_bookmark(&self, bookmark: &Bookmark) -> Result<()> {
-        let mut bookmark = bookmark.to_owned();
+        self.add_bookmark_with_text(bookmark, None)
+    }
+
+    /// Like [`BkmrkMan::add_bookmark`], also saving the readable text of the
+    /// page, as returned by [`BkmrkMan::fetch_page`], and indexing it for
+    /// search.
+    pub fn add_bookmark_with_text(&self, bookmark: &Bookmark, text: Option<&str>) -> Result<()> {
+        let mut bookmark = self.auto_tagger()?.apply(bookmark);
         let time_now = Utc::now().timestamp();
         bookmark.added_at = time_now;
         bookmark.last_modified = time_now;
 
-        self.db.add_one(&bookmark)
+        self.db.add_one(&bookmark, text)
     }
 
-    pub fn add_bookmarks(&self, bookmark: &[Bookmark]) -> Result<(usize, usize)> {
-        self.db.add_many(bookmark)
+    /// Fetches the title, description and other details of the page at `link`
+    /// for a new bookmark, along with the readable text of the page if any
+    /// could be found.
+    pub fn fetch_page(&self, link: &str) -> Result<(SiteMetadata, Option<String>)> {
+        SiteMetadata::get_metadata_with_text(link, fetch::DEFAULT_TIMEOUT)
+    }
+
+    /// Adds bookmarks with the tags of matching tagging rules, resolving
+    /// those whose link is already saved with `strategy`. Returns the outcome
+    /// for each bookmark in order.
+    pub fn add_bookmarks(
+        &self,
+        bookmarks: &[Bookmark],
+        strategy: ConflictStrategy,
+    ) -> Result<Vec<ImportOutcome>> {
+        let auto_tagger = self.auto_tagger()?;
+        let bookmarks: Vec<_> = bookmarks
+            .iter()
+            .map(|bookmark| auto_tagger.apply(bookmark))
+            .collect();
+        self.db.add_many(&bookmarks, strategy)
     }
 
     pub fn delete_bookmarks(&self, bookmarks: &[Bookmark]) -> Result<(usize, usize)> {
-        self.db.delete_many(bookmarks)
+        let counts = self.db.delete_many(bookmarks)?;
+        for bookmark in bookmarks {
+            // Archive rows are deleted along with the bookmark
+            if self.db.archives(&bookmark.id)?.is_empty() {
+                let dir = utils::files::get_archive_dir(&bookmark.id)?;
+                if dir.exists() {
+                    std::fs::remove_dir_all(&dir).wrap_err_with(|| {
+                        format!("Couldn't delete archives in {}", dir.display())
+                    })?;
+                }
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Groups of bookmarks whose links point to the same page, along with
+    /// their canonical link. Bookmarks in a group are ordered oldest first.
+    pub fn find_duplicates(&self) -> Result<Vec<(String, Vec<Bookmark>)>> {
+        self.db.duplicates()
+    }
+
+    /// Deletes `duplicates`, moving their tags to `keep`.
+    pub fn merge_bookmarks(&self, keep: &Bookmark, duplicates: &[Bookmark]) -> Result<()> {
+        self.db.merge(keep, duplicates)
+    }
+
+    /// Probes the links of `bookmarks` concurrently and saves the results.
+    /// `on_result` is called as each link finishes, in no particular order.
+    pub fn check_links<F>(
+        &self,
+        bookmarks: &[Bookmark],
+        options: &CheckOptions,
+        mut on_result: F,
+    ) -> Result<Vec<(Bookmark, LinkStatus)>>
+    where
+        F: FnMut(&Bookmark, &LinkStatus),
+    {
+        let links: Vec<_> = bookmarks.iter().map(|b| b.link.to_owned()).collect();
+        let mut results = Vec::with_capacity(bookmarks.len());
+        let mut save_error = None;
+
+        link_check::check_concurrently(&links, options, |idx, status| {
+            let bookmark = &bookmarks[idx];
+            if let Err(e) = self.db.save_link_check(&bookmark.id, &status) {
+                save_error.get_or_insert(e);
+            }
+            on_result(bookmark, &status);
+            results.push((bookmark.to_owned(), status));
+        });
+
+        match save_error {
+            Some(e) => Err(e.wrap_err("Couldn't save link check results")),
+            None => Ok(results),
+        }
+    }
+
+    /// Latest saved link check results of the bookmarks matching `query`.
+    pub fn link_checks(&self, query: &BookmarkQuery) -> Result<Vec<(Bookmark, LinkStatus)>> {
+        self.db.link_checks(query)
+    }
+
+    /// Downloads a copy of the bookmarked page under `~/.bkmrk/archive/<id>/`.
+    pub fn archive_bookmark(&self, bookmark: &Bookmark) -> Result<Archive> {
+        let dir = utils::files::get_archive_dir(&bookmark.id)?;
+        let archive = archive::save(&bookmark.link, &dir)?;
+        self.db.add_archive(&bookmark.id, &archive)?;
+        Ok(archive)
+    }
+
+    /// Saved copies of a bookmarked page, newest first.
+    pub fn archives(&self, bookmark: &Bookmark) -> Result<Vec<Archive>> {
+        self.db.archives(&bookmark.id)
+    }
+
+    /// Downloads the favicons and preview images of `bookmarks` into
+    /// `~/.bkmrk/cache/`. Images already downloaded from the same URL for any
+    /// bookmark are reused. `on_result` is called as each URL finishes
+    /// downloading, in no particular order.
+    pub fn cache_assets<F>(
+        &self,
+        bookmarks: &[Bookmark],
+        options: &RefreshOptions,
+        on_result: F,
+    ) -> Result<CacheReport>
+    where
+        F: FnMut(&str, &Result<Asset>),
+    {
+        let dir = utils::files::get_cache_dir()?;
+        self.cache_assets_in(&dir, bookmarks, options, on_result)
+    }
+
+    fn cache_assets_in<F>(
+        &self,
+        dir: &Path,
+        bookmarks: &[Bookmark],
+        options: &RefreshOptions,
+        mut on_result: F,
+    ) -> Result<CacheReport>
+    where
+        F: FnMut(&str, &Result<Asset>),
+    {
+        let mut report = CacheReport::default();
+        // Bookmarks waiting for each URL, in the order URLs were first seen
+        let mut urls: Vec<String> = Vec::new();
+        let mut waiting: HashMap<String, Vec<(&Bookmark, AssetKind)>> = HashMap::new();
+        for bookmark in bookmarks {
+            let assets = [
+                (AssetKind::Favicon, &bookmark.metadata.favicon_url),
+                (AssetKind::Image, &bookmark.metadata.image_url),
+            ];
+            for (kind, url) in assets {
+                // Bookmarks saved by older versions may hold relative image
+                // URLs, which are fixed by fetching their metadata again
+                let url = match url.as_deref().map(str::trim) {
+                    Some(url) if Url::parse(url).is_ok() => url,
+                    _ => continue,
+                };
+                if let Some((cached_url, hash)) = self.db.bookmark_asset(&bookmark.id, kind)? {
+                    if cached_url == url {
+                        self.db.touch_asset(&hash)?;
+                        continue;
+                    }
+                }
+                if let Some(asset) = self.db.asset_for_url(url)? {
+                    self.db.link_asset(&bookmark.id, kind, url, &asset.hash)?;
+                    report.reused += 1;
+                    continue;
+                }
+                if !waiting.contains_key(url) {
+                    urls.push(url.to_owned());
+                }
+                waiting
+                    .entry(url.to_owned())
+                    .or_default()
+                    .push((bookmark, kind));
+            }
+        }
+
+        cache::download_concurrently(&urls, dir, options, |idx, downloaded| {
+            let url = &urls[idx];
+            let result = downloaded.and_then(|asset| self.save_asset(asset, url, &waiting[url]));
+            on_result(url, &result);
+            match result {
+                Ok(_) => report.downloaded += 1,
+                Err(e) => report.failed.push((url.to_owned(), format!("{:#}", e))),
+            }
+        });
+
+        Ok(report)
+    }
+
+    fn save_asset(
+        &self,
+        asset: Asset,
+        url: &str,
+        users: &[(&Bookmark, AssetKind)],
+    ) -> Result<Asset> {
+        let asset = match self.db.asset(&asset.hash)? {
+            // The same image was saved before under another extension
+            Some(existing) => {
+                if existing.path != asset.path {
+                    remove_file(&asset.path)?;
+                }
+                existing
+            }
+            None => {
+                self.db.add_asset(&asset)?;
+                asset
+            }
+        };
+        for (bookmark, kind) in users {
+            self.db.link_asset(&bookmark.id, *kind, url, &asset.hash)?;
+        }
+        Ok(asset)
+    }
+
+    /// Deletes cached assets until they take at most `max_size` bytes. Assets
+    /// no bookmark uses any more go first, then the least recently used.
+    pub fn evict_assets(&self, max_size: u64) -> Result<Eviction> {
+        let mut size = self.db.cache_stats()?.size;
+        let mut eviction = Eviction::default();
+        for (asset, used) in self.db.assets_by_last_use()? {
+            if used && size <= max_size {
+                break;
+            }
+            self.db.delete_asset(&asset.hash)?;
+            remove_file(&asset.path)?;
+            size = size.saturating_sub(asset.size);
+            eviction.removed += 1;
+            eviction.freed += asset.size;
+        }
+        Ok(eviction)
+    }
+
+    pub fn cache_stats(&self) -> Result<CacheStats> {
+        self.db.cache_stats()
+    }
+
+    /// Fetches the current metadata of a bookmarked page without changing the
+    /// bookmark. The readable text of the page is saved and indexed for search.
+    pub fn fetch_metadata(&self, bookmark: &Bookmark) -> Result<SiteMetadata> {
+        let fetched = SiteMetadata::get_metadata_with_text(&bookmark.link, fetch::DEFAULT_TIMEOUT);
+        self.save_fetched(bookmark, fetched)
+    }
+
+    /// Fetches the current metadata of `bookmarks` concurrently, like
+    /// [`BkmrkMan::fetch_metadata`]. `on_result` is called as each page
+    /// finishes, in no particular order. Results are returned in the order of
+    /// `bookmarks`.
+    pub fn fetch_all_metadata<F>(
+        &self,
+        bookmarks: &[Bookmark],
+        options: &RefreshOptions,
+        mut on_result: F,
+    ) -> Vec<(Bookmark, Result<SiteMetadata>)>
+    where
+        F: FnMut(&Bookmark, &Result<SiteMetadata>),
+    {
+        let links: Vec<_> = bookmarks.iter().map(|b| b.link.to_owned()).collect();
+        let mut results: Vec<_> = bookmarks.iter().map(|_| None).collect();
+
+        refresh::fetch_concurrently(&links, options, |idx, fetched| {
+            let bookmark = &bookmarks[idx];
+            let result = self.save_fetched(bookmark, fetched);
+            on_result(bookmark, &result);
+            results[idx] = Some((bookmark.to_owned(), result));
+        });
+
+        results.into_iter().flatten().collect()
+    }
+
+    fn save_fetched(
+        &self,
+        bookmark: &Bookmark,
+        fetched: Result<(SiteMetadata, Option<String>)>,
+    ) -> Result<SiteMetadata> {
+        let (metadata, text) = fetched?;
+        if let Some(text) = text {
+            self.db
+                .save_content(&bookmark.id, &text)
+                .wrap_err("Couldn't save page content")?;
+        }
+        Ok(metadata)
+    }
+
+    /// Readable text of a bookmarked page, if it has been fetched.
+    pub fn page_text(&self, bookmark: &Bookmark) -> Result<Option<String>> {
+        self.db.content(&bookmark.id)
     }
+
     pub fn update_bookmark_name(&self, old: &Bookmark, updated_val: &str) -> Result<()> {
         self.db.update_name(old, updated_val)
     }
@@ -86,6 +480,11 @@ impl BkmrkMan {
     pub fn update_bookmark_image_url(&self, old: &Bookmark, updated_val: &str) -> Result<()> {
         self.db.update_image_url(old, updated_val)
     }
+    /// Replaces the metadata other than title, description, image and type
+    /// with that of `metadata`.
+    pub fn update_bookmark_details(&self, old: &Bookmark, metadata: &SiteMetadata) -> Result<()> {
+        self.db.update_details(old, metadata)
+    }
     pub fn update_bookmark_tags(
         &self,
         old: &Bookmark,
@@ -94,15 +493,332 @@ impl BkmrkMan {
         self.db.update_tags(old, updated_val)
     }
 
+    /// Renames a tag and its descendants. Bookmarks that already have the new
+    /// tag just lose the old one. Returns the number of bookmarks changed.
     pub fn tag_rename(&self, tag_name: &str, new_tag_name: &str) -> Result<usize> {
-        self.db.tag_rename(tag_name, new_tag_name)
+        let operation = TagOperation::Merge {
+            sources: vec![tag_name.to_owned()],
+            into: new_tag_name.to_owned(),
+        };
+        let query = BookmarkQuery::new().tags_any([tag_name]);
+        let edits = self.tag_edits(&operation, &query)?;
+        self.apply_tag_edits(&edits)?;
+        if let (Some(old), Some(new)) = (
+            self.normalize_tag(tag_name)?,
+            self.normalize_tag(new_tag_name)?,
+        ) {
+            self.db.move_tag_infos(&old, &new)?;
+        }
+        Ok(edits.len())
     }
 
+    /// The changes `operation` would make to the tags of the bookmarks
+    /// matching `query`, one per bookmark that changes. The tags of the
+    /// operation are normalized first.
+    pub fn tag_edits(
+        &self,
+        operation: &TagOperation,
+        query: &BookmarkQuery,
+    ) -> Result<Vec<TagEdit>> {
+        let operation = operation.map_tags(|tag| {
+            self.db
+                .normalize_tag(tag)?
+                .ok_or_else(|| eyre!("\"{}\" isn't a valid tag", tag))
+        })?;
+        let edits = self
+            .get_bookmarks(query)?
+            .iter()
+            .filter_map(|bookmark| TagEdit::new(bookmark, &operation.apply(&bookmark.tags.0)))
+            .collect();
+        Ok(edits)
+    }
+
+    /// Applies edits from [`Self::tag_edits`], all or none of them.
+    pub fn apply_tag_edits(&self, edits: &[TagEdit]) -> Result<()> {
+        self.db
+            .apply_tag_edits(edits)
+            .wrap_err("Couldn't update tags")
+    }
+
+    /// Removes `tag_name` and its descendants from all bookmarks. The tag is
+    /// normalized first, so an alias deletes the tag it stands for.
     pub fn tag_delete(&self, tag_name: &str) -> Result<usize> {
-        self.db.tag_delete(tag_name)
+        let tag = self
+            .normalize_tag(tag_name)?
+            .ok_or_else(|| eyre!("\"{}\" isn't a valid tag", tag_name))?;
+        self.db.tag_delete(&tag)
     }
 
     pub fn tag_counts(&self) -> Result<Vec<(String, usize)>> {
         self.db.tag_counts()
     }
+
+    /// Applies the normalization rules and aliases to `tag`, the way tags are
+    /// stored. Returns `None` if nothing is left of the tag.
+    pub fn normalize_tag(&self, tag: &str) -> Result<Option<String>> {
+        self.db.normalize_tag(tag)
+    }
+
+    pub fn tag_aliases(&self) -> Result<Vec<(String, String)>> {
+        self.db.tag_aliases()
+    }
+
+    /// Makes `alias` a synonym of `tag`, so that it's stored and searched as
+    /// `tag`. Saved bookmarks keep their tags until [`Self::normalize_tags`].
+    /// Returns the normalized alias and tag.
+    pub fn add_tag_alias(&self, alias: &str, tag: &str) -> Result<(String, String)> {
+        self.db.add_tag_alias(alias, tag)
+    }
+
+    pub fn remove_tag_alias(&self, alias: &str) -> Result<bool> {
+        self.db.remove_tag_alias(alias)
+    }
+
+    /// Saved tags that the normalization rules and aliases would rewrite.
+    pub fn tag_changes(&self) -> Result<Vec<TagChange>> {
+        self.db.tag_changes()
+    }
+
+    /// Rewrites saved tags as listed by [`Self::tag_changes`].
+    pub fn normalize_tags(&self, changes: &[TagChange]) -> Result<usize> {
+        self.db.apply_tag_changes(changes)
+    }
+
+    /// All tags arranged by their hierarchy, with counts rolled up from
+    /// descendants.
+    pub fn tag_tree(&self) -> Result<Vec<TagNode>> {
+        Ok(tags::build_tree(
+            &self.db.tag_pairs()?,
+            &self.db.tag_infos()?,
+        ))
+    }
+
+    /// Suggests tags for `bookmark` from the tags of similar saved bookmarks,
+    /// most likely first. See [`suggest::suggest_tags`] for how bookmarks are
+    /// compared. Everything is computed from the saved bookmarks.
+    pub fn suggest_tags(&self, bookmark: &Bookmark) -> Result<Vec<TagSuggestion>> {
+        let mut bookmark = bookmark.to_owned();
+        let mut tags = Vec::new();
+        for tag in &bookmark.tags.0 {
+            tags.extend(self.normalize_tag(tag)?);
+        }
+        bookmark.tags.0 = tags;
+
+        let library = self.get_bookmarks(&BookmarkQuery::new())?;
+        Ok(suggest::suggest_tags(&bookmark, &library))
+    }
+
+    pub fn auto_tag_rules(&self) -> Result<&[AutoTagRule]> {
+        Ok(self.auto_tagger()?.rules())
+    }
+
+    /// The tags that the tagging rules would add to the bookmarks matching
+    /// `query`, one edit per bookmark missing some of them.
+    pub fn auto_tag_edits(&self, query: &BookmarkQuery) -> Result<Vec<TagEdit>> {
+        let mut edits = Vec::new();
+        for bookmark in self.get_bookmarks(query)? {
+            if let Some(edit) = self.auto_tag_edit(&bookmark)? {
+                edits.push(edit);
+            }
+        }
+        Ok(edits)
+    }
+
+    /// Adds the tags of the tagging rules matching `bookmark`, e.g. after
+    /// its metadata was refreshed. Returns the tags added.
+    pub fn auto_tag_bookmark(&self, bookmark: &Bookmark) -> Result<Vec<String>> {
+        match self.auto_tag_edit(bookmark)? {
+            Some(edit) => {
+                self.apply_tag_edits(std::slice::from_ref(&edit))?;
+                Ok(edit.added)
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn auto_tag_edit(&self, bookmark: &Bookmark) -> Result<Option<TagEdit>> {
+        let mut tags = bookmark.tags.0.to_owned();
+        for tag in self.auto_tagger()?.tags_for(bookmark) {
+            if let Some(tag) = self.normalize_tag(&tag)? {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+        }
+        Ok(TagEdit::new(bookmark, &tags))
+    }
+
+    /// Descriptions, colors, icons and pins of tags, by tag.
+    pub fn tag_infos(&self) -> Result<HashMap<String, TagInfo>> {
+        self.db.tag_infos()
+    }
+
+    /// Saves `info` for its tag, after normalizing the tag and color. Returns
+    /// the saved info.
+    pub fn set_tag_info(&self, info: &TagInfo) -> Result<TagInfo> {
+        let mut info = info.to_owned();
+        info.tag = self
+            .normalize_tag(&info.tag)?
+            .ok_or_else(|| eyre!("\"{}\" isn't a valid tag", info.tag))?;
+        info.color = info.color.as_deref().map(tags::parse_color).transpose()?;
+        self.db.save_tag_info(&info)?;
+        Ok(info)
+    }
+}
+
+/// Deletes a file, which may already be gone.
+fn remove_file(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            Err(e).wrap_err_with(|| format!("Couldn't delete {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::test_server::{Response, TestServer};
+
+    const ICON: &[u8] = b"\x89PNG icon";
+    const IMAGE: &[u8] = b"\x89PNG a larger preview image";
+
+    fn man() -> BkmrkMan {
+        let db = Database::connect(
+            Path::new(":memory:"),
+            UrlNormalizer::new(),
+            TagNormalizer::new(),
+        )
+        .unwrap();
+        BkmrkMan {
+            db,
+            auto_tagger: OnceCell::with_value(AutoTagger::empty()),
+        }
+    }
+
+    fn add(man: &BkmrkMan, link: &str, favicon_url: Option<String>, image_url: Option<String>) {
+        let mut bookmark = Bookmark::sample(link, link, &[]);
+        bookmark.metadata.favicon_url = favicon_url;
+        bookmark.metadata.image_url = image_url;
+        man.add_bookmark(&bookmark).unwrap();
+    }
+
+    /// Saved bookmarks ordered by link.
+    fn bookmarks(man: &BkmrkMan) -> Vec<Bookmark> {
+        let mut bookmarks = man.get_bookmarks(&BookmarkQuery::new()).unwrap();
+        bookmarks.sort_by(|a, b| a.link.cmp(&b.link));
+        bookmarks
+    }
+
+    fn options() -> RefreshOptions {
+        RefreshOptions {
+            host_delay: Duration::ZERO,
+            ..Default::default()
+        }
+    }
+
+    fn files(dir: &Path) -> usize {
+        std::fs::read_dir(dir).map_or(0, |entries| entries.count())
+    }
+
+    /// Serves the same icon at `/a.png` and `/b.png` and an image at
+    /// `/image.png`.
+    fn server() -> TestServer {
+        TestServer::start(|_, path| match path {
+            "/image.png" => Response::ok("image/png", IMAGE),
+            _ => Response::ok("image/png", ICON),
+        })
+    }
+
+    #[test]
+    fn stores_identical_assets_once() {
+        let server = server();
+        let dir = tempfile::tempdir().unwrap();
+        let man = man();
+        add(&man, "https://a.example", Some(server.url("/a.png")), None);
+        add(&man, "https://b.example", Some(server.url("/b.png")), None);
+        let bookmarks = bookmarks(&man);
+
+        let report = man
+            .cache_assets_in(dir.path(), &bookmarks, &options(), |_, _| {})
+            .unwrap();
+        assert_eq!(report.downloaded, 2);
+        assert!(report.failed.is_empty());
+        assert_eq!(man.cache_stats().unwrap().assets, 1);
+        assert_eq!(files(dir.path()), 1);
+
+        let hashes: Vec<_> = bookmarks
+            .iter()
+            .map(|bookmark| man.db.bookmark_asset(&bookmark.id, AssetKind::Favicon))
+            .map(|asset| asset.unwrap().unwrap().1)
+            .collect();
+        assert_eq!(hashes[0], hashes[1]);
+
+        let report = man
+            .cache_assets_in(dir.path(), &bookmarks, &options(), |_, _| {})
+            .unwrap();
+        assert_eq!(report.downloaded, 0);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn evicts_unused_assets_first() {
+        let server = server();
+        let dir = tempfile::tempdir().unwrap();
+        let man = man();
+        add(
+            &man,
+            "https://a.example",
+            None,
+            Some(server.url("/image.png")),
+        );
+        add(&man, "https://b.example", Some(server.url("/a.png")), None);
+        let bookmarks = bookmarks(&man);
+        man.cache_assets_in(dir.path(), &bookmarks, &options(), |_, _| {})
+            .unwrap();
+        let stats = man.cache_stats().unwrap();
+        assert_eq!(stats.assets, 2);
+
+        // The larger image would go first if both were used
+        man.db.delete_many(&bookmarks[1..]).unwrap();
+        let eviction = man.evict_assets(stats.size - 1).unwrap();
+        assert_eq!(eviction.removed, 1);
+        assert_eq!(eviction.freed, ICON.len() as u64);
+        assert_eq!(files(dir.path()), 1);
+        assert!(man
+            .db
+            .bookmark_asset(&bookmarks[0].id, AssetKind::Image)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn evicts_everything_down_to_zero() {
+        let server = server();
+        let dir = tempfile::tempdir().unwrap();
+        let man = man();
+        add(
+            &man,
+            "https://a.example",
+            Some(server.url("/a.png")),
+            Some(server.url("/image.png")),
+        );
+        let bookmarks = bookmarks(&man);
+        man.cache_assets_in(dir.path(), &bookmarks, &options(), |_, _| {})
+            .unwrap();
+
+        let eviction = man.evict_assets(0).unwrap();
+        assert_eq!(eviction.removed, 2);
+        assert_eq!(eviction.freed, (ICON.len() + IMAGE.len()) as u64);
+        assert_eq!(man.cache_stats().unwrap().assets, 0);
+        assert_eq!(files(dir.path()), 0);
+        assert!(man
+            .db
+            .bookmark_asset(&bookmarks[0].id, AssetKind::Favicon)
+            .unwrap()
+            .is_none());
+    }
 }
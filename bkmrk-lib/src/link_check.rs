@@ -0,0 +1,239 @@
+use std::{thread, time::Duration};
+
+use chrono::Utc;
+use curl::easy::Easy;
+
+use crate::pool;
+
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+    /// Number of links probed at the same time
+    pub parallelism: usize,
+    /// Time allowed for each request, including redirects
+    pub timeout: Duration,
+    /// Extra attempts for links that fail with a network error or a 429 / 5xx
+    /// status
+    pub retries: u32,
+    /// Redirects followed before giving up
+    pub max_redirects: u32,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        Self {
+            parallelism: 8,
+            timeout: Duration::from_secs(15),
+            retries: 1,
+            max_redirects: 10,
+        }
+    }
+}
+
+/// Result of probing a bookmark's link.
+#[derive(Debug, Clone, Default)]
+pub struct LinkStatus {
+    /// HTTP status of the final response. `None` if no response was received.
+    pub status: Option<u32>,
+    /// Where the link ended up, if it was redirected
+    pub final_url: Option<String>,
+    /// Why no response was received
+    pub error: Option<String>,
+    pub checked_at: i64,
+}
+
+impl LinkStatus {
+    pub fn is_ok(&self) -> bool {
+        matches!(self.status, Some(200..=399))
+    }
+
+    pub fn is_broken(&self) -> bool {
+        !self.is_ok()
+    }
+
+    pub fn is_redirected(&self) -> bool {
+        self.final_url.is_some()
+    }
+}
+
+/// Probes `links` on `options.parallelism` threads, calling `on_result` on the
+/// calling thread with each link's index as soon as it has been checked.
+pub(crate) fn check_concurrently<F>(links: &[String], options: &CheckOptions, on_result: F)
+where
+    F: FnMut(usize, LinkStatus),
+{
+    let probe_options = options.clone();
+    pool::run_concurrently(
+        links.to_vec(),
+        options.parallelism,
+        move |link| probe(&link, &probe_options),
+        on_result,
+    );
+}
+
+/// Checks a single link, retrying transient failures.
+pub fn probe(link: &str, options: &CheckOptions) -> LinkStatus {
+    let mut attempt = 0;
+    loop {
+        let status = probe_once(link, options);
+        let transient = matches!(status.status, None | Some(429) | Some(500..=599));
+        if !transient || attempt >= options.retries {
+            return status;
+        }
+        attempt += 1;
+        thread::sleep(Duration::from_millis(500 * attempt as u64));
+    }
+}
+
+/// Sends a `HEAD` request, falling back to `GET` for servers that answer
+/// `HEAD` with an error status.
+fn probe_once(link: &str, options: &CheckOptions) -> LinkStatus {
+    let head = request(link, options, true);
+    match head.status {
+        Some(status) if status >= 400 => request(link, options, false),
+        _ => head,
+    }
+}
+
+fn request(link: &str, options: &CheckOptions, head: bool) -> LinkStatus {
+    let checked_at = Utc::now().timestamp();
+    let mut easy = Easy::new();
+    let result = (|| {
+        easy.url(link)?;
+        easy.nobody(head)?;
+        easy.follow_location(true)?;
+        easy.max_redirections(options.max_redirects)?;
+        easy.timeout(options.timeout)?;
+        easy.useragent(concat!("bkmrk/", env!("CARGO_PKG_VERSION")))?;
+        easy.write_function(|data| Ok(data.len()))?;
+        easy.perform()?;
+        let status = easy.response_code()?;
+        let final_url = match easy.redirect_count()? {
+            0 => None,
+            _ => easy.effective_url()?.map(|url| url.to_owned()),
+        };
+        Ok::<_, curl::Error>((status, final_url))
+    })();
+
+    match result {
+        Ok((status, final_url)) => LinkStatus {
+            status: Some(status),
+            final_url,
+            error: None,
+            checked_at,
+        },
+        Err(e) => LinkStatus {
+            status: None,
+            final_url: None,
+            error: Some(e.to_string()),
+            checked_at,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::test_server::{Response, TestServer};
+
+    fn options() -> CheckOptions {
+        CheckOptions {
+            timeout: Duration::from_secs(5),
+            retries: 0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reports_ok_links() {
+        let server = TestServer::start(|_, _| Response::ok("text/html", "<p>hi</p>"));
+        let status = probe(&server.url("/page"), &options());
+        assert_eq!(status.status, Some(200));
+        assert!(status.is_ok());
+        assert!(!status.is_redirected());
+        assert_eq!(status.error, None);
+        assert_eq!(server.requests(), ["HEAD /page"]);
+    }
+
+    #[test]
+    fn reports_missing_pages() {
+        let server = TestServer::start(|_, _| Response::status(404));
+        let status = probe(&server.url("/gone"), &options());
+        assert_eq!(status.status, Some(404));
+        assert!(status.is_broken());
+        // HEAD errors are checked again with GET
+        assert_eq!(server.requests(), ["HEAD /gone", "GET /gone"]);
+    }
+
+    #[test]
+    fn falls_back_to_get_when_head_fails() {
+        let server = TestServer::start(|method, _| match method {
+            "HEAD" => Response::status(405),
+            _ => Response::ok("text/html", "<p>hi</p>"),
+        });
+        let status = probe(&server.url("/page"), &options());
+        assert_eq!(status.status, Some(200));
+    }
+
+    #[test]
+    fn records_redirect_target() {
+        let server = TestServer::start(|_, path| match path {
+            "/old" => Response::redirect(301, "/new"),
+            _ => Response::ok("text/html", "<p>moved</p>"),
+        });
+        let status = probe(&server.url("/old"), &options());
+        assert_eq!(status.status, Some(200));
+        assert!(status.is_redirected());
+        assert_eq!(status.final_url, Some(server.url("/new")));
+    }
+
+    #[test]
+    fn gives_up_after_timeout() {
+        let server = TestServer::start(|_, _| {
+            Response::ok("text/html", "late").delay(Duration::from_secs(3))
+        });
+        let options = CheckOptions {
+            timeout: Duration::from_millis(200),
+            ..options()
+        };
+        let status = probe(&server.url("/slow"), &options);
+        assert_eq!(status.status, None);
+        assert!(status.error.is_some());
+        assert!(status.is_broken());
+    }
+
+    #[test]
+    fn retries_transient_failures() {
+        let attempts = AtomicUsize::new(0);
+        let server = TestServer::start(move |method, _| {
+            // The first attempt fails for both HEAD and the GET fallback
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Response::status(503),
+                _ if method == "HEAD" => Response::status(200),
+                _ => Response::status(500),
+            }
+        });
+        let options = CheckOptions {
+            retries: 1,
+            ..options()
+        };
+        let status = probe(&server.url("/flaky"), &options);
+        assert_eq!(status.status, Some(200));
+        assert_eq!(
+            server.requests(),
+            ["HEAD /flaky", "GET /flaky", "HEAD /flaky"]
+        );
+    }
+
+    #[test]
+    fn does_not_retry_missing_pages() {
+        let server = TestServer::start(|_, _| Response::status(404));
+        let options = CheckOptions {
+            retries: 2,
+            ..options()
+        };
+        probe(&server.url("/gone"), &options);
+        assert_eq!(server.requests().len(), 2);
+    }
+}
@@ -0,0 +1,710 @@
+use eyre::{eyre, Result, WrapErr};
+use log::{info, warn};
+use rusqlite::{Connection, Transaction};
+
+type Migration = fn(&Transaction) -> Result<()>;
+
+/// Ordered schema upgrade steps.
+///
+/// The schema version stored in `PRAGMA user_version` is the number of steps
+/// that have been applied, so steps must only ever be appended to this list.
+const MIGRATIONS: &[Migration] = &[
+    initial_schema,
+    search_index,
+    canonical_links,
+    cascade_deletes,
+    link_checks,
+    archives,
+    page_content,
+    page_details,
+    extractor_details,
+    asset_cache,
+    tag_aliases,
+    unique_tags,
+    tag_info,
+    settings,
+    search_content,
+];
+
+pub fn latest_version() -> usize {
+    MIGRATIONS.len()
+}
+
+pub fn current_version(conn: &Connection) -> Result<usize> {
+    let version: usize = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .wrap_err("Couldn't read database schema version")?;
+    Ok(version)
+}
+
+/// Brings the database schema up to date, applying each pending step in its
+/// own transaction. Must run with foreign keys disabled, as steps that rebuild
+/// tables would otherwise cascade into their dependents.
+pub fn migrate(conn: &mut Connection) -> Result<()> {
+    let version = current_version(conn)?;
+    let latest = latest_version();
+
+    if version > latest {
+        return Err(eyre!(
+            "Database schema version {} is newer than the latest version supported by this build ({}). Please upgrade bkmrk.",
+            version,
+            latest
+        ));
+    }
+
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let new_version = idx + 1;
+        let tx = conn.transaction()?;
+        migration(&tx)
+            .wrap_err_with(|| format!("Couldn't migrate database to version {}", new_version))?;
+        let violations: usize =
+            tx.query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| {
+                row.get(0)
+            })?;
+        if violations > 0 {
+            return Err(eyre!(
+                "Migrating to version {} left {} rows with dangling references",
+                new_version,
+                violations
+            ));
+        }
+        tx.pragma_update(None, "user_version", new_version)?;
+        tx.commit()?;
+        info!("Migrated database to version {}", new_version);
+    }
+
+    Ok(())
+}
+
+/// Tables as they existed before versioning. Databases created by older builds
+/// already have these, hence the `IF NOT EXISTS`.
+fn initial_schema(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS `Bookmark`(
+            id CHAR(6) PRIMARY KEY,
+            link VARCHAR(300) NOT NULL UNIQUE,
+            added_at DATETIME NOT NULL,
+            last_modified DATETIME NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS `Tag`(
+            bookmark_id NOT NULL,
+            tag VARCHAR(100),
+            FOREIGN KEY (bookmark_id) REFERENCES `Bookmark`(id)
+        );
+
+        CREATE TABLE IF NOT EXISTS `Metadata`(
+            bookmark_id NOT NULL,
+            title VARCHAR(300) NOT NULL,
+            description TEXT,
+            image_url TEXT,
+            site_type VARCHAR(20),
+            FOREIGN KEY (bookmark_id) REFERENCES `Bookmark`(id)
+        );",
+    )?;
+    Ok(())
+}
+
+/// Rebuilds the search index row of a single bookmark. Used by the triggers that
+/// keep `BookmarkSearch` in sync with the `Bookmark`, `Metadata` and `Tag` tables.
+fn search_refresh(bookmark_id: &str) -> String {
+    format!(
+        "
+        DELETE FROM `BookmarkSearch` WHERE bookmark_id = {id};
+        INSERT INTO `BookmarkSearch` (bookmark_id, title, description, link, tags)
+            SELECT b.id, m.title, m.description, b.link,
+                (SELECT group_concat(t.tag, ' ') FROM `Tag` AS t WHERE t.bookmark_id = b.id)
+            FROM `Bookmark` AS b, `Metadata` AS m
+            WHERE b.id = m.bookmark_id AND b.id = {id};",
+        id = bookmark_id
+    )
+}
+
+fn search_index(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(&format!(
+        "
+        CREATE VIRTUAL TABLE `BookmarkSearch` USING fts5(
+            bookmark_id UNINDEXED,
+            title,
+            description,
+            link,
+            tags
+        );
+
+        INSERT INTO `BookmarkSearch` (bookmark_id, title, description, link, tags)
+            SELECT b.id, m.title, m.description, b.link,
+                (SELECT group_concat(t.tag, ' ') FROM `Tag` AS t WHERE t.bookmark_id = b.id)
+            FROM `Bookmark` AS b, `Metadata` AS m
+            WHERE b.id = m.bookmark_id;
+
+        CREATE TRIGGER `search_bookmark_update` AFTER UPDATE ON `Bookmark` BEGIN {bookmark_update} END;
+        CREATE TRIGGER `search_bookmark_delete` AFTER DELETE ON `Bookmark` BEGIN
+            DELETE FROM `BookmarkSearch` WHERE bookmark_id = OLD.id;
+        END;
+        CREATE TRIGGER `search_metadata_insert` AFTER INSERT ON `Metadata` BEGIN {metadata_insert} END;
+        CREATE TRIGGER `search_metadata_update` AFTER UPDATE ON `Metadata` BEGIN {metadata_update} END;
+        CREATE TRIGGER `search_metadata_delete` AFTER DELETE ON `Metadata` BEGIN {metadata_delete} END;
+        CREATE TRIGGER `search_tag_insert` AFTER INSERT ON `Tag` BEGIN {tag_insert} END;
+        CREATE TRIGGER `search_tag_update` AFTER UPDATE ON `Tag` BEGIN {tag_update} END;
+        CREATE TRIGGER `search_tag_delete` AFTER DELETE ON `Tag` BEGIN {tag_delete} END;",
+        bookmark_update = search_refresh("NEW.id"),
+        metadata_insert = search_refresh("NEW.bookmark_id"),
+        metadata_update = search_refresh("NEW.bookmark_id"),
+        metadata_delete = search_refresh("OLD.bookmark_id"),
+        tag_insert = search_refresh("NEW.bookmark_id"),
+        tag_update = search_refresh("NEW.bookmark_id"),
+        tag_delete = search_refresh("OLD.bookmark_id"),
+    ))?;
+    Ok(())
+}
+
+/// Adds the canonical form of each link, used to find bookmarks that point to
+/// the same page. Relies on the `canonical_url` function registered on connect.
+fn canonical_links(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "
+        ALTER TABLE `Bookmark` ADD COLUMN canonical_link TEXT;
+        UPDATE `Bookmark` SET canonical_link = canonical_url(link);
+        CREATE INDEX `bookmark_canonical_link` ON `Bookmark`(canonical_link);",
+    )?;
+    Ok(())
+}
+
+/// Like [`search_refresh`], but finds the index row by the bookmark's `seq`
+/// instead of scanning the unindexed `bookmark_id` column.
+fn search_refresh_by_seq(bookmark_id: &str) -> String {
+    format!(
+        "
+        DELETE FROM `BookmarkSearch` WHERE rowid = (SELECT seq FROM `Bookmark` WHERE id = {id});
+        INSERT INTO `BookmarkSearch` (rowid, bookmark_id, title, description, link, tags)
+            SELECT b.seq, b.id, m.title, m.description, b.link,
+                (SELECT group_concat(t.tag, ' ') FROM `Tag` AS t WHERE t.bookmark_id = b.id)
+            FROM `Bookmark` AS b, `Metadata` AS m
+            WHERE b.id = m.bookmark_id AND b.id = {id};",
+        id = bookmark_id
+    )
+}
+
+/// Rebuilds the tables so that `Tag` and `Metadata` rows are deleted along
+/// with their bookmark, and gives bookmarks a stable integer `seq` that the
+/// search index uses as its rowid. Tags and metadata left behind by
+/// interrupted deletes are dropped. Bookmarks without metadata get their link
+/// as the title, and of repeated metadata rows only the first is kept.
+fn cascade_deletes(tx: &Transaction) -> Result<()> {
+    let untitled: usize = tx.query_row(
+        "SELECT COUNT(*) FROM `Bookmark` WHERE id NOT IN (SELECT bookmark_id FROM `Metadata`)",
+        [],
+        |row| row.get(0),
+    )?;
+    if untitled > 0 {
+        warn!(
+            "{} bookmarks had no title or description, using their links as titles",
+            untitled
+        );
+    }
+    let repeated: usize = tx.query_row(
+        "SELECT COUNT(*) - COUNT(DISTINCT bookmark_id) FROM `Metadata` WHERE bookmark_id IN (SELECT id FROM `Bookmark`)",
+        [],
+        |row| row.get(0),
+    )?;
+    if repeated > 0 {
+        warn!(
+            "Dropped {} repeated titles and descriptions, keeping the first saved for each bookmark",
+            repeated
+        );
+    }
+
+    tx.execute_batch(&format!(
+        "
+        DROP TRIGGER `search_bookmark_update`;
+        DROP TRIGGER `search_bookmark_delete`;
+        DROP TRIGGER `search_metadata_insert`;
+        DROP TRIGGER `search_metadata_update`;
+        DROP TRIGGER `search_metadata_delete`;
+        DROP TRIGGER `search_tag_insert`;
+        DROP TRIGGER `search_tag_update`;
+        DROP TRIGGER `search_tag_delete`;
+
+        CREATE TABLE `Bookmark_new`(
+            seq INTEGER PRIMARY KEY,
+            id CHAR(6) NOT NULL UNIQUE,
+            link VARCHAR(300) NOT NULL UNIQUE,
+            canonical_link TEXT,
+            added_at DATETIME NOT NULL,
+            last_modified DATETIME NOT NULL
+        );
+        INSERT INTO `Bookmark_new` (id, link, canonical_link, added_at, last_modified)
+            SELECT id, link, canonical_link, added_at, last_modified FROM `Bookmark`
+            ORDER BY rowid;
+
+        CREATE TABLE `Tag_new`(
+            bookmark_id CHAR(6) NOT NULL REFERENCES `Bookmark`(id) ON DELETE CASCADE,
+            tag VARCHAR(100)
+        );
+        INSERT INTO `Tag_new` (bookmark_id, tag)
+            SELECT bookmark_id, tag FROM `Tag`
+            WHERE bookmark_id IN (SELECT id FROM `Bookmark_new`)
+            ORDER BY rowid;
+
+        CREATE TABLE `Metadata_new`(
+            bookmark_id CHAR(6) NOT NULL UNIQUE REFERENCES `Bookmark`(id) ON DELETE CASCADE,
+            title VARCHAR(300) NOT NULL,
+            description TEXT,
+            image_url TEXT,
+            site_type VARCHAR(20)
+        );
+        INSERT INTO `Metadata_new` (bookmark_id, title, description, image_url, site_type)
+            SELECT bookmark_id, title, description, image_url, site_type FROM `Metadata`
+            WHERE bookmark_id IN (SELECT id FROM `Bookmark_new`)
+            AND rowid IN (SELECT MIN(rowid) FROM `Metadata` GROUP BY bookmark_id)
+            ORDER BY rowid;
+        INSERT INTO `Metadata_new` (bookmark_id, title, site_type)
+            SELECT id, link, 'website' FROM `Bookmark_new`
+            WHERE id NOT IN (SELECT bookmark_id FROM `Metadata_new`);
+
+        DROP TABLE `Tag`;
+        DROP TABLE `Metadata`;
+        DROP TABLE `Bookmark`;
+        ALTER TABLE `Bookmark_new` RENAME TO `Bookmark`;
+        ALTER TABLE `Tag_new` RENAME TO `Tag`;
+        ALTER TABLE `Metadata_new` RENAME TO `Metadata`;
+        CREATE INDEX `bookmark_canonical_link` ON `Bookmark`(canonical_link);
+        CREATE INDEX `tag_bookmark_id` ON `Tag`(bookmark_id);
+
+        DELETE FROM `BookmarkSearch`;
+        INSERT INTO `BookmarkSearch` (rowid, bookmark_id, title, description, link, tags)
+            SELECT b.seq, b.id, m.title, m.description, b.link,
+                (SELECT group_concat(t.tag, ' ') FROM `Tag` AS t WHERE t.bookmark_id = b.id)
+            FROM `Bookmark` AS b, `Metadata` AS m
+            WHERE b.id = m.bookmark_id;
+
+        CREATE TRIGGER `search_bookmark_update` AFTER UPDATE ON `Bookmark` BEGIN {bookmark_update} END;
+        CREATE TRIGGER `search_bookmark_delete` AFTER DELETE ON `Bookmark` BEGIN
+            DELETE FROM `BookmarkSearch` WHERE rowid = OLD.seq;
+        END;
+        CREATE TRIGGER `search_metadata_insert` AFTER INSERT ON `Metadata` BEGIN {metadata_insert} END;
+        CREATE TRIGGER `search_metadata_update` AFTER UPDATE ON `Metadata` BEGIN {metadata_update} END;
+        CREATE TRIGGER `search_metadata_delete` AFTER DELETE ON `Metadata` BEGIN {metadata_delete} END;
+        CREATE TRIGGER `search_tag_insert` AFTER INSERT ON `Tag` BEGIN {tag_insert} END;
+        CREATE TRIGGER `search_tag_update` AFTER UPDATE ON `Tag` BEGIN {tag_update} END;
+        CREATE TRIGGER `search_tag_delete` AFTER DELETE ON `Tag` BEGIN {tag_delete} END;",
+        bookmark_update = search_refresh_by_seq("NEW.id"),
+        metadata_insert = search_refresh_by_seq("NEW.bookmark_id"),
+        metadata_update = search_refresh_by_seq("NEW.bookmark_id"),
+        metadata_delete = search_refresh_by_seq("OLD.bookmark_id"),
+        tag_insert = search_refresh_by_seq("NEW.bookmark_id"),
+        tag_update = search_refresh_by_seq("NEW.bookmark_id"),
+        tag_delete = search_refresh_by_seq("OLD.bookmark_id"),
+    ))?;
+    Ok(())
+}
+
+fn link_checks(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE `LinkCheck`(
+            bookmark_id CHAR(6) PRIMARY KEY REFERENCES `Bookmark`(id) ON DELETE CASCADE,
+            status INTEGER,
+            final_url TEXT,
+            error TEXT,
+            checked_at DATETIME NOT NULL
+        );",
+    )?;
+    Ok(())
+}
+
+fn archives(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE `Archive`(
+            bookmark_id CHAR(6) NOT NULL REFERENCES `Bookmark`(id) ON DELETE CASCADE,
+            path TEXT NOT NULL,
+            archived_at DATETIME NOT NULL
+        );
+        CREATE INDEX `archive_bookmark_id` ON `Archive`(bookmark_id);",
+    )?;
+    Ok(())
+}
+
+/// Keeps the title, description, link and tags of a bookmark's index row up to
+/// date. Unlike [`search_refresh_by_seq`] it updates the row in place, so the
+/// indexed page content is left alone.
+fn search_update(bookmark_id: &str) -> String {
+    format!(
+        "
+        UPDATE `BookmarkSearch` SET
+            title = (SELECT title FROM `Metadata` WHERE bookmark_id = {id}),
+            description = (SELECT description FROM `Metadata` WHERE bookmark_id = {id}),
+            link = (SELECT link FROM `Bookmark` WHERE id = {id}),
+            tags = (SELECT group_concat(tag, ' ') FROM `Tag` WHERE bookmark_id = {id})
+        WHERE rowid = (SELECT seq FROM `Bookmark` WHERE id = {id});",
+        id = bookmark_id
+    )
+}
+
+/// Stores the readable text of bookmarked pages, zlib compressed, and adds it
+/// to the search index. Existing bookmarks get their text the next time their
+/// metadata is fetched.
+fn page_content(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(&format!(
+        "
+        DROP TRIGGER `search_bookmark_update`;
+        DROP TRIGGER `search_bookmark_delete`;
+        DROP TRIGGER `search_metadata_insert`;
+        DROP TRIGGER `search_metadata_update`;
+        DROP TRIGGER `search_metadata_delete`;
+        DROP TRIGGER `search_tag_insert`;
+        DROP TRIGGER `search_tag_update`;
+        DROP TRIGGER `search_tag_delete`;
+
+        CREATE TABLE `Content`(
+            bookmark_id CHAR(6) PRIMARY KEY REFERENCES `Bookmark`(id) ON DELETE CASCADE,
+            text BLOB NOT NULL,
+            fetched_at DATETIME NOT NULL
+        );
+
+        DROP TABLE `BookmarkSearch`;
+        CREATE VIRTUAL TABLE `BookmarkSearch` USING fts5(
+            bookmark_id UNINDEXED,
+            title,
+            description,
+            link,
+            tags,
+            content
+        );
+        INSERT INTO `BookmarkSearch` (rowid, bookmark_id, title, description, link, tags)
+            SELECT b.seq, b.id, m.title, m.description, b.link,
+                (SELECT group_concat(t.tag, ' ') FROM `Tag` AS t WHERE t.bookmark_id = b.id)
+            FROM `Bookmark` AS b, `Metadata` AS m
+            WHERE b.id = m.bookmark_id;
+
+        CREATE TRIGGER `search_bookmark_update` AFTER UPDATE ON `Bookmark` BEGIN {bookmark_update} END;
+        CREATE TRIGGER `search_bookmark_delete` AFTER DELETE ON `Bookmark` BEGIN
+            DELETE FROM `BookmarkSearch` WHERE rowid = OLD.seq;
+        END;
+        CREATE TRIGGER `search_metadata_insert` AFTER INSERT ON `Metadata` BEGIN {metadata_insert} END;
+        CREATE TRIGGER `search_metadata_update` AFTER UPDATE ON `Metadata` BEGIN {metadata_update} END;
+        CREATE TRIGGER `search_metadata_delete` AFTER DELETE ON `Metadata` BEGIN
+            DELETE FROM `BookmarkSearch` WHERE rowid = (SELECT seq FROM `Bookmark` WHERE id = OLD.bookmark_id);
+        END;
+        CREATE TRIGGER `search_tag_insert` AFTER INSERT ON `Tag` BEGIN {tag_insert} END;
+        CREATE TRIGGER `search_tag_update` AFTER UPDATE ON `Tag` BEGIN {tag_update} END;
+        CREATE TRIGGER `search_tag_delete` AFTER DELETE ON `Tag` BEGIN {tag_delete} END;",
+        bookmark_update = search_update("NEW.id"),
+        metadata_insert = search_refresh_by_seq("NEW.bookmark_id"),
+        metadata_update = search_update("NEW.bookmark_id"),
+        tag_insert = search_update("NEW.bookmark_id"),
+        tag_update = search_update("NEW.bookmark_id"),
+        tag_delete = search_update("OLD.bookmark_id"),
+    ))?;
+    Ok(())
+}
+
+/// Adds the page details read from HTML metadata, and the canonical form of
+/// the URL each page declares as canonical, used to find duplicates.
+fn page_details(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "
+        ALTER TABLE `Metadata` ADD COLUMN favicon_url TEXT;
+        ALTER TABLE `Metadata` ADD COLUMN canonical_url TEXT;
+        ALTER TABLE `Metadata` ADD COLUMN site_name TEXT;
+        ALTER TABLE `Metadata` ADD COLUMN author TEXT;
+        ALTER TABLE `Metadata` ADD COLUMN language VARCHAR(35);
+        ALTER TABLE `Metadata` ADD COLUMN published_at DATETIME;
+        ALTER TABLE `Metadata` ADD COLUMN modified_at DATETIME;
+        ALTER TABLE `Metadata` ADD COLUMN keywords TEXT;
+        ALTER TABLE `Metadata` ADD COLUMN feeds TEXT;
+
+        ALTER TABLE `Bookmark` ADD COLUMN page_canonical_link TEXT;
+        CREATE INDEX `bookmark_page_canonical_link` ON `Bookmark`(page_canonical_link);",
+    )?;
+    Ok(())
+}
+
+fn extractor_details(tx: &Transaction) -> Result<()> {
+    tx.execute_batch("ALTER TABLE `Metadata` ADD COLUMN extra TEXT;")?;
+    Ok(())
+}
+
+fn asset_cache(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE `Asset`(
+            hash CHAR(64) PRIMARY KEY,
+            path TEXT NOT NULL,
+            content_type TEXT,
+            size INTEGER NOT NULL,
+            last_used DATETIME NOT NULL
+        );
+        CREATE INDEX `asset_last_used` ON `Asset`(last_used);
+
+        CREATE TABLE `BookmarkAsset`(
+            bookmark_id CHAR(6) NOT NULL REFERENCES `Bookmark`(id) ON DELETE CASCADE,
+            kind VARCHAR(10) NOT NULL,
+            url TEXT NOT NULL,
+            hash CHAR(64) NOT NULL REFERENCES `Asset`(hash) ON DELETE CASCADE,
+            PRIMARY KEY (bookmark_id, kind)
+        );
+        CREATE INDEX `bookmark_asset_url` ON `BookmarkAsset`(url);
+        CREATE INDEX `bookmark_asset_hash` ON `BookmarkAsset`(hash);",
+    )?;
+    Ok(())
+}
+
+/// Adds synonyms that tags are rewritten to, keyed by their normalized form.
+fn tag_aliases(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE `TagAlias`(
+            alias TEXT PRIMARY KEY,
+            tag TEXT NOT NULL
+        );
+        CREATE INDEX `tag_alias_tag` ON `TagAlias`(tag);",
+    )?;
+    Ok(())
+}
+
+/// Drops repeated tags of a bookmark and keeps them from coming back.
+fn unique_tags(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "
+        DELETE FROM `Tag` WHERE rowid NOT IN (SELECT MIN(rowid) FROM `Tag` GROUP BY bookmark_id, tag);
+        CREATE UNIQUE INDEX `tag_bookmark_tag` ON `Tag`(bookmark_id, tag);",
+    )?;
+    Ok(())
+}
+
+fn tag_info(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE `TagInfo`(
+            tag TEXT PRIMARY KEY,
+            description TEXT,
+            color CHAR(7),
+            icon TEXT,
+            pinned BOOLEAN NOT NULL DEFAULT 0
+        );",
+    )?;
+    Ok(())
+}
+
+/// Values the database was last used with, such as the URL normalizer
+/// settings its canonical links were computed with.
+fn settings(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE `Setting`(
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );",
+    )?;
+    Ok(())
+}
+
+/// Removes a bookmark's entries from the search index. The index reads its
+/// text from `BookmarkSearchSource`, and FTS5 needs the indexed text to find
+/// the entries, so this must run before the text changes.
+fn search_remove(bookmark_id: &str) -> String {
+    format!(
+        "
+        INSERT INTO `BookmarkSearch` (`BookmarkSearch`, rowid, bookmark_id, title, description, link, tags, content)
+            SELECT 'delete', seq, bookmark_id, title, description, link, tags, content
+            FROM `BookmarkSearchSource` WHERE bookmark_id = {id};",
+        id = bookmark_id
+    )
+}
+
+/// Indexes the current text of a bookmark, after [`search_remove`] and the
+/// change.
+fn search_add(bookmark_id: &str) -> String {
+    format!(
+        "
+        INSERT INTO `BookmarkSearch` (rowid, bookmark_id, title, description, link, tags, content)
+            SELECT seq, bookmark_id, title, description, link, tags, content
+            FROM `BookmarkSearchSource` WHERE bookmark_id = {id};",
+        id = bookmark_id
+    )
+}
+
+/// Makes the search index read its text from the tables instead of keeping a
+/// copy, so page content is only stored compressed in `Content`. The
+/// `page_text` function registered on connect decompresses it for the index.
+///
+/// Removing entries from the index needs the text they were indexed with, so
+/// the triggers remove them before each change and add them back after it.
+/// Statements that skip or replace rows, like `INSERT OR IGNORE`, would leave
+/// the index out of step and mustn't be used on the indexed tables.
+fn search_content(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(&format!(
+        "
+        DROP TRIGGER `search_bookmark_update`;
+        DROP TRIGGER `search_bookmark_delete`;
+        DROP TRIGGER `search_metadata_insert`;
+        DROP TRIGGER `search_metadata_update`;
+        DROP TRIGGER `search_metadata_delete`;
+        DROP TRIGGER `search_tag_insert`;
+        DROP TRIGGER `search_tag_update`;
+        DROP TRIGGER `search_tag_delete`;
+        DROP TABLE `BookmarkSearch`;
+
+        CREATE VIEW `BookmarkSearchSource` AS
+            SELECT b.seq, b.id AS bookmark_id, m.title, m.description, b.link,
+                (SELECT group_concat(t.tag, ' ') FROM `Tag` AS t WHERE t.bookmark_id = b.id) AS tags,
+                (SELECT page_text(c.text) FROM `Content` AS c WHERE c.bookmark_id = b.id) AS content
+            FROM `Bookmark` AS b, `Metadata` AS m
+            WHERE b.id = m.bookmark_id;
+        CREATE VIRTUAL TABLE `BookmarkSearch` USING fts5(
+            bookmark_id UNINDEXED,
+            title,
+            description,
+            link,
+            tags,
+            content,
+            content = 'BookmarkSearchSource',
+            content_rowid = 'seq'
+        );
+        INSERT INTO `BookmarkSearch` (`BookmarkSearch`) VALUES ('rebuild');
+
+        CREATE TRIGGER `search_bookmark_before_update` BEFORE UPDATE OF link ON `Bookmark` BEGIN {remove_old} END;
+        CREATE TRIGGER `search_bookmark_update` AFTER UPDATE OF link ON `Bookmark` BEGIN {add_new} END;
+        CREATE TRIGGER `search_bookmark_delete` BEFORE DELETE ON `Bookmark` BEGIN {remove_old} END;
+
+        CREATE TRIGGER `search_metadata_insert` AFTER INSERT ON `Metadata` BEGIN {add_new_child} END;
+        CREATE TRIGGER `search_metadata_before_update` BEFORE UPDATE OF title, description ON `Metadata` BEGIN {remove_old_child} END;
+        CREATE TRIGGER `search_metadata_update` AFTER UPDATE OF title, description ON `Metadata` BEGIN {add_new_child} END;
+        CREATE TRIGGER `search_metadata_delete` BEFORE DELETE ON `Metadata` BEGIN {remove_old_child} END;
+
+        CREATE TRIGGER `search_tag_before_insert` BEFORE INSERT ON `Tag` BEGIN {remove_new_child} END;
+        CREATE TRIGGER `search_tag_insert` AFTER INSERT ON `Tag` BEGIN {add_new_child} END;
+        CREATE TRIGGER `search_tag_before_update` BEFORE UPDATE OF tag ON `Tag` BEGIN {remove_old_child} END;
+        CREATE TRIGGER `search_tag_update` AFTER UPDATE OF tag ON `Tag` BEGIN {add_new_child} END;
+        CREATE TRIGGER `search_tag_before_delete` BEFORE DELETE ON `Tag` BEGIN {remove_old_child} END;
+        CREATE TRIGGER `search_tag_delete` AFTER DELETE ON `Tag` BEGIN {add_old_child} END;
+
+        CREATE TRIGGER `search_content_insert` AFTER INSERT ON `Content` BEGIN
+            INSERT INTO `BookmarkSearch` (`BookmarkSearch`, rowid, bookmark_id, title, description, link, tags, content)
+                SELECT 'delete', seq, bookmark_id, title, description, link, tags, NULL
+                FROM `BookmarkSearchSource` WHERE bookmark_id = NEW.bookmark_id;
+            {add_new_child}
+        END;
+        CREATE TRIGGER `search_content_before_update` BEFORE UPDATE OF text ON `Content` BEGIN {remove_old_child} END;
+        CREATE TRIGGER `search_content_update` AFTER UPDATE OF text ON `Content` BEGIN {add_new_child} END;
+        CREATE TRIGGER `search_content_before_delete` BEFORE DELETE ON `Content` BEGIN {remove_old_child} END;
+        CREATE TRIGGER `search_content_delete` AFTER DELETE ON `Content` BEGIN {add_old_child} END;",
+        remove_old = search_remove("OLD.id"),
+        add_new = search_add("NEW.id"),
+        remove_old_child = search_remove("OLD.bookmark_id"),
+        remove_new_child = search_remove("NEW.bookmark_id"),
+        add_new_child = search_add("NEW.bookmark_id"),
+        add_old_child = search_add("OLD.bookmark_id"),
+    ))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::*;
+    use crate::{
+        canonical::UrlNormalizer, db::Database, query::BookmarkQuery, tags::TagNormalizer,
+    };
+
+    /// Tables and rows as written by builds from before schema versioning.
+    const BASELINE: &str = "
+        CREATE TABLE `Bookmark`(
+            id CHAR(6) PRIMARY KEY,
+            link VARCHAR(300) NOT NULL UNIQUE,
+            added_at DATETIME NOT NULL,
+            last_modified DATETIME NOT NULL
+        );
+        CREATE TABLE `Tag`(
+            bookmark_id NOT NULL,
+            tag VARCHAR(100),
+            FOREIGN KEY (bookmark_id) REFERENCES `Bookmark`(id)
+        );
+        CREATE TABLE `Metadata`(
+            bookmark_id NOT NULL,
+            title VARCHAR(300) NOT NULL,
+            description TEXT,
+            image_url TEXT,
+            site_type VARCHAR(20),
+            FOREIGN KEY (bookmark_id) REFERENCES `Bookmark`(id)
+        );
+
+        INSERT INTO `Bookmark` VALUES ('a1b2c3', 'https://tokio.rs/', 1645000000, 1645000100);
+        INSERT INTO `Metadata` VALUES ('a1b2c3', 'Tokio', 'An asynchronous runtime', NULL, 'website');
+        INSERT INTO `Tag` VALUES ('a1b2c3', 'rust');
+        INSERT INTO `Tag` VALUES ('a1b2c3', 'async');
+        INSERT INTO `Tag` VALUES ('a1b2c3', 'rust');
+
+        INSERT INTO `Bookmark` VALUES ('d4e5f6', 'https://www.youtube.com/watch?v=abc', 1646000000, 1646000000);
+        INSERT INTO `Metadata` VALUES ('d4e5f6', 'A talk', NULL, 'https://img.example/a.png', 'video.other');
+        INSERT INTO `Metadata` VALUES ('d4e5f6', 'A talk, again', NULL, NULL, 'website');
+
+        INSERT INTO `Bookmark` VALUES ('0a0b0c', 'https://untitled.example/', 1647000000, 1647000000);
+        INSERT INTO `Tag` VALUES ('0a0b0c', 'misc');";
+
+    #[test]
+    fn migrates_baseline_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bookmarks.db");
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(BASELINE)
+            .unwrap();
+
+        let db = Database::connect(&path, UrlNormalizer::new(), TagNormalizer::new()).unwrap();
+        drop(db);
+        let conn = Connection::open(&path).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+
+        let db = Database::connect(&path, UrlNormalizer::new(), TagNormalizer::new()).unwrap();
+        let mut bookmarks = db.get(&BookmarkQuery::new()).unwrap();
+        bookmarks.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(bookmarks.len(), 3);
+
+        let untitled = &bookmarks[0];
+        assert_eq!(untitled.id, "0a0b0c");
+        assert_eq!(untitled.metadata.title, "https://untitled.example/");
+        assert_eq!(untitled.tags.0, ["misc"]);
+
+        let tokio = &bookmarks[1];
+        assert_eq!(tokio.id, "a1b2c3");
+        assert_eq!(tokio.link, "https://tokio.rs/");
+        assert_eq!(
+            (tokio.added_at, tokio.last_modified),
+            (1645000000, 1645000100)
+        );
+        assert_eq!(tokio.metadata.title, "Tokio");
+        assert_eq!(
+            tokio.metadata.description.as_deref(),
+            Some("An asynchronous runtime")
+        );
+        let mut tags = tokio.tags.0.clone();
+        tags.sort();
+        assert_eq!(tags, ["async", "rust"]);
+
+        let talk = &bookmarks[2];
+        assert_eq!(talk.metadata.title, "A talk");
+        assert_eq!(talk.metadata.site_type.to_string(), "video.other");
+        assert_eq!(
+            talk.metadata.image_url.as_deref(),
+            Some("https://img.example/a.png")
+        );
+        assert!(talk.tags.0.is_empty());
+
+        let found = db.search("asynchronous", None).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].bookmark.id, "a1b2c3");
+    }
+
+    #[test]
+    fn rejects_newer_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+        assert!(migrate(&mut conn).is_err());
+    }
+}
@@ -6,9 +6,10 @@ use pest_derive::Parser;
 use crate::bookmark::TagList;
 use crate::element::{Element, Tags};
 use crate::site_metadata::SiteMetadata;
-use crate::{utils, Bookmark};
+use crate::{tags, utils, Bookmark};
 use chrono::Utc;
-use std::collections::{HashMap, HashSet};
+use itertools::Itertools;
+use std::collections::HashMap;
 
 #[derive(Parser)]
 #[grammar = "netscape.pest"]
@@ -16,7 +17,7 @@ struct NetScapeParser;
 
 pub fn parse_str(contents: &str, add_folder_labels: bool) -> Result<Vec<Bookmark>> {
     let elements = get_elements(contents)?;
-    let bookmarks = get_bookmarks(&elements, None, add_folder_labels)?;
+    let bookmarks = get_bookmarks(&elements, &[], add_folder_labels)?;
     Ok(bookmarks)
 }
 
@@ -38,25 +39,32 @@ fn get_elements(contents: &str) -> Result<Vec<Element>> {
     Ok(elements)
 }
 
+/// Reads the bookmarks in `elements`, which are inside the folders named by
+/// `folders`, outermost first.
 fn get_bookmarks(
     elements: &[Element],
-    labels: Option<&[String]>,
+    folders: &[String],
     add_folder_labels: bool,
 ) -> Result<Vec<Bookmark>> {
     let mut bookmarks = Vec::new();
-    let mut labels = Vec::from(labels.unwrap_or_default());
+    // A folder's `<H3>` name is followed by the `<DL>` holding its contents
+    let mut folder = None;
 
     for element in elements {
         match element.name {
-            Tags::TITLE | Tags::H1 | Tags::H3 => {
-                labels.push(element.inner_text.to_owned().unwrap_or_default())
+            Tags::H3 => {
+                folder = Some(utils::unescape_html(
+                    &element.inner_text.to_owned().unwrap_or_default(),
+                ))
             }
             Tags::DL => {
                 let dl_children = element
                     .children
                     .as_ref()
                     .ok_or(eyre!("Failed to get element children"))?;
-                let mut ch = get_bookmarks(dl_children, Some(&labels), add_folder_labels)?;
+                let mut path = folders.to_vec();
+                path.extend(folder.take());
+                let mut ch = get_bookmarks(dl_children, &path, add_folder_labels)?;
                 bookmarks.append(&mut ch);
             }
             Tags::A => {
@@ -66,21 +74,17 @@ fn get_bookmarks(
                     .unwrap_or(&String::default())
                     .split(",")
                     .filter(|s| !s.is_empty())
-                    .map(String::from)
+                    .map(utils::unescape_html)
                     .collect::<Vec<_>>();
 
                 if add_folder_labels {
-                    attr_labels.extend_from_slice(&labels);
+                    attr_labels.extend(tags::from_path(folders));
                 }
 
-                let attr_labels = attr_labels
-                    .into_iter()
-                    .collect::<HashSet<_>>()
-                    .into_iter()
-                    .collect();
+                let attr_labels = attr_labels.into_iter().unique().collect();
 
                 let bk = Bookmark {
-                    link: attributes.get("HREF").unwrap().to_owned(),
+                    link: utils::unescape_html(attributes.get("HREF").unwrap()),
                     added_at: match attributes.get("ADD_DATE") {
                         Some(ts_str) => ts_str.parse()?,
                         None => Utc::now().timestamp(),
@@ -91,10 +95,10 @@ fn get_bookmarks(
                     },
                     tags: TagList(attr_labels),
                     metadata: SiteMetadata {
-                        title: utils::squeeze_whitespaces(
+                        title: utils::unescape_html(&utils::squeeze_whitespaces(
                             element.inner_text.to_owned().unwrap_or_default().trim(),
-                        ),
-                        description: element.text.to_owned(),
+                        )),
+                        description: element.text.as_deref().map(utils::unescape_html),
                         ..Default::default()
                     },
                     ..Default::default()
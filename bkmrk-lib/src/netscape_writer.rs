@@ -0,0 +1,229 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    cache,
+    tags::{self, TagInfo},
+    utils, Bookmark,
+};
+
+const INDENT: &str = "    ";
+
+/// Writes bookmarks in the Netscape bookmark file format read by
+/// [`netscape_parser::parse_str`](crate::netscape_parser::parse_str).
+///
+/// With `tag_folders`, each tag becomes an `<H3>` folder holding the bookmarks
+/// that carry it, and untagged bookmarks are written at the top level.
+/// Hierarchical tags like `dev/rust` become nested folders. Folders of pinned
+/// tags come first, and folders of tags with a color in `tag_infos` get a
+/// `COLOR` attribute.
+pub fn write_string(
+    bookmarks: &[Bookmark],
+    tag_folders: bool,
+    tag_infos: &HashMap<String, TagInfo>,
+) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file.
+     It will be read and overwritten.
+     DO NOT EDIT! -->
+<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+",
+    );
+
+    if tag_folders {
+        let mut root = Folder::default();
+        for bookmark in bookmarks {
+            for tag in &bookmark.tags.0 {
+                let folder = tag
+                    .split(tags::SEPARATOR)
+                    .filter(|name| !name.is_empty())
+                    .fold(&mut root, |folder, name| {
+                        folder.folders.entry(name).or_default()
+                    });
+                folder.bookmarks.push(bookmark);
+            }
+        }
+
+        write_folders(&mut out, "", &root, 1, tag_infos);
+        for bookmark in bookmarks.iter().filter(|b| b.tags.0.is_empty()) {
+            write_bookmark(&mut out, bookmark, 1);
+        }
+    } else {
+        for bookmark in bookmarks {
+            write_bookmark(&mut out, bookmark, 1);
+        }
+    }
+
+    out += "</DL><p>\n";
+    out
+}
+
+/// Bookmarks filed under a tag, and the folders of its child tags.
+#[derive(Default)]
+struct Folder<'a> {
+    folders: BTreeMap<&'a str, Folder<'a>>,
+    bookmarks: Vec<&'a Bookmark>,
+}
+
+/// Writes the child folders of `parent`, the folder of the tag `path`.
+fn write_folders(
+    out: &mut String,
+    path: &str,
+    parent: &Folder,
+    depth: usize,
+    tag_infos: &HashMap<String, TagInfo>,
+) {
+    let indent = INDENT.repeat(depth);
+    let mut folders: Vec<_> = parent
+        .folders
+        .iter()
+        .map(|(name, folder)| {
+            let tag = match path {
+                "" => name.to_string(),
+                _ => format!("{}{}{}", path, tags::SEPARATOR, name),
+            };
+            let info = tag_infos.get(&tag);
+            (tag, name, folder, info)
+        })
+        .collect();
+    folders.sort_by_key(|(_, _, _, info)| !info.is_some_and(|info| info.pinned));
+
+    for (tag, name, folder, info) in folders {
+        let color = info
+            .and_then(|info| info.color.as_deref())
+            .map(|color| format!(" COLOR=\"{}\"", utils::escape_html(color)))
+            .unwrap_or_default();
+        *out += &format!(
+            "{}<DT><H3{}>{}</H3>\n",
+            indent,
+            color,
+            utils::escape_html(name)
+        );
+        *out += &format!("{}<DL><p>\n", indent);
+        write_folders(out, &tag, folder, depth + 1, tag_infos);
+        for bookmark in &folder.bookmarks {
+            write_bookmark(out, bookmark, depth + 1);
+        }
+        *out += &format!("{}</DL><p>\n", indent);
+    }
+}
+
+fn write_bookmark(out: &mut String, bookmark: &Bookmark, depth: usize) {
+    let indent = INDENT.repeat(depth);
+
+    let mut attributes = format!(
+        "HREF=\"{}\" ADD_DATE=\"{}\" LAST_MODIFIED=\"{}\"",
+        utils::escape_html(&bookmark.link),
+        bookmark.added_at,
+        bookmark.last_modified
+    );
+    // Embedded so the export shows icons without going online
+    if let Some(icon) = bookmark.favicon_path.as_deref().and_then(cache::data_uri) {
+        attributes += &format!(" ICON=\"{}\"", icon);
+    }
+    if !bookmark.tags.0.is_empty() {
+        attributes += &format!(
+            " TAGS=\"{}\"",
+            utils::escape_html(&bookmark.tags.0.join(","))
+        );
+    }
+
+    *out += &format!(
+        "{}<DT><A {}>{}</A>\n",
+        indent,
+        attributes,
+        utils::escape_html(&bookmark.metadata.title)
+    );
+
+    if let Some(description) = &bookmark.metadata.description {
+        if !description.trim().is_empty() {
+            *out += &format!("{}<DD>{}\n", indent, utils::escape_html(description.trim()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::netscape_parser;
+
+    fn sample() -> Vec<Bookmark> {
+        let mut bookmarks = vec![
+            Bookmark::sample(
+                "https://example.com/search?q=rust&lang=en",
+                "Rust & <Friends> \"quoted\"",
+                &["rust", "dev/web"],
+            ),
+            Bookmark::sample("https://tokio.rs/", "Tokio", &[]),
+            Bookmark::sample(
+                "https://developer.mozilla.org/",
+                "MDN Web Docs",
+                &["dev/web"],
+            ),
+        ];
+        bookmarks[0].metadata.description = Some("Tips & tricks for <b>fast</b> code".to_owned());
+        bookmarks[2].metadata.description = Some("Resources for developers".to_owned());
+        bookmarks
+    }
+
+    type Summary = (String, String, Option<String>, Vec<String>, i64, i64);
+
+    fn summary(bookmark: &Bookmark) -> Summary {
+        let mut tags = bookmark.tags.0.clone();
+        tags.sort();
+        (
+            bookmark.link.clone(),
+            bookmark.metadata.title.clone(),
+            bookmark.metadata.description.clone(),
+            tags,
+            bookmark.added_at,
+            bookmark.last_modified,
+        )
+    }
+
+    #[test]
+    fn round_trips_flat_list() {
+        let bookmarks = sample();
+        let html = write_string(&bookmarks, false, &HashMap::new());
+        assert!(html.contains("Rust &amp; &lt;Friends&gt; &quot;quoted&quot;"));
+
+        let parsed = netscape_parser::parse_str(&html, false).unwrap();
+        assert_eq!(
+            parsed.iter().map(summary).collect::<Vec<_>>(),
+            bookmarks.iter().map(summary).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn round_trips_tag_folders() {
+        let bookmarks = sample();
+        let html = write_string(&bookmarks, true, &HashMap::new());
+        assert_eq!(html.matches("<H3").count(), 3);
+
+        // Bookmarks are written once per tag
+        let parsed = netscape_parser::parse_str(&html, true).unwrap();
+        assert_eq!(parsed.len(), 4);
+        let parsed: Vec<Summary> = parsed.iter().map(summary).unique().sorted().collect();
+        let expected: Vec<Summary> = bookmarks.iter().map(summary).sorted().collect();
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn tag_folders_nest_hierarchical_tags() {
+        let bookmarks = vec![Bookmark::sample("https://go.dev/", "Go", &["dev/go"])];
+        let html = write_string(&bookmarks, true, &HashMap::new());
+        let dev = html.find("<H3>dev</H3>").unwrap();
+        let go = html.find("<H3>go</H3>").unwrap();
+        assert!(dev < go);
+
+        // The folders alone carry the tag
+        let html = html.replace(" TAGS=\"dev/go\"", "");
+        let parsed = netscape_parser::parse_str(&html, true).unwrap();
+        assert_eq!(parsed[0].tags.0, ["dev/go"]);
+    }
+}
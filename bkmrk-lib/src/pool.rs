@@ -0,0 +1,117 @@
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+/// Runs `work` on each of `items` on up to `parallelism` threads, calling
+/// `on_result` on the calling thread with each item's index as soon as its
+/// work is done.
+pub(crate) fn run_concurrently<T, R, W, F>(
+    items: Vec<T>,
+    parallelism: usize,
+    work: W,
+    mut on_result: F,
+) where
+    T: Send + 'static,
+    R: Send + 'static,
+    W: Fn(T) -> R + Send + Sync + 'static,
+    F: FnMut(usize, R),
+{
+    let workers = parallelism.max(1).min(items.len());
+    let jobs = Arc::new(Mutex::new(items.into_iter().enumerate()));
+    let work = Arc::new(work);
+    let (sender, receiver) = mpsc::channel();
+
+    let workers = (0..workers)
+        .map(|_| {
+            let jobs = Arc::clone(&jobs);
+            let work = Arc::clone(&work);
+            let sender = sender.clone();
+            thread::spawn(move || loop {
+                let job = jobs.lock().ok().and_then(|mut jobs| jobs.next());
+                let (idx, item) = match job {
+                    Some(job) => job,
+                    None => break,
+                };
+                if sender.send((idx, work(item))).is_err() {
+                    break;
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(sender);
+
+    for (idx, result) in receiver {
+        on_result(idx, result);
+    }
+
+    for worker in workers {
+        worker.join().ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[test]
+    fn keeps_indices_of_results() {
+        let mut results = Vec::new();
+        run_concurrently(
+            (0..20u64).collect(),
+            4,
+            |item| {
+                // Later items finish first
+                thread::sleep(Duration::from_millis((20 - item) * 5));
+                item * 10
+            },
+            |idx, result| results.push((idx, result)),
+        );
+
+        assert_ne!(results.first(), Some(&(0, 0)));
+        results.sort_unstable();
+        let expected: Vec<_> = (0..20).map(|idx| (idx, idx as u64 * 10)).collect();
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn runs_at_most_parallelism_at_once() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let (work_running, work_most) = (Arc::clone(&running), Arc::clone(&most));
+        let mut count = 0;
+        run_concurrently(
+            vec![(); 12],
+            3,
+            move |_| {
+                let now = work_running.fetch_add(1, Ordering::SeqCst) + 1;
+                work_most.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(20));
+                work_running.fetch_sub(1, Ordering::SeqCst);
+            },
+            |_, _| count += 1,
+        );
+
+        assert_eq!(count, 12);
+        assert_eq!(most.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn runs_with_no_parallelism_or_items() {
+        let mut results = Vec::new();
+        run_concurrently(
+            vec![1, 2],
+            0,
+            |item| item,
+            |idx, item| results.push((idx, item)),
+        );
+        assert_eq!(results, [(0, 1), (1, 2)]);
+
+        run_concurrently(Vec::<u8>::new(), 4, |item| item, |_, _| unreachable!());
+    }
+}
@@ -0,0 +1,452 @@
+use std::fmt::Display;
+
+use eyre::Result;
+use rusqlite::types::Value;
+
+use crate::{query_parser, site_metadata::SiteType, tags};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DomainFilter {
+    /// Matches the host exactly, e.g. `github.com` but not `gist.github.com`
+    Exact(String),
+    /// Matches the host and all of its subdomains
+    Suffix(String),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    AddedAsc,
+    AddedDesc,
+    ModifiedAsc,
+    ModifiedDesc,
+    TitleAsc,
+    TitleDesc,
+}
+
+impl Display for SortOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortOrder::AddedAsc => write!(f, "b.added_at ASC"),
+            SortOrder::AddedDesc => write!(f, "b.added_at DESC"),
+            SortOrder::ModifiedAsc => write!(f, "b.last_modified ASC"),
+            SortOrder::ModifiedDesc => write!(f, "b.last_modified DESC"),
+            SortOrder::TitleAsc => write!(f, "m.title COLLATE NOCASE ASC"),
+            SortOrder::TitleDesc => write!(f, "m.title COLLATE NOCASE DESC"),
+        }
+    }
+}
+
+/// Filter for selecting bookmarks from the database.
+///
+/// Every filter that is set must match. Within a filter, `tags_any`, domains,
+/// site types and languages match if any of their values do, while
+/// `tags_all`, `title_contains`, `author_contains` and `matching` require all
+/// of them. Tag filters also match descendants of hierarchical tags, so
+/// `dev/rust` matches bookmarks tagged `dev/rust/async`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookmarkQuery {
+    tags_any: Vec<String>,
+    tags_all: Vec<String>,
+    tags_none: Vec<String>,
+    domains: Vec<DomainFilter>,
+    domains_none: Vec<DomainFilter>,
+    added_after: Option<i64>,
+    added_before: Option<i64>,
+    modified_after: Option<i64>,
+    modified_before: Option<i64>,
+    site_types: Vec<SiteType>,
+    site_types_none: Vec<SiteType>,
+    title_contains: Vec<String>,
+    title_excludes: Vec<String>,
+    languages: Vec<String>,
+    languages_none: Vec<String>,
+    author_contains: Vec<String>,
+    author_excludes: Vec<String>,
+    text: Vec<String>,
+    text_none: Vec<String>,
+    sort: SortOrder,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+impl BookmarkQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a filter expression like
+    /// `tag:rust -tag:old site:github.com added:>2024-01-01 type:article "async runtime"`.
+    ///
+    /// Supported filters are `tag:`, `site:` (host and subdomains), `host:`
+    /// (exact host), `type:`, `title:`, `lang:`, `author:`, `added:` and
+    /// `modified:`. Dates take an
+    /// optional `>`, `>=`, `<` or `<=` comparison. Filters other than dates can
+    /// be negated with a leading `-`. Anything else, including links like
+    /// `https://docs.rs`, is matched against the search index as a word or a
+    /// quoted phrase.
+    pub fn parse(expr: &str) -> Result<Self> {
+        query_parser::parse(expr)
+    }
+
+    pub fn tags_any<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tags_any.extend(tags.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn tags_all<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tags_all.extend(tags.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn tags_none<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tags_none.extend(tags.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn domain_exact(mut self, domain: &str) -> Self {
+        self.domains
+            .push(DomainFilter::Exact(domain.to_lowercase()));
+        self
+    }
+
+    pub fn domain_suffix(mut self, domain: &str) -> Self {
+        self.domains
+            .push(DomainFilter::Suffix(domain.to_lowercase()));
+        self
+    }
+
+    pub fn exclude_domain_exact(mut self, domain: &str) -> Self {
+        self.domains_none
+            .push(DomainFilter::Exact(domain.to_lowercase()));
+        self
+    }
+
+    pub fn exclude_domain_suffix(mut self, domain: &str) -> Self {
+        self.domains_none
+            .push(DomainFilter::Suffix(domain.to_lowercase()));
+        self
+    }
+
+    /// Only bookmarks added at or after `timestamp`
+    pub fn added_after(mut self, timestamp: i64) -> Self {
+        self.added_after = Some(timestamp);
+        self
+    }
+
+    /// Only bookmarks added before `timestamp`
+    pub fn added_before(mut self, timestamp: i64) -> Self {
+        self.added_before = Some(timestamp);
+        self
+    }
+
+    /// Only bookmarks modified at or after `timestamp`
+    pub fn modified_after(mut self, timestamp: i64) -> Self {
+        self.modified_after = Some(timestamp);
+        self
+    }
+
+    /// Only bookmarks modified before `timestamp`
+    pub fn modified_before(mut self, timestamp: i64) -> Self {
+        self.modified_before = Some(timestamp);
+        self
+    }
+
+    pub fn site_type(mut self, site_type: SiteType) -> Self {
+        self.site_types.push(site_type);
+        self
+    }
+
+    pub fn exclude_site_type(mut self, site_type: SiteType) -> Self {
+        self.site_types_none.push(site_type);
+        self
+    }
+
+    /// Only bookmarks whose title contains `text`, ignoring case
+    pub fn title_contains(mut self, text: &str) -> Self {
+        self.title_contains.push(text.to_owned());
+        self
+    }
+
+    /// Only bookmarks whose title doesn't contain `text`, ignoring case
+    pub fn title_excludes(mut self, text: &str) -> Self {
+        self.title_excludes.push(text.to_owned());
+        self
+    }
+
+    /// Only bookmarks of pages in `language` or one of its regional variants,
+    /// e.g. `en` matches `en` and `en-US`
+    pub fn language(mut self, language: &str) -> Self {
+        self.languages.push(language.to_owned());
+        self
+    }
+
+    pub fn exclude_language(mut self, language: &str) -> Self {
+        self.languages_none.push(language.to_owned());
+        self
+    }
+
+    /// Only bookmarks whose author contains `text`, ignoring case
+    pub fn author_contains(mut self, text: &str) -> Self {
+        self.author_contains.push(text.to_owned());
+        self
+    }
+
+    /// Only bookmarks whose author doesn't contain `text`, ignoring case
+    pub fn author_excludes(mut self, text: &str) -> Self {
+        self.author_excludes.push(text.to_owned());
+        self
+    }
+
+    /// Only bookmarks whose indexed text contains the phrase `text`. A trailing
+    /// `*` matches any word starting with the last word of the phrase.
+    pub fn matching(mut self, text: &str) -> Self {
+        self.text.push(text.to_owned());
+        self
+    }
+
+    /// Only bookmarks whose indexed text doesn't contain the phrase `text`
+    pub fn not_matching(mut self, text: &str) -> Self {
+        self.text_none.push(text.to_owned());
+        self
+    }
+
+    pub fn sort(mut self, sort: SortOrder) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Rewrites the tags of the tag filters with `f`, e.g. to normalize them
+    /// the way stored tags are.
+    pub(crate) fn map_tags<F>(&self, mut f: F) -> Result<Self>
+    where
+        F: FnMut(&str) -> Result<String>,
+    {
+        let mut query = self.clone();
+        for tags in [
+            &mut query.tags_any,
+            &mut query.tags_all,
+            &mut query.tags_none,
+        ] {
+            for tag in tags.iter_mut() {
+                *tag = f(tag)?;
+            }
+        }
+        Ok(query)
+    }
+
+    /// Compiles the query into SQL conditions to append to a `WHERE` clause
+    /// over `Bookmark AS b` and `Metadata AS m`, followed by the ordering and
+    /// paging clauses, along with the values to bind to its parameters.
+    pub(crate) fn compile(&self) -> (String, Vec<Value>) {
+        let mut sql = String::new();
+        let mut params = Vec::new();
+
+        if !self.tags_any.is_empty() {
+            sql += &format!(
+                " AND b.id IN (SELECT bookmark_id FROM `Tag` WHERE {})",
+                tag_conditions(&self.tags_any, &mut params)
+            );
+        }
+
+        for tag in &self.tags_all {
+            sql += &format!(
+                " AND b.id IN (SELECT bookmark_id FROM `Tag` WHERE {})",
+                tag_conditions(std::slice::from_ref(tag), &mut params)
+            );
+        }
+
+        if !self.tags_none.is_empty() {
+            sql += &format!(
+                " AND b.id NOT IN (SELECT bookmark_id FROM `Tag` WHERE {})",
+                tag_conditions(&self.tags_none, &mut params)
+            );
+        }
+
+        if !self.domains.is_empty() {
+            sql += &format!(" AND ({})", domain_conditions(&self.domains, &mut params));
+        }
+
+        if !self.domains_none.is_empty() {
+            sql += &format!(
+                " AND NOT ({})",
+                domain_conditions(&self.domains_none, &mut params)
+            );
+        }
+
+        let ranges = [
+            ("b.added_at >= ?", self.added_after),
+            ("b.added_at < ?", self.added_before),
+            ("b.last_modified >= ?", self.modified_after),
+            ("b.last_modified < ?", self.modified_before),
+        ];
+        for (condition, value) in ranges {
+            if let Some(ts) = value {
+                sql += &format!(" AND {}", condition);
+                params.push(Value::from(ts));
+            }
+        }
+
+        if !self.site_types.is_empty() {
+            sql += &format!(
+                " AND m.site_type IN ({})",
+                placeholders(self.site_types.len())
+            );
+            params.extend(self.site_types.iter().map(|t| Value::from(t.to_string())));
+        }
+
+        if !self.site_types_none.is_empty() {
+            sql += &format!(
+                " AND m.site_type NOT IN ({})",
+                placeholders(self.site_types_none.len())
+            );
+            params.extend(
+                self.site_types_none
+                    .iter()
+                    .map(|t| Value::from(t.to_string())),
+            );
+        }
+
+        for text in &self.title_contains {
+            sql += " AND m.title LIKE ? ESCAPE '\\'";
+            params.push(Value::from(format!("%{}%", escape_like(text))));
+        }
+
+        for text in &self.title_excludes {
+            sql += " AND m.title NOT LIKE ? ESCAPE '\\'";
+            params.push(Value::from(format!("%{}%", escape_like(text))));
+        }
+
+        if !self.languages.is_empty() {
+            sql += &format!(
+                " AND ({})",
+                language_conditions(&self.languages, &mut params)
+            );
+        }
+
+        if !self.languages_none.is_empty() {
+            sql += &format!(
+                " AND NOT coalesce({}, 0)",
+                language_conditions(&self.languages_none, &mut params)
+            );
+        }
+
+        for text in &self.author_contains {
+            sql += " AND m.author LIKE ? ESCAPE '\\'";
+            params.push(Value::from(format!("%{}%", escape_like(text))));
+        }
+
+        for text in &self.author_excludes {
+            sql += " AND coalesce(m.author, '') NOT LIKE ? ESCAPE '\\'";
+            params.push(Value::from(format!("%{}%", escape_like(text))));
+        }
+
+        for text in &self.text {
+            sql +=
+                " AND b.seq IN (SELECT rowid FROM `BookmarkSearch` WHERE `BookmarkSearch` MATCH ?)";
+            params.push(Value::from(fts_phrase(text)));
+        }
+
+        for text in &self.text_none {
+            sql += " AND b.seq NOT IN (SELECT rowid FROM `BookmarkSearch` WHERE `BookmarkSearch` MATCH ?)";
+            params.push(Value::from(fts_phrase(text)));
+        }
+
+        sql += &format!(" ORDER BY {}", self.sort);
+
+        if self.limit.is_some() || self.offset.is_some() {
+            sql += " LIMIT ? OFFSET ?";
+            params.push(Value::from(self.limit.map(|l| l as i64).unwrap_or(-1)));
+            params.push(Value::from(self.offset.unwrap_or(0) as i64));
+        }
+
+        (sql, params)
+    }
+}
+
+fn domain_conditions(domains: &[DomainFilter], params: &mut Vec<Value>) -> String {
+    domains
+        .iter()
+        .map(|domain| match domain {
+            DomainFilter::Exact(host) => {
+                params.push(Value::from(host.to_owned()));
+                "url_host(b.link) = ?"
+            }
+            DomainFilter::Suffix(host) => {
+                params.push(Value::from(host.to_owned()));
+                params.push(Value::from(format!("%.{}", escape_like(host))));
+                "(url_host(b.link) = ? OR url_host(b.link) LIKE ? ESCAPE '\\')"
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+fn language_conditions(languages: &[String], params: &mut Vec<Value>) -> String {
+    languages
+        .iter()
+        .map(|language| {
+            let language = escape_like(&language.replace('_', "-"));
+            params.push(Value::from(language.to_owned()));
+            params.push(Value::from(format!("{}-%", language)));
+            "m.language LIKE ? ESCAPE '\\' OR m.language LIKE ? ESCAPE '\\'"
+        })
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// Matches any of `tags` or their descendants, so `dev/rust` also matches
+/// `dev/rust/async`.
+fn tag_conditions(tags: &[String], params: &mut Vec<Value>) -> String {
+    tags.iter()
+        .map(|tag| {
+            let parent = format!("{}{}", tag, tags::SEPARATOR);
+            params.push(Value::from(tag.to_owned()));
+            params.push(Value::from(parent.chars().count() as i64));
+            params.push(Value::from(parent));
+            "tag = ? OR substr(tag, 1, ?) = ?"
+        })
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// Quotes `text` as an FTS5 phrase so that operators in it are matched literally.
+fn fts_phrase(text: &str) -> String {
+    let (text, prefix) = match text.strip_suffix('*') {
+        Some(text) => (text, " *"),
+        None => (text, ""),
+    };
+    format!("\"{}\"{}", text.replace('"', "\"\""), prefix)
+}
+
+fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
+
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
@@ -0,0 +1,290 @@
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use eyre::{eyre, Result};
+use pest::error::{Error, ErrorVariant};
+use pest::iterators::Pair;
+use pest::{Parser, Span};
+use pest_derive::Parser;
+
+use crate::query::BookmarkQuery;
+use crate::site_metadata::SiteType;
+
+#[derive(Parser)]
+#[grammar = "query.pest"]
+struct QueryParser;
+
+pub fn parse(expr: &str) -> Result<BookmarkQuery> {
+    let terms = QueryParser::parse(Rule::query, expr).map_err(|e| format_error(expr, e))?;
+
+    let mut query = BookmarkQuery::new();
+    for term in terms {
+        match term.as_rule() {
+            Rule::term => query = apply_term(query, term).map_err(|e| format_error(expr, e))?,
+            Rule::EOI => {}
+            _ => unreachable!(),
+        }
+    }
+
+    Ok(query)
+}
+
+fn apply_term(query: BookmarkQuery, term: Pair<Rule>) -> Result<BookmarkQuery, Error<Rule>> {
+    let mut negated = false;
+    let mut query = query;
+
+    for pair in term.into_inner() {
+        match pair.as_rule() {
+            Rule::negation => negated = true,
+            Rule::filter => query = apply_filter(query, pair, negated)?,
+            Rule::phrase | Rule::word => {
+                let text = text_value(pair);
+                query = match negated {
+                    true => query.not_matching(&text),
+                    false => query.matching(&text),
+                };
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    Ok(query)
+}
+
+fn apply_filter(
+    query: BookmarkQuery,
+    filter: Pair<Rule>,
+    negated: bool,
+) -> Result<BookmarkQuery, Error<Rule>> {
+    let filter_span = filter.as_span();
+    let mut inner = filter.into_inner();
+    let key = inner.next().unwrap();
+    let mut value = inner.next();
+
+    let mut comparison = None;
+    if let Some(pair) = value
+        .clone()
+        .filter(|pair| pair.as_rule() == Rule::comparison)
+    {
+        comparison = Some(pair);
+        value = inner.next();
+    }
+
+    let value = value.ok_or_else(|| custom_error(filter_span, "Expected a value"))?;
+    let value_span = value.as_span();
+    let text = text_value(value);
+
+    if text.is_empty() {
+        return Err(custom_error(value_span, "Expected a value"));
+    }
+
+    let key_name = key.as_str().to_lowercase();
+    if let Some(comparison) = &comparison {
+        if key_name != "added" && key_name != "modified" {
+            return Err(custom_error(
+                comparison.as_span(),
+                &format!("\"{}\" doesn't support comparisons", key.as_str()),
+            ));
+        }
+    }
+
+    let query = match (key_name.as_str(), negated) {
+        ("tag", false) => query.tags_all([text]),
+        ("tag", true) => query.tags_none([text]),
+        ("site", false) => query.domain_suffix(&text),
+        ("site", true) => query.exclude_domain_suffix(&text),
+        ("host", false) => query.domain_exact(&text),
+        ("host", true) => query.exclude_domain_exact(&text),
+        ("type", false) => query.site_type(SiteType::from(&text)),
+        ("type", true) => query.exclude_site_type(SiteType::from(&text)),
+        ("title", false) => query.title_contains(&text),
+        ("title", true) => query.title_excludes(&text),
+        ("lang", false) => query.language(&text),
+        ("lang", true) => query.exclude_language(&text),
+        ("author", false) => query.author_contains(&text),
+        ("author", true) => query.author_excludes(&text),
+        ("added" | "modified", true) => {
+            return Err(custom_error(
+                filter_span,
+                "Date filters can't be negated, use a comparison instead",
+            ))
+        }
+        ("added" | "modified", false) => {
+            let start = parse_date(&text).ok_or_else(|| {
+                custom_error(value_span, "Invalid date, expected YYYY-MM-DD")
+            })?;
+            let end = start + Duration::days(1).num_seconds();
+            let (after, before) = match comparison.as_ref().map(|c| c.as_str()) {
+                Some(">") => (Some(end), None),
+                Some(">=") => (Some(start), None),
+                Some("<") => (None, Some(start)),
+                Some("<=") => (None, Some(end)),
+                _ => (Some(start), Some(end)),
+            };
+            let mut query = query;
+            if key_name == "added" {
+                if let Some(ts) = after {
+                    query = query.added_after(ts);
+                }
+                if let Some(ts) = before {
+                    query = query.added_before(ts);
+                }
+            } else {
+                if let Some(ts) = after {
+                    query = query.modified_after(ts);
+                }
+                if let Some(ts) = before {
+                    query = query.modified_before(ts);
+                }
+            }
+            query
+        }
+        _ => {
+            return Err(custom_error(
+                key.as_span(),
+                &format!(
+                    "Unknown filter \"{}\". Expected one of tag, site, host, type, title, lang, author, added, modified",
+                    key.as_str()
+                ),
+            ))
+        }
+    };
+
+    Ok(query)
+}
+
+fn text_value(pair: Pair<Rule>) -> String {
+    match pair.as_rule() {
+        Rule::phrase => pair.into_inner().as_str().to_owned(),
+        _ => pair.as_str().to_owned(),
+    }
+}
+
+fn parse_date(text: &str) -> Option<i64> {
+    let date = NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)?;
+    Some(Utc.from_utc_datetime(&date).timestamp())
+}
+
+fn custom_error(span: Span, message: &str) -> Error<Rule> {
+    Error::new_from_span(
+        ErrorVariant::CustomError {
+            message: message.to_owned(),
+        },
+        span,
+    )
+}
+
+fn format_error(expr: &str, error: Error<Rule>) -> eyre::Report {
+    let error = error.renamed_rules(|rule| match rule {
+        Rule::term => "filter or search term".into(),
+        Rule::phrase | Rule::phrase_contents => "quoted text".into(),
+        Rule::word => "word".into(),
+        Rule::comparison => "comparison".into(),
+        Rule::EOI => "end of query".into(),
+        rule => format!("{:?}", rule),
+    });
+    eyre!("Invalid query \"{}\"\n{}", expr, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JAN_1: i64 = 1704067200;
+    const JAN_2: i64 = JAN_1 + 86400;
+
+    fn error(expr: &str) -> String {
+        parse(expr).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_filters_and_words() {
+        assert_eq!(
+            parse("tag:rust site:github.com host:docs.rs type:article async").unwrap(),
+            BookmarkQuery::new()
+                .tags_all(["rust"])
+                .domain_suffix("github.com")
+                .domain_exact("docs.rs")
+                .site_type(SiteType::Article)
+                .matching("async")
+        );
+        assert_eq!(parse("").unwrap(), BookmarkQuery::new());
+    }
+
+    #[test]
+    fn parses_negations() {
+        assert_eq!(
+            parse("-tag:old -site:github.com -title:draft -lang:de -runtime").unwrap(),
+            BookmarkQuery::new()
+                .tags_none(["old"])
+                .exclude_domain_suffix("github.com")
+                .title_excludes("draft")
+                .exclude_language("de")
+                .not_matching("runtime")
+        );
+        assert!(error("-added:2024-01-01").contains("Date filters can't be negated"));
+    }
+
+    #[test]
+    fn parses_date_comparisons() {
+        let cases = [
+            ("added:2024-01-01", Some(JAN_1), Some(JAN_2)),
+            ("added:>2024-01-01", Some(JAN_2), None),
+            ("added:>=2024-01-01", Some(JAN_1), None),
+            ("added:<2024-01-01", None, Some(JAN_1)),
+            ("added:<=2024-01-01", None, Some(JAN_2)),
+        ];
+        for (expr, after, before) in cases {
+            let mut expected = BookmarkQuery::new();
+            if let Some(ts) = after {
+                expected = expected.added_after(ts);
+            }
+            if let Some(ts) = before {
+                expected = expected.added_before(ts);
+            }
+            assert_eq!(parse(expr).unwrap(), expected, "{}", expr);
+        }
+
+        assert_eq!(
+            parse("modified:>=2024-01-01").unwrap(),
+            BookmarkQuery::new().modified_after(JAN_1)
+        );
+        assert!(error("added:>2024-13-01").contains("Invalid date"));
+        assert!(error("tag:>rust").contains("\"tag\" doesn't support comparisons"));
+    }
+
+    #[test]
+    fn parses_phrases() {
+        assert_eq!(
+            parse(r#""async runtime" title:"the book" -"old news""#).unwrap(),
+            BookmarkQuery::new()
+                .matching("async runtime")
+                .title_contains("the book")
+                .not_matching("old news")
+        );
+        assert!(error(r#"title:"""#).contains("Expected a value"));
+        assert!(error(r#""unclosed"#).contains("Invalid query"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let message = error("tags:rust");
+        assert!(message.contains("Unknown filter \"tags\""), "{}", message);
+    }
+
+    #[test]
+    fn rejects_empty_filters() {
+        assert!(error("tag:").contains("Expected a value"));
+        assert!(error("rust tag: web").contains("Expected a value"));
+    }
+
+    #[test]
+    fn parses_urls_as_words() {
+        assert_eq!(
+            parse("https://docs.rs -http://example.com").unwrap(),
+            BookmarkQuery::new()
+                .matching("https://docs.rs")
+                .not_matching("http://example.com")
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use std::{collections::HashMap, rc::Rc};
+
+use markup5ever_rcdom::{Handle, NodeData};
+use regex::Regex;
+
+use crate::utils;
+
+/// Elements that never hold article text.
+const SKIPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "nav", "header", "footer", "aside", "form", "iframe", "svg",
+    "button", "select", "template", "figure",
+];
+
+/// Elements whose text is kept as a separate paragraph.
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "pre",
+    "blockquote",
+    "li",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "td",
+    "dd",
+];
+
+/// Paragraphs shorter than this are ignored when scoring.
+const MIN_PARAGRAPH_LEN: usize = 25;
+
+/// Extracts the main text of a parsed HTML page, leaving out navigation, sidebars,
+/// comments and other boilerplate.
+///
+/// Follows the approach of Readability: paragraphs score points for their
+/// parent and grandparent elements based on their length and number of commas,
+/// scores are adjusted by class names and link density, and the text of the
+/// best scoring element is returned one paragraph per line.
+pub(crate) fn extract_text(document: &Handle) -> Option<String> {
+    let mut scorer = Scorer {
+        classes: ClassPatterns::new(),
+        scores: HashMap::new(),
+    };
+    scorer.score(document, &mut Vec::new());
+
+    let (top, _) = scorer
+        .scores
+        .values()
+        .map(|(node, score)| {
+            let weighted = score * (1.0 - link_density(node));
+            (node, weighted)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    let mut paragraphs = Vec::new();
+    collect_paragraphs(top, &scorer.classes, &mut paragraphs);
+    let text = paragraphs.join("\n\n");
+    (!text.is_empty()).then_some(text)
+}
+
+/// Class and id names hinting at whether an element holds the article.
+struct ClassPatterns {
+    unlikely: Regex,
+    likely: Regex,
+    positive: Regex,
+    negative: Regex,
+}
+
+impl ClassPatterns {
+    fn new() -> Self {
+        Self {
+            unlikely: Regex::new(r"(?i)comment|sidebar|footer|menu|share|social|sponsor|advert|promo|related|cookie|banner|popup|modal|subscribe|newsletter|breadcrumb").unwrap(),
+            likely: Regex::new(r"(?i)article|content|main|post|body|entry|text").unwrap(),
+            positive: Regex::new(r"(?i)article|content|main|post|body|entry|text|story").unwrap(),
+            negative: Regex::new(r"(?i)comment|meta|footer|sidebar|widget|hidden|masthead").unwrap(),
+        }
+    }
+
+    fn is_unlikely(&self, node: &Handle) -> bool {
+        let names = class_and_id(node);
+        self.unlikely.is_match(&names) && !self.likely.is_match(&names)
+    }
+
+    fn initial_score(&self, node: &Handle) -> f64 {
+        let names = class_and_id(node);
+        let mut score = match &node.data {
+            NodeData::Element { name, .. } => match name.local.as_ref() {
+                "article" | "main" => 10.0,
+                "div" | "section" => 5.0,
+                "pre" | "td" | "blockquote" => 3.0,
+                "ol" | "ul" | "dl" | "form" => -3.0,
+                "th" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => -5.0,
+                _ => 0.0,
+            },
+            _ => 0.0,
+        };
+        if self.positive.is_match(&names) {
+            score += 25.0;
+        }
+        if self.negative.is_match(&names) {
+            score -= 25.0;
+        }
+        score
+    }
+}
+
+struct Scorer {
+    classes: ClassPatterns,
+    /// Candidate elements by address, with their scores
+    scores: HashMap<usize, (Handle, f64)>,
+}
+
+impl Scorer {
+    fn score(&mut self, node: &Handle, ancestors: &mut Vec<Handle>) {
+        if let NodeData::Element { name, .. } = &node.data {
+            let tag = name.local.as_ref();
+            if SKIPPED_TAGS.contains(&tag) || self.classes.is_unlikely(node) {
+                return;
+            }
+
+            if matches!(tag, "p" | "pre" | "td" | "blockquote") {
+                let text = utils::squeeze_whitespaces(&text_content(node));
+                let text = text.trim();
+                if text.len() >= MIN_PARAGRAPH_LEN {
+                    let score = 1.0
+                        + text.matches(',').count() as f64
+                        + (text.len() as f64 / 100.0).min(3.0);
+                    let parents = ancestors.iter().rev().take(2);
+                    for (level, ancestor) in parents.enumerate() {
+                        let divider = (level + 1) as f64;
+                        self.entry(ancestor).1 += score / divider;
+                    }
+                }
+            }
+        }
+
+        ancestors.push(Rc::clone(node));
+        for child in node.children.borrow().iter() {
+            self.score(child, ancestors);
+        }
+        ancestors.pop();
+    }
+
+    fn entry(&mut self, node: &Handle) -> &mut (Handle, f64) {
+        let classes = &self.classes;
+        self.scores
+            .entry(Rc::as_ptr(node) as usize)
+            .or_insert_with(|| (Rc::clone(node), classes.initial_score(node)))
+    }
+}
+
+fn class_and_id(node: &Handle) -> String {
+    match &node.data {
+        NodeData::Element { attrs, .. } => attrs
+            .borrow()
+            .iter()
+            .filter(|attr| matches!(attr.name.local.as_ref(), "class" | "id"))
+            .map(|attr| attr.value.to_string())
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => String::new(),
+    }
+}
+
+/// Share of the text of `node` that is inside links.
+fn link_density(node: &Handle) -> f64 {
+    fn link_text_len(node: &Handle) -> usize {
+        match &node.data {
+            NodeData::Element { name, .. } if name.local.as_ref() == "a" => {
+                text_content(node).trim().len()
+            }
+            _ => node.children.borrow().iter().map(link_text_len).sum(),
+        }
+    }
+
+    let text_len = text_content(node).trim().len();
+    match text_len {
+        0 => 1.0,
+        _ => link_text_len(node) as f64 / text_len as f64,
+    }
+}
+
+fn text_content(node: &Handle) -> String {
+    let mut text = String::new();
+    fn walk(node: &Handle, text: &mut String) {
+        match &node.data {
+            NodeData::Text { contents } => text.push_str(&contents.borrow()),
+            NodeData::Element { name, .. } if SKIPPED_TAGS.contains(&name.local.as_ref()) => {}
+            _ => {
+                for child in node.children.borrow().iter() {
+                    walk(child, text);
+                }
+            }
+        }
+    }
+    walk(node, &mut text);
+    text
+}
+
+fn collect_paragraphs(node: &Handle, classes: &ClassPatterns, paragraphs: &mut Vec<String>) {
+    if let NodeData::Element { name, .. } = &node.data {
+        let tag = name.local.as_ref();
+        if SKIPPED_TAGS.contains(&tag) || classes.is_unlikely(node) {
+            return;
+        }
+        if BLOCK_TAGS.contains(&tag) {
+            let text = utils::squeeze_whitespaces(&text_content(node));
+            let text = text.trim();
+            if !text.is_empty() {
+                paragraphs.push(text.to_owned());
+            }
+            return;
+        }
+    }
+
+    for child in node.children.borrow().iter() {
+        collect_paragraphs(child, classes, paragraphs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use html5ever::{parse_document, tendril::TendrilSink};
+    use markup5ever_rcdom::RcDom;
+
+    use super::*;
+
+    fn extract(html: &str) -> Option<String> {
+        let dom = parse_document(RcDom::default(), Default::default()).one(html);
+        extract_text(&dom.document)
+    }
+
+    #[test]
+    fn leaves_out_boilerplate() {
+        let text = extract(include_str!("../tests/fixtures/article.html")).unwrap();
+        assert_eq!(
+            text,
+            "Growing tomatoes on a balcony\n\n\
+            Tomatoes need at least six hours of direct sun, so pick the sunniest corner of the balcony, and use a pot of twenty litres or more.\n\n\
+            Water them deeply every other day, in the morning, and feed them with a potassium-rich fertilizer once the first flowers appear.\n\n\
+            Compact varieties like Tumbling Tom stay small and don't need a cage."
+        );
+    }
+
+    #[test]
+    fn prefers_text_over_links() {
+        let text = extract(include_str!("../tests/fixtures/link_list.html")).unwrap();
+        assert_eq!(
+            text,
+            "This week was quiet, so there are only a few links to share.\n\n\
+            Next week we are back with the usual long list of reading."
+        );
+    }
+
+    #[test]
+    fn finds_nothing_without_paragraphs() {
+        assert_eq!(
+            extract(include_str!("../tests/fixtures/no_paragraphs.html")),
+            None
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use eyre::Result;
+
+use crate::{pool, site_metadata::SiteMetadata, utils};
+
+#[derive(Debug, Clone)]
+pub struct RefreshOptions {
+    /// Number of pages fetched at the same time
+    pub parallelism: usize,
+    /// Time allowed for each page, including redirects
+    pub timeout: Duration,
+    /// Minimum time between two requests to the same host
+    pub host_delay: Duration,
+}
+
+impl Default for RefreshOptions {
+    fn default() -> Self {
+        Self {
+            parallelism: 8,
+            timeout: Duration::from_secs(10),
+            host_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Fetches the metadata and readable text of `links` on
+/// `options.parallelism` threads, calling `on_result` on the calling thread
+/// with each link's index as soon as it has been fetched.
+pub(crate) fn fetch_concurrently<F>(links: &[String], options: &RefreshOptions, on_result: F)
+where
+    F: FnMut(usize, Result<(SiteMetadata, Option<String>)>),
+{
+    let limiter = HostLimiter::new(options.host_delay);
+    let timeout = options.timeout;
+    pool::run_concurrently(
+        links.to_vec(),
+        options.parallelism,
+        move |link| {
+            limiter.wait(&link);
+            SiteMetadata::get_metadata_with_text(&link, timeout)
+        },
+        on_result,
+    );
+}
+
+/// Spaces out requests to the same host so that refreshing many bookmarks
+/// from one site doesn't hammer it.
+#[derive(Clone)]
+pub(crate) struct HostLimiter {
+    delay: Duration,
+    /// When the next request to each host may start
+    next_slots: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl HostLimiter {
+    pub(crate) fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            next_slots: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Blocks until a request to the host of `link` is allowed.
+    pub(crate) fn wait(&self, link: &str) {
+        let host = match utils::get_host(link) {
+            Some(host) => host,
+            None => return,
+        };
+
+        let now = Instant::now();
+        let slot = match self.next_slots.lock() {
+            Ok(mut next_slots) => {
+                let slot = next_slots.get(&host).map_or(now, |&next| next.max(now));
+                next_slots.insert(host, slot + self.delay);
+                slot
+            }
+            Err(_) => return,
+        };
+        thread::sleep(slot - now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: Duration = Duration::from_millis(50);
+
+    /// Times at which `links` were let through by one limiter, each waited
+    /// for on its own thread, in order and relative to the start.
+    fn let_through(links: &[&str]) -> Vec<Duration> {
+        let limiter = Arc::new(HostLimiter::new(DELAY));
+        let start = Instant::now();
+        let threads: Vec<_> = links
+            .iter()
+            .map(|link| {
+                let limiter = Arc::clone(&limiter);
+                let link = link.to_string();
+                thread::spawn(move || {
+                    limiter.wait(&link);
+                    start.elapsed()
+                })
+            })
+            .collect();
+        let mut times: Vec<_> = threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect();
+        times.sort();
+        times
+    }
+
+    #[test]
+    fn spaces_requests_to_one_host() {
+        let times = let_through(&[
+            "https://example.com/a",
+            "https://example.com/b",
+            "http://example.com/c",
+        ]);
+        for (idx, time) in times.iter().enumerate() {
+            assert!(*time >= DELAY * idx as u32);
+        }
+    }
+
+    #[test]
+    fn lets_requests_to_other_hosts_through() {
+        let times = let_through(&[
+            "https://a.example/",
+            "https://b.example/",
+            "https://c.example/",
+            "not a link",
+        ]);
+        assert!(times.iter().all(|time| *time < DELAY));
+    }
+}
@@ -0,0 +1,16 @@
+use crate::bookmark::Bookmark;
+
+/// Marks the start of a matched term in [`SearchResult::snippet`].
+pub const HIGHLIGHT_START: &str = "\u{2}";
+/// Marks the end of a matched term in [`SearchResult::snippet`].
+pub const HIGHLIGHT_END: &str = "\u{3}";
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub bookmark: Bookmark,
+    /// Excerpt of the best matching field, with matched terms wrapped in
+    /// [`HIGHLIGHT_START`] and [`HIGHLIGHT_END`].
+    pub snippet: String,
+    /// BM25 relevance of the match. Lower is better.
+    pub rank: f64,
+}
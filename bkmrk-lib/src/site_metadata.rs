@@ -1,58 +1,311 @@
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display, rc::Rc, time::Duration};
 
-use eyre::{Result, WrapErr};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use eyre::{eyre, Result, WrapErr};
+use html5ever::{parse_document, tendril::TendrilSink};
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use serde::{Deserialize, Serialize};
+use url::Url;
 
-#[derive(Debug, Default, Clone)]
+use crate::{
+    extractors::{ExtractorRegistry, Page},
+    fetch, readability,
+};
+
+/// `<link>` types whose `href` is an RSS or Atom feed.
+const FEED_TYPES: &[&str] = &[
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+];
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SiteMetadata {
     pub title: String,
     pub description: Option<String>,
     pub image_url: Option<String>,
     pub site_type: SiteType,
+    pub favicon_url: Option<String>,
+    /// The page's preferred URL, as declared by `<link rel="canonical">` or
+    /// `og:url`
+    pub canonical_url: Option<String>,
+    pub site_name: Option<String>,
+    pub author: Option<String>,
+    /// Language tag, e.g. `en` or `pt-BR`
+    pub language: Option<String>,
+    /// Unix timestamp in seconds
+    pub published_at: Option<i64>,
+    /// Unix timestamp in seconds
+    pub modified_at: Option<i64>,
+    pub keywords: Vec<String>,
+    /// RSS / Atom feeds advertised by the page
+    pub feeds: Vec<String>,
+    /// Site specific details found by a [`crate::extractors::MetadataExtractor`],
+    /// e.g. the stars of a GitHub repository
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 impl SiteMetadata {
     pub fn get_metadata(url: &str) -> Result<Self> {
-        let info = webpage::Webpage::from_url(
-            url,
-            webpage::WebpageOptions {
-                allow_insecure: true,
-                ..Default::default()
-            },
+        Self::get_metadata_with_text(url, fetch::DEFAULT_TIMEOUT).map(|(metadata, _)| metadata)
+    }
+
+    /// Like [`SiteMetadata::get_metadata`], also returning the main text of
+    /// the page if any could be found.
+    pub(crate) fn get_metadata_with_text(
+        url: &str,
+        timeout: Duration,
+    ) -> Result<(Self, Option<String>)> {
+        let response =
+            fetch::fetch_with_timeout(url, timeout).wrap_err("Couldn't read from URL")?;
+        if response.status >= 400 {
+            return Err(eyre!("{} returned HTTP {}", url, response.status));
+        }
+        Self::from_html(
+            &response.text(),
+            &response.url,
+            &ExtractorRegistry::default(),
         )
-        .wrap_err("Couldn't read from URL")?;
+    }
+
+    /// Reads the metadata of an HTML page fetched from `url`, using the
+    /// matching extractor of `registry` for site specific details.
+    pub fn parse(html: &str, url: &str, registry: &ExtractorRegistry) -> Result<Self> {
+        Self::from_html(html, url, registry).map(|(metadata, _)| metadata)
+    }
+
+    /// Reads the metadata and main text of an HTML page fetched from `url`.
+    pub(crate) fn from_html(
+        html: &str,
+        url: &str,
+        registry: &ExtractorRegistry,
+    ) -> Result<(Self, Option<String>)> {
+        let dom = parse_document(RcDom::default(), Default::default())
+            .from_utf8()
+            .read_from(&mut html.as_bytes())
+            .wrap_err("Couldn't parse page")?;
+        let document = Rc::clone(&dom.document);
+        let text = readability::extract_text(&document);
+        let links = head_links(&document, url);
+        let page = Page::from_document(&document, url)?;
+        let info = webpage::HTML::from_dom(dom, None);
+        let meta = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| info.meta.get(*name))
+                .map(|value| value.trim().to_owned())
+                .filter(|value| !value.is_empty())
+        };
 
-        let title = info.html.title.unwrap_or("".into());
-        let description = info.html.description;
-        let site_type = SiteType::from(&info.html.opengraph.og_type);
+        let title = info.title.to_owned().unwrap_or_default();
+        let site_type = SiteType::from(&info.opengraph.og_type);
         let image_url = info
-            .html
             .opengraph
             .images
             .iter()
-            .map(|obj| obj.url.to_owned())
-            .next();
-        Ok(Self {
+            .find_map(|obj| resolve(url, &obj.url));
+
+        let favicon_url = links
+            .iter()
+            .find(|link| link.rel.iter().any(|rel| rel == "icon"))
+            .or_else(|| {
+                links
+                    .iter()
+                    .find(|link| link.rel.iter().any(|rel| rel == "apple-touch-icon"))
+            })
+            .map(|link| link.href.to_owned());
+        let canonical_url = links
+            .iter()
+            .find(|link| link.rel.iter().any(|rel| rel == "canonical"))
+            .map(|link| link.href.to_owned())
+            .or_else(|| meta(&["og:url"]).and_then(|href| resolve(url, &href)))
+            .filter(|canonical| is_plausible_canonical(url, canonical));
+        let feeds = links
+            .iter()
+            .filter(|link| link.rel.iter().any(|rel| rel == "alternate"))
+            .filter(|link| FEED_TYPES.contains(&link.link_type.as_str()))
+            .map(|link| link.href.to_owned())
+            .collect();
+
+        let author = meta(&["author", "article:author", "twitter:creator"])
+            .filter(|author| !author.starts_with("http"));
+        let language = info
+            .language
+            .to_owned()
+            .or_else(|| meta(&["content-language", "og:locale"]))
+            .and_then(|lang| {
+                lang.split(',')
+                    .next()
+                    .map(|lang| lang.trim().replace('_', "-"))
+            })
+            .filter(|lang| !lang.is_empty());
+        let published_at = meta(&[
+            "article:published_time",
+            "og:published_time",
+            "datePublished",
+            "date",
+            "dc.date",
+            "DC.date.issued",
+        ])
+        .and_then(|date| parse_timestamp(&date));
+        let modified_at = meta(&[
+            "article:modified_time",
+            "og:updated_time",
+            "dateModified",
+            "last-modified",
+        ])
+        .and_then(|date| parse_timestamp(&date));
+        let keywords = meta(&["keywords", "news_keywords"])
+            .map(|keywords| {
+                keywords
+                    .split(',')
+                    .map(|keyword| keyword.trim().to_owned())
+                    .filter(|keyword| !keyword.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut metadata = Self {
             title,
-            description,
+            description: info.description.to_owned(),
             image_url,
             site_type,
-        })
+            favicon_url,
+            canonical_url,
+            site_name: meta(&["og:site_name", "application-name"]),
+            author,
+            language,
+            published_at,
+            modified_at,
+            keywords,
+            feeds,
+            extra: BTreeMap::new(),
+        };
+        registry.extract(&page, &mut metadata);
+        Ok((metadata, text))
+    }
+
+    /// Whether the details other than title, description, image and type
+    /// differ from `other`'s.
+    pub fn details_differ(&self, other: &SiteMetadata) -> bool {
+        self.favicon_url != other.favicon_url
+            || self.canonical_url != other.canonical_url
+            || self.site_name != other.site_name
+            || self.author != other.author
+            || self.language != other.language
+            || self.published_at != other.published_at
+            || self.modified_at != other.modified_at
+            || self.keywords != other.keywords
+            || self.feeds != other.feeds
+            || self.extra != other.extra
+    }
+}
+
+/// A `<link>` element of a page, with `href` resolved to an absolute URL.
+struct HeadLink {
+    rel: Vec<String>,
+    link_type: String,
+    href: String,
+}
+
+fn head_links(document: &Handle, page_url: &str) -> Vec<HeadLink> {
+    fn walk(node: &Handle, page_url: &str, links: &mut Vec<HeadLink>) {
+        if let NodeData::Element { name, attrs, .. } = &node.data {
+            if name.local.as_ref() == "link" {
+                let attrs = attrs.borrow();
+                let attr = |key: &str| {
+                    attrs
+                        .iter()
+                        .find(|attr| attr.name.local.as_ref() == key)
+                        .map(|attr| attr.value.to_string())
+                };
+                if let Some(href) = attr("href").and_then(|href| resolve(page_url, &href)) {
+                    links.push(HeadLink {
+                        rel: attr("rel")
+                            .unwrap_or_default()
+                            .to_lowercase()
+                            .split_whitespace()
+                            .map(str::to_owned)
+                            .collect(),
+                        link_type: attr("type").unwrap_or_default().to_lowercase(),
+                        href,
+                    });
+                }
+            }
+        }
+        for child in node.children.borrow().iter() {
+            walk(child, page_url, links);
+        }
+    }
+
+    let mut links = Vec::new();
+    walk(document, page_url, &mut links);
+    links
+}
+
+fn resolve(base: &str, href: &str) -> Option<String> {
+    let url = Url::parse(base).ok()?.join(href.trim()).ok()?;
+    match url.scheme() {
+        "http" | "https" => Some(url.to_string()),
+        _ => None,
+    }
+}
+
+/// Some sites declare their home page as the canonical URL of every page,
+/// which would make all their bookmarks look like duplicates. Such canonical
+/// URLs are ignored unless the page itself is a home page.
+fn is_plausible_canonical(page_url: &str, canonical: &str) -> bool {
+    let is_root = |url: &str| {
+        Url::parse(url)
+            .is_ok_and(|url| url.path().trim_end_matches('/').is_empty() && url.query().is_none())
+    };
+    !is_root(canonical) || is_root(page_url)
+}
+
+pub(crate) fn parse_timestamp(date: &str) -> Option<i64> {
+    let date = date.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(date) {
+        return Some(datetime.timestamp());
+    }
+    if let Ok(datetime) = DateTime::parse_from_rfc2822(date) {
+        return Some(datetime.timestamp());
+    }
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S") {
+        return Some(Utc.from_utc_datetime(&datetime).timestamp());
     }
+    let day = date.get(..10)?;
+    let datetime = NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)?;
+    Some(Utc.from_utc_datetime(&datetime).timestamp())
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SiteType {
+    #[serde(rename = "music.song")]
     MusicSong,
+    #[serde(rename = "music.album")]
     MusicAlbum,
+    #[serde(rename = "music.playlist")]
     MusicPlaylist,
+    #[serde(rename = "music.radio_station")]
     MusicRadioStation,
+    #[serde(rename = "video.movie")]
     VideoMovie,
+    #[serde(rename = "video.episode")]
     VideoEpisode,
+    #[serde(rename = "video.tv_show")]
     VideoTvShow,
+    #[serde(rename = "video.other")]
     VideoOther,
+    #[serde(rename = "article")]
     Article,
+    #[serde(rename = "book")]
     Book,
+    #[serde(rename = "profile")]
     Profile,
+    #[serde(rename = "website", other)]
     Website,
 }
 
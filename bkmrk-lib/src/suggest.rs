@@ -0,0 +1,277 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{bookmark::Bookmark, tags, utils};
+
+/// Suggestions scoring below this are left out.
+const MIN_SCORE: f64 = 0.25;
+const MAX_SUGGESTIONS: usize = 8;
+/// Number of most similar bookmarks whose tags are considered.
+const NEIGHBOURS: usize = 20;
+
+const DOMAIN_WEIGHT: f64 = 1.0;
+const TITLE_WEIGHT: f64 = 1.0;
+const TEXT_WEIGHT: f64 = 1.5;
+/// Added for tags whose name appears in the title or description.
+const NAME_BONUS: f64 = 0.5;
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "how", "in", "is", "it", "its",
+    "of", "on", "or", "that", "the", "this", "to", "was", "what", "when", "why", "with", "you",
+    "your", "www", "com", "org", "net", "http", "https", "html",
+];
+
+/// A tag suggested for a bookmark.
+#[derive(Debug, Clone)]
+pub struct TagSuggestion {
+    pub tag: String,
+    /// Between 0 and 1, higher is more likely to fit
+    pub score: f64,
+}
+
+/// Suggests tags for `bookmark` from the tags of similar bookmarks in
+/// `library`, most likely first. Tags the bookmark already has aren't
+/// suggested.
+///
+/// Bookmarks are similar if they are on the same site, share title words, or
+/// have similar titles and descriptions by TF-IDF. A tag scores the share of
+/// the similarity of the most similar bookmarks that carry it, scaled down
+/// when they are only loosely similar, plus a bonus if its name appears in
+/// the bookmark's title or description.
+pub(crate) fn suggest_tags(bookmark: &Bookmark, library: &[Bookmark]) -> Vec<TagSuggestion> {
+    let library: Vec<&Bookmark> = library
+        .iter()
+        .filter(|other| other.id != bookmark.id && !other.tags.0.is_empty())
+        .collect();
+
+    let documents: Vec<Vec<String>> = library.iter().map(|other| document(other)).collect();
+    let idf = inverse_document_frequencies(&documents);
+    let vector = tf_idf(&document(bookmark), &idf);
+
+    let host = utils::get_host(&bookmark.link);
+    let title: HashSet<String> = words(&bookmark.metadata.title).collect();
+
+    let mut neighbours: Vec<(&Bookmark, f64)> = library
+        .iter()
+        .zip(&documents)
+        .map(|(other, doc)| {
+            let same_host = host.is_some() && utils::get_host(&other.link) == host;
+            let other_title: HashSet<String> = words(&other.metadata.title).collect();
+            let similarity = DOMAIN_WEIGHT * same_host as u8 as f64
+                + TITLE_WEIGHT * jaccard(&title, &other_title)
+                + TEXT_WEIGHT * cosine(&vector, &tf_idf(doc, &idf));
+            (*other, similarity)
+        })
+        .filter(|(_, similarity)| *similarity > 0.0)
+        .collect();
+    neighbours.sort_by(|a, b| b.1.total_cmp(&a.1));
+    neighbours.truncate(NEIGHBOURS);
+
+    // Shares are scaled down when there is little similarity to go by, so a
+    // single loosely related bookmark doesn't make its tags certain.
+    let total: f64 = neighbours.iter().map(|(_, similarity)| similarity).sum();
+    let confidence = total / (total + 1.0);
+    let mut scores: HashMap<&str, f64> = HashMap::new();
+    for (other, similarity) in &neighbours {
+        for tag in &other.tags.0 {
+            *scores.entry(tag).or_default() += confidence * similarity / total;
+        }
+    }
+
+    let text: HashSet<String> = document(bookmark).into_iter().collect();
+    let named = |tag: &str| {
+        let name = tag.rsplit(tags::SEPARATOR).next().unwrap_or(tag);
+        let mut name_words = words(name).peekable();
+        name_words.peek().is_some() && name_words.all(|word| text.contains(&word))
+    };
+
+    let library_tags: HashSet<&str> = library
+        .iter()
+        .flat_map(|other| other.tags.0.iter().map(String::as_str))
+        .collect();
+    let mut suggestions: Vec<TagSuggestion> = library_tags
+        .into_iter()
+        .filter(|tag| !bookmark.tags.0.iter().any(|own| own == tag))
+        .map(|tag| {
+            let score = scores.get(tag).copied().unwrap_or_default()
+                + if named(tag) { NAME_BONUS } else { 0.0 };
+            TagSuggestion {
+                tag: tag.to_owned(),
+                score: score.min(1.0),
+            }
+        })
+        .filter(|suggestion| suggestion.score >= MIN_SCORE)
+        .collect();
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.tag.cmp(&b.tag)));
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+/// Lowercase words of `text` without stop words and single characters.
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() > 1)
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+}
+
+/// Words of the title and description of `bookmark`.
+fn document(bookmark: &Bookmark) -> Vec<String> {
+    let description = bookmark.metadata.description.as_deref().unwrap_or_default();
+    words(&bookmark.metadata.title)
+        .chain(words(description))
+        .collect()
+}
+
+fn inverse_document_frequencies(documents: &[Vec<String>]) -> HashMap<String, f64> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for document in documents {
+        let unique: HashSet<&str> = document.iter().map(String::as_str).collect();
+        for word in unique {
+            *counts.entry(word).or_default() += 1;
+        }
+    }
+    let total = documents.len() as f64;
+    counts
+        .into_iter()
+        .map(|(word, count)| (word.to_owned(), (1.0 + total / count as f64).ln()))
+        .collect()
+}
+
+/// TF-IDF weights of the words in `document`. Words no other document has
+/// are left out, as they can't make documents similar.
+fn tf_idf(document: &[String], idf: &HashMap<String, f64>) -> HashMap<String, f64> {
+    let mut vector: HashMap<String, f64> = HashMap::new();
+    for word in document {
+        if let Some(weight) = idf.get(word) {
+            *vector.entry(word.to_owned()).or_default() += weight;
+        }
+    }
+    vector
+}
+
+fn cosine(a: &HashMap<String, f64>, b: &HashMap<String, f64>) -> f64 {
+    let dot: f64 = a
+        .iter()
+        .filter_map(|(word, weight)| b.get(word).map(|other| weight * other))
+        .sum();
+    let norm = |v: &HashMap<String, f64>| v.values().map(|w| w * w).sum::<f64>().sqrt();
+    match norm(a) * norm(b) {
+        n if n > 0.0 => dot / n,
+        _ => 0.0,
+    }
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    match a.union(b).count() {
+        0 => 0.0,
+        union => a.intersection(b).count() as f64 / union as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library() -> Vec<Bookmark> {
+        let library = vec![
+            Bookmark::sample(
+                "https://docs.rs/serde",
+                "serde docs",
+                &["rust", "serialization"],
+            ),
+            Bookmark::sample("https://docs.rs/regex", "regex docs", &["rust", "text"]),
+            Bookmark::sample("https://docs.rs/rand", "rand docs", &["rust"]),
+            Bookmark::sample(
+                "https://bakery.example/bread",
+                "Sourdough bread recipe",
+                &["cooking"],
+            ),
+            Bookmark::sample(
+                "https://example.org/notes",
+                "Cluster notes",
+                &["dev/kubernetes"],
+            ),
+        ];
+        library
+            .into_iter()
+            .enumerate()
+            .map(|(idx, mut bookmark)| {
+                bookmark.id = (idx + 1).to_string();
+                bookmark
+            })
+            .collect()
+    }
+
+    fn tags(suggestions: &[TagSuggestion]) -> Vec<&str> {
+        suggestions.iter().map(|s| s.tag.as_str()).collect()
+    }
+
+    #[test]
+    fn suggests_tags_of_the_same_site() {
+        let new = Bookmark::sample("https://docs.rs/tokio", "tokio", &[]);
+        let suggestions = suggest_tags(&new, &library());
+        assert_eq!(tags(&suggestions)[0], "rust");
+        assert!(suggestions[0].score > 0.5);
+        assert!(!tags(&suggestions).contains(&"cooking"));
+    }
+
+    #[test]
+    fn suggests_tags_of_similar_titles() {
+        let new = Bookmark::sample("https://blog.example/rye", "Rye bread recipe", &[]);
+        assert_eq!(tags(&suggest_tags(&new, &library())), ["cooking"]);
+    }
+
+    #[test]
+    fn suggests_tags_named_in_the_title() {
+        let new = Bookmark::sample("https://k8s.example/", "Kubernetes basics", &[]);
+        let suggestions = suggest_tags(&new, &library());
+        assert_eq!(tags(&suggestions), ["dev/kubernetes"]);
+        assert_eq!(suggestions[0].score, NAME_BONUS);
+    }
+
+    #[test]
+    fn leaves_out_tags_the_bookmark_has() {
+        let new = Bookmark::sample("https://docs.rs/tokio", "tokio", &["rust"]);
+        assert!(!tags(&suggest_tags(&new, &library())).contains(&"rust"));
+    }
+
+    #[test]
+    fn ignores_the_bookmark_itself() {
+        let library = library();
+        let suggestions = suggest_tags(&library[3], &library);
+        assert!(suggestions.is_empty());
+    }
+
+    #[test]
+    fn leaves_out_unlikely_tags() {
+        let new = Bookmark::sample("https://unrelated.example/", "Gardening tips", &[]);
+        assert!(suggest_tags(&new, &library()).is_empty());
+
+        // Each of four bookmarks on the same site carries a fifth of the
+        // similarity, too little for the tags only one of them has
+        let mut library = library();
+        let mut extra = Bookmark::sample("https://docs.rs/clap", "clap docs", &["rust"]);
+        extra.id = "6".to_owned();
+        library.push(extra);
+        let new = Bookmark::sample("https://docs.rs/tokio", "tokio", &[]);
+        assert_eq!(tags(&suggest_tags(&new, &library)), ["rust"]);
+    }
+
+    #[test]
+    fn ranks_and_limits_suggestions() {
+        let many: Vec<String> = (0..12).map(|i| format!("tag{:02}", i)).collect();
+        let many: Vec<&str> = many.iter().map(String::as_str).collect();
+        let mut library = library();
+        let mut extra = Bookmark::sample("https://docs.rs/anyhow", "anyhow docs", &many);
+        extra.id = "6".to_owned();
+        library.push(extra);
+
+        let new = Bookmark::sample("https://docs.rs/anyhow/latest", "anyhow docs", &[]);
+        let suggestions = suggest_tags(&new, &library);
+        assert_eq!(suggestions.len(), MAX_SUGGESTIONS);
+        assert!(suggestions
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+        assert!(suggestions.iter().all(|s| s.score <= 1.0));
+    }
+}
@@ -0,0 +1,500 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use eyre::{eyre, Result};
+
+use crate::bookmark::Bookmark;
+
+/// Separates the levels of a hierarchical tag like `dev/rust/async`.
+pub const SEPARATOR: char = '/';
+
+/// Rules that make differently written tags compare equal, e.g. `Rust Lang`,
+/// `rust_lang` and `rust-lang`. Each level of a hierarchical tag is
+/// normalized separately and surrounding whitespace is always trimmed.
+/// Explicit synonyms like `rustlang` for `rust` are stored as aliases in the
+/// database and applied after these rules.
+#[derive(Debug, Clone)]
+pub struct TagNormalizer {
+    /// Lowercase tags
+    pub fold_case: bool,
+    /// Replace runs of whitespace, `_` and `-` with a single `-`
+    pub hyphenate: bool,
+}
+
+impl Default for TagNormalizer {
+    fn default() -> Self {
+        Self {
+            fold_case: true,
+            hyphenate: true,
+        }
+    }
+}
+
+impl TagNormalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies the rules to `tag`. Empty levels are dropped, so the result
+    /// may be empty.
+    pub fn normalize(&self, tag: &str) -> String {
+        tag.split(SEPARATOR)
+            .map(|level| {
+                let level = match self.fold_case {
+                    true => level.trim().to_lowercase(),
+                    false => level.trim().to_owned(),
+                };
+                match self.hyphenate {
+                    true => level
+                        .split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+                        .filter(|word| !word.is_empty())
+                        .collect::<Vec<_>>()
+                        .join("-"),
+                    false => level,
+                }
+            })
+            .filter(|level| !level.is_empty())
+            .collect::<Vec<_>>()
+            .join(&SEPARATOR.to_string())
+    }
+}
+
+/// A tag that `bkmrk tag normalize` rewrites.
+#[derive(Debug, Clone)]
+pub struct TagChange {
+    pub from: String,
+    /// Normalized tag, or `None` if nothing is left of the tag
+    pub to: Option<String>,
+    /// Number of bookmarks with the tag
+    pub count: usize,
+}
+
+/// A change to the tags of many bookmarks. Operations on a tag also apply to
+/// its descendants, so merging `js` into `javascript` turns `js/react` into
+/// `javascript/react`.
+#[derive(Debug, Clone)]
+pub enum TagOperation {
+    /// Replaces each of `sources` with `into`
+    Merge { sources: Vec<String>, into: String },
+    /// Replaces `tag` with all of `into`
+    Split { tag: String, into: Vec<String> },
+    /// Adds `tags`
+    Add { tags: Vec<String> },
+    /// Removes `tags`
+    Remove { tags: Vec<String> },
+}
+
+impl TagOperation {
+    /// Rewrites every tag of the operation with `f`, e.g. to normalize them.
+    pub(crate) fn map_tags<F>(&self, mut f: F) -> Result<Self>
+    where
+        F: FnMut(&str) -> Result<String>,
+    {
+        let mut map_all =
+            |tags: &[String]| tags.iter().map(|tag| f(tag)).collect::<Result<Vec<_>>>();
+        Ok(match self {
+            Self::Merge { sources, into } => Self::Merge {
+                sources: map_all(sources)?,
+                into: map_all(std::slice::from_ref(into))?.remove(0),
+            },
+            Self::Split { tag, into } => Self::Split {
+                tag: map_all(std::slice::from_ref(tag))?.remove(0),
+                into: map_all(into)?,
+            },
+            Self::Add { tags } => Self::Add {
+                tags: map_all(tags)?,
+            },
+            Self::Remove { tags } => Self::Remove {
+                tags: map_all(tags)?,
+            },
+        })
+    }
+
+    /// The tags of a bookmark with `tags` after the operation.
+    pub fn apply(&self, tags: &[String]) -> Vec<String> {
+        let (removed, added): (Vec<&str>, Vec<String>) = match self {
+            Self::Merge { sources, into } => moved(tags, sources, std::slice::from_ref(into)),
+            Self::Split { tag, into } => moved(tags, std::slice::from_ref(tag), into),
+            Self::Add { tags } => (Vec::new(), tags.to_owned()),
+            Self::Remove { tags: removed } => (
+                tags.iter()
+                    .map(String::as_str)
+                    .filter(|tag| removed.iter().any(|parent| is_within(tag, parent)))
+                    .collect(),
+                Vec::new(),
+            ),
+        };
+
+        let mut result: Vec<String> = tags
+            .iter()
+            .filter(|tag| !removed.contains(&tag.as_str()))
+            .cloned()
+            .collect();
+        for tag in added {
+            if !result.contains(&tag) {
+                result.push(tag);
+            }
+        }
+        result
+    }
+}
+
+/// Tags within any of `sources` and their replacements under each of
+/// `targets`, keeping the part below the source.
+fn moved<'a>(
+    tags: &'a [String],
+    sources: &[String],
+    targets: &[String],
+) -> (Vec<&'a str>, Vec<String>) {
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    for tag in tags {
+        let source = sources
+            .iter()
+            .filter(|source| is_within(tag, source))
+            .max_by_key(|source| source.len());
+        if let Some(source) = source {
+            removed.push(tag.as_str());
+            let rest = &tag[source.len()..];
+            added.extend(targets.iter().map(|target| format!("{}{}", target, rest)));
+        }
+    }
+    (removed, added)
+}
+
+/// The tags of one bookmark changed by a [`TagOperation`].
+#[derive(Debug, Clone)]
+pub struct TagEdit {
+    pub bookmark: Bookmark,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl TagEdit {
+    /// Compares the tags of `bookmark` with `tags`. Returns `None` if they
+    /// are the same.
+    pub(crate) fn new(bookmark: &Bookmark, tags: &[String]) -> Option<Self> {
+        let old = &bookmark.tags.0;
+        let added: Vec<_> = tags
+            .iter()
+            .filter(|tag| !old.contains(tag))
+            .cloned()
+            .collect();
+        let removed: Vec<_> = old
+            .iter()
+            .filter(|tag| !tags.contains(tag))
+            .cloned()
+            .collect();
+        (!added.is_empty() || !removed.is_empty()).then(|| Self {
+            bookmark: bookmark.to_owned(),
+            added,
+            removed,
+        })
+    }
+}
+
+/// Joins folder names into one hierarchical tag, e.g. `["Dev", "Rust"]` into
+/// `Dev/Rust`. Separators inside a name are replaced so that each name stays a
+/// single level.
+pub fn from_path<I, S>(names: I) -> Option<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let levels: Vec<String> = names
+        .into_iter()
+        .map(|name| name.as_ref().trim().replace(SEPARATOR, "-"))
+        .filter(|name| !name.is_empty())
+        .collect();
+    (!levels.is_empty()).then(|| levels.join(&SEPARATOR.to_string()))
+}
+
+/// The tag and its ancestors, outermost first: `dev`, `dev/rust` and
+/// `dev/rust/async` for `dev/rust/async`.
+pub fn prefixes(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices(SEPARATOR)
+        .map(move |(idx, _)| &tag[..idx])
+        .chain(std::iter::once(tag))
+}
+
+/// Whether `tag` is `parent` or one of its descendants.
+pub fn is_within(tag: &str, parent: &str) -> bool {
+    tag == parent
+        || tag
+            .strip_prefix(parent)
+            .is_some_and(|rest| rest.starts_with(SEPARATOR))
+}
+
+/// Colors that can be given by name instead of `#rrggbb`.
+const NAMED_COLORS: &[(&str, &str)] = &[
+    ("red", "#e5484d"),
+    ("orange", "#f76808"),
+    ("yellow", "#f5d90a"),
+    ("green", "#30a46c"),
+    ("teal", "#12a594"),
+    ("cyan", "#05a2c2"),
+    ("blue", "#0091ff"),
+    ("purple", "#8e4ec6"),
+    ("pink", "#d6409f"),
+    ("brown", "#ad7f58"),
+    ("gray", "#8f8f8f"),
+    ("white", "#ffffff"),
+    ("black", "#000000"),
+];
+
+/// Turns a color name, `#rgb` or `#rrggbb` into `#rrggbb`.
+pub fn parse_color(color: &str) -> Result<String> {
+    let color = color.trim().to_lowercase();
+    if let Some((_, hex)) = NAMED_COLORS.iter().find(|(name, _)| *name == color) {
+        return Ok(hex.to_string());
+    }
+
+    let digits = color.strip_prefix('#').unwrap_or(&color);
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(eyre!("Unknown color \"{}\"", color));
+    }
+    match digits.len() {
+        3 => Ok(format!(
+            "#{}",
+            digits.chars().flat_map(|c| [c, c]).collect::<String>()
+        )),
+        6 => Ok(format!("#{}", digits)),
+        _ => Err(eyre!("Unknown color \"{}\"", color)),
+    }
+}
+
+/// What a tag is for and how it's shown.
+#[derive(Debug, Clone, Default)]
+pub struct TagInfo {
+    pub tag: String,
+    pub description: Option<String>,
+    /// `#rrggbb`, see [`parse_color`]
+    pub color: Option<String>,
+    /// Emoji or short text shown before the tag
+    pub icon: Option<String>,
+    /// Pinned tags are listed before others
+    pub pinned: bool,
+}
+
+impl TagInfo {
+    pub fn new(tag: &str) -> Self {
+        Self {
+            tag: tag.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Red, green and blue parts of the color.
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        let digits = self.color.as_deref()?.strip_prefix('#')?;
+        let part = |idx: usize| u8::from_str_radix(digits.get(idx..idx + 2)?, 16).ok();
+        Some((part(0)?, part(2)?, part(4)?))
+    }
+
+    /// Whether nothing but the tag is set.
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none() && self.color.is_none() && self.icon.is_none() && !self.pinned
+    }
+}
+
+/// Orders `tags` with pinned tags first, keeping the order otherwise.
+pub fn pinned_first(tags: &mut [String], infos: &HashMap<String, TagInfo>) {
+    tags.sort_by_key(|tag| !infos.get(tag).is_some_and(|info| info.pinned));
+}
+
+/// A level of the tag hierarchy.
+#[derive(Debug, Clone)]
+pub struct TagNode {
+    /// Full tag, e.g. `dev/rust`
+    pub tag: String,
+    /// Bookmarks tagged with exactly this tag
+    pub count: usize,
+    /// Bookmarks tagged with this tag or any of its descendants
+    pub total: usize,
+    pub info: Option<TagInfo>,
+    pub children: Vec<TagNode>,
+}
+
+impl TagNode {
+    /// Last level of the tag, e.g. `rust` for `dev/rust`
+    pub fn name(&self) -> &str {
+        self.tag
+            .rsplit_once(SEPARATOR)
+            .map_or(self.tag.as_str(), |(_, name)| name)
+    }
+}
+
+/// Builds the tag hierarchy from `(bookmark id, tag)` pairs. Levels without
+/// bookmarks of their own, like `dev` when only `dev/rust` is used, are
+/// included. Siblings are ordered with pinned tags first, then by their total
+/// count, most used first.
+pub(crate) fn build_tree(
+    pairs: &[(String, String)],
+    infos: &HashMap<String, TagInfo>,
+) -> Vec<TagNode> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut bookmarks: BTreeMap<&str, HashSet<&str>> = BTreeMap::new();
+    for (bookmark_id, tag) in pairs {
+        *counts.entry(tag).or_default() += 1;
+        for prefix in prefixes(tag).filter(|prefix| !prefix.is_empty()) {
+            bookmarks.entry(prefix).or_default().insert(bookmark_id);
+        }
+    }
+
+    let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+    for &tag in bookmarks.keys() {
+        let parent = tag.rsplit_once(SEPARATOR).map_or("", |(parent, _)| parent);
+        children.entry(parent).or_default().push(tag);
+    }
+
+    fn build(
+        parent: &str,
+        children: &HashMap<&str, Vec<&str>>,
+        counts: &HashMap<&str, usize>,
+        bookmarks: &BTreeMap<&str, HashSet<&str>>,
+        infos: &HashMap<String, TagInfo>,
+    ) -> Vec<TagNode> {
+        let mut nodes: Vec<TagNode> = children
+            .get(parent)
+            .map(|tags| {
+                tags.iter()
+                    .map(|&tag| TagNode {
+                        tag: tag.to_owned(),
+                        count: counts.get(tag).copied().unwrap_or_default(),
+                        total: bookmarks[tag].len(),
+                        info: infos.get(tag).cloned(),
+                        children: build(tag, children, counts, bookmarks, infos),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let pinned = |node: &TagNode| node.info.as_ref().is_some_and(|info| info.pinned);
+        nodes.sort_by(|a, b| {
+            pinned(b)
+                .cmp(&pinned(a))
+                .then_with(|| b.total.cmp(&a.total))
+                .then_with(|| a.tag.cmp(&b.tag))
+        });
+        nodes
+    }
+
+    build("", &children, &counts, &bookmarks, infos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    fn merge(sources: &[&str], into: &str) -> TagOperation {
+        TagOperation::Merge {
+            sources: strings(sources),
+            into: into.to_owned(),
+        }
+    }
+
+    #[test]
+    fn normalizes_each_level() {
+        let normalizer = TagNormalizer::new();
+        assert_eq!(
+            normalizer.normalize(" Dev / Rust Lang/async__IO "),
+            "dev/rust-lang/async-io"
+        );
+        assert_eq!(normalizer.normalize("web - dev//CSS"), "web-dev/css");
+        assert_eq!(normalizer.normalize(" / _ "), "");
+    }
+
+    #[test]
+    fn normalizes_with_rules_turned_off() {
+        let normalizer = TagNormalizer {
+            fold_case: false,
+            hyphenate: true,
+        };
+        assert_eq!(
+            normalizer.normalize("Rust_Lang/Web Dev"),
+            "Rust-Lang/Web-Dev"
+        );
+
+        let normalizer = TagNormalizer {
+            fold_case: true,
+            hyphenate: false,
+        };
+        assert_eq!(
+            normalizer.normalize(" Rust_Lang / Web Dev "),
+            "rust_lang/web dev"
+        );
+    }
+
+    #[test]
+    fn merges_into_existing_tag() {
+        let operation = merge(&["js"], "javascript");
+        assert_eq!(
+            operation.apply(&strings(&["js", "javascript", "web"])),
+            strings(&["javascript", "web"])
+        );
+        assert_eq!(
+            operation.apply(&strings(&["web", "js"])),
+            strings(&["web", "javascript"])
+        );
+    }
+
+    #[test]
+    fn merges_descendants() {
+        let operation = merge(&["js"], "javascript");
+        assert_eq!(
+            operation.apply(&strings(&["js", "js/react", "jsx"])),
+            strings(&["jsx", "javascript", "javascript/react"])
+        );
+    }
+
+    #[test]
+    fn merges_overlapping_sources_from_the_longest() {
+        let operation = merge(&["js", "js/react"], "web");
+        assert_eq!(
+            operation.apply(&strings(&["js/react/hooks", "js/vue"])),
+            strings(&["web/hooks", "web/vue"])
+        );
+    }
+
+    #[test]
+    fn splits_into_itself_and_others() {
+        let operation = TagOperation::Split {
+            tag: "web".to_owned(),
+            into: strings(&["web", "frontend"]),
+        };
+        let tags = operation.apply(&strings(&["web", "web/css"]));
+        assert_eq!(
+            tags,
+            strings(&["web", "frontend", "web/css", "frontend/css"])
+        );
+
+        let bookmark = Bookmark::sample("https://example.com", "Example", &["web", "web/css"]);
+        let edit = TagEdit::new(&bookmark, &tags).unwrap();
+        assert_eq!(edit.added, strings(&["frontend", "frontend/css"]));
+        assert!(edit.removed.is_empty());
+    }
+
+    #[test]
+    fn removes_descendants() {
+        let operation = TagOperation::Remove {
+            tags: strings(&["todo"]),
+        };
+        assert_eq!(
+            operation.apply(&strings(&["todo", "todo/later", "todos"])),
+            strings(&["todos"])
+        );
+    }
+
+    #[test]
+    fn adds_missing_tags_only() {
+        let operation = TagOperation::Add {
+            tags: strings(&["rust", "async"]),
+        };
+        let bookmark = Bookmark::sample("https://example.com", "Example", &["rust"]);
+        let edit = TagEdit::new(&bookmark, &operation.apply(&bookmark.tags.0)).unwrap();
+        assert_eq!(edit.added, strings(&["async"]));
+        assert!(TagEdit::new(&bookmark, &strings(&["rust"])).is_none());
+    }
+}
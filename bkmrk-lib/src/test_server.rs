@@ -0,0 +1,125 @@
+//! A minimal HTTP server for tests, answering on a local port.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// A canned response.
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u32,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Time to wait before answering
+    pub delay: Duration,
+}
+
+impl Response {
+    pub fn status(status: u32) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn ok(content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            body: body.into(),
+            ..Self::status(200)
+        }
+        .header("Content-Type", content_type)
+    }
+
+    pub fn redirect(status: u32, location: &str) -> Self {
+        Self::status(status).header("Location", location)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+type Handler = dyn Fn(&str, &str) -> Response + Send + Sync;
+
+pub struct TestServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestServer {
+    /// Serves the responses `handler` returns for the method and path of each
+    /// request. The server runs until the test process exits.
+    pub fn start(handler: impl Fn(&str, &str) -> Response + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let log = Arc::clone(&requests);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let handler = Arc::clone(&handler);
+                let log = Arc::clone(&log);
+                thread::spawn(move || serve(stream, handler.as_ref(), &log));
+            }
+        });
+
+        Self { addr, requests }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// Requests received so far, like `GET /page`.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn serve(stream: TcpStream, handler: &Handler, log: &Mutex<Vec<String>>) {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // Skip the headers, requests in tests have no body
+    let mut line = String::new();
+    while reader.read_line(&mut line).is_ok_and(|read| read > 2) {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    log.lock().unwrap().push(format!("{} {}", method, path));
+
+    let response = handler(method, path);
+    thread::sleep(response.delay);
+    let mut out = format!(
+        "HTTP/1.1 {} Test\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    )
+    .into_bytes();
+    for (name, value) in &response.headers {
+        out.extend(format!("{}: {}\r\n", name, value).into_bytes());
+    }
+    out.extend(b"\r\n");
+    if method != "HEAD" {
+        out.extend(&response.body);
+    }
+    (&stream).write_all(&out).ok();
+}
@@ -24,6 +24,24 @@ pub fn get_db_path() -> Result<PathBuf> {
     Ok(base_dir.join("data.db"))
 }
 
+/// Directory holding the saved copies of a bookmark.
+pub fn get_archive_dir(bookmark_id: &str) -> Result<PathBuf> {
+    let base_dir = get_base_dir()?;
+    Ok(base_dir.join("archive").join(bookmark_id))
+}
+
+/// Directory holding downloaded favicons and preview images.
+pub fn get_cache_dir() -> Result<PathBuf> {
+    let base_dir = get_base_dir()?;
+    Ok(base_dir.join("cache"))
+}
+
+/// File holding the rules of [`crate::autotag::AutoTagger`].
+pub fn get_auto_tag_rules_path() -> Result<PathBuf> {
+    let base_dir = get_base_dir()?;
+    Ok(base_dir.join("autotag.toml"))
+}
+
 pub fn read_file(file_path: &Path) -> Result<String> {
     let mut file = File::open(file_path)
         .wrap_err_with(|| format!("ERROR: Couldn't open file {}", file_path.display()))?;
@@ -2,6 +2,7 @@ pub mod files;
 
 use chrono::{DateTime, NaiveDateTime, Utc};
 use regex::Regex;
+use url::Url;
 
 pub fn squeeze_whitespaces(text: &str) -> String {
     let ws = Regex::new(r"[\s\n]+").unwrap();
@@ -9,8 +10,34 @@ pub fn squeeze_whitespaces(text: &str) -> String {
     new_str.into_owned()
 }
 
+/// Escapes the characters that are significant in HTML text and attribute values.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Reverses [`escape_html`], along with the other entities browsers commonly
+/// emit in bookmark files.
+pub fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
 pub fn get_date_string(ts: i64) -> String {
     let naive = NaiveDateTime::from_timestamp(ts, 0);
     let datetime: DateTime<Utc> = DateTime::from_utc(naive, Utc);
     datetime.format("%Y-%m-%d %H:%M:%S").to_string()
 }
+
+pub fn get_host(link: &str) -> Option<String> {
+    let url = Url::parse(link).ok()?;
+    url.host_str().map(|host| host.to_lowercase())
+}
@@ -0,0 +1,48 @@
+-- The tables of a Firefox places.sqlite that the importer reads
+CREATE TABLE moz_places (
+    id INTEGER PRIMARY KEY,
+    url LONGVARCHAR,
+    title LONGVARCHAR,
+    description TEXT
+);
+CREATE TABLE moz_bookmarks (
+    id INTEGER PRIMARY KEY,
+    type INTEGER,
+    fk INTEGER DEFAULT NULL,
+    parent INTEGER,
+    position INTEGER,
+    title LONGVARCHAR,
+    dateAdded INTEGER,
+    lastModified INTEGER,
+    guid TEXT
+);
+CREATE TABLE moz_keywords (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    keyword TEXT UNIQUE,
+    place_id INTEGER
+);
+
+INSERT INTO moz_places VALUES (1, 'https://tokio.rs/', 'Tokio - An asynchronous Rust runtime', 'Build reliable network applications');
+INSERT INTO moz_places VALUES (2, 'https://doc.rust-lang.org/book/', 'The Rust Programming Language', NULL);
+INSERT INTO moz_places VALUES (3, 'place:sort=8&maxResults=10', 'Most Visited', NULL);
+
+-- Folders
+INSERT INTO moz_bookmarks VALUES (1, 2, NULL, 0, 0, '', 1609459200000000, 1609459200000000, 'root________');
+INSERT INTO moz_bookmarks VALUES (2, 2, NULL, 1, 0, 'menu', 1609459200000000, 1609459200000000, 'menu________');
+INSERT INTO moz_bookmarks VALUES (3, 2, NULL, 1, 1, 'toolbar', 1609459200000000, 1609459200000000, 'toolbar_____');
+INSERT INTO moz_bookmarks VALUES (4, 2, NULL, 1, 2, 'tags', 1609459200000000, 1609459200000000, 'tags________');
+INSERT INTO moz_bookmarks VALUES (5, 2, NULL, 1, 3, 'unfiled', 1609459200000000, 1609459200000000, 'unfiled_____');
+INSERT INTO moz_bookmarks VALUES (6, 2, NULL, 3, 0, 'Dev', 1609459200000000, 1609459200000000, 'dev_folder__');
+
+-- Bookmarks
+INSERT INTO moz_bookmarks VALUES (7, 1, 1, 6, 0, 'Tokio', 1609459200000000, 1609545600000000, 'tokio_______');
+INSERT INTO moz_bookmarks VALUES (8, 1, 2, 2, 0, '', 1612137600000000, 0, 'rust_book___');
+INSERT INTO moz_bookmarks VALUES (9, 1, 3, 5, 0, 'Most Visited', 1609459200000000, 1609459200000000, 'most_visited');
+
+-- Tags, as folders under the tags root holding an entry for each tagged place
+INSERT INTO moz_bookmarks VALUES (10, 2, NULL, 4, 0, 'rust', 1609459200000000, 1609459200000000, 'tag_rust____');
+INSERT INTO moz_bookmarks VALUES (11, 1, 1, 10, 0, NULL, 1609459200000000, 1609459200000000, 'tag_entry_1_');
+INSERT INTO moz_bookmarks VALUES (12, 2, NULL, 4, 1, 'async', 1609459200000000, 1609459200000000, 'tag_async___');
+INSERT INTO moz_bookmarks VALUES (13, 1, 1, 12, 0, NULL, 1609459200000000, 1609459200000000, 'tag_entry_2_');
+
+INSERT INTO moz_keywords (keyword, place_id) VALUES ('tk', 1);
@@ -1,6 +1,9 @@
 #![deny(missing_docs)]
 
-use clap::{ArgEnum, Parser, Subcommand};
+use std::time::Duration;
+
+use bkmrk_lib::refresh::RefreshOptions;
+use clap::{ArgEnum, Args, Parser, Subcommand};
 
 #[derive(Debug, Parser)]
 #[clap(name = "bkmrk")]
@@ -18,6 +21,156 @@ pub struct App {
 pub enum OutputType {
     Table,
     FormatString,
+    Json,
+    #[clap(name = "jsonl")]
+    JsonLines,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
+pub enum OnConflict {
+    Skip,
+    Overwrite,
+    MergeTags,
+    KeepNewer,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
+pub enum SortBy {
+    Added,
+    AddedDesc,
+    Modified,
+    ModifiedDesc,
+    Title,
+    TitleDesc,
+}
+
+#[derive(Debug, Args)]
+#[clap(allow_hyphen_values = true)]
+pub struct FilterArgs {
+    /// Filter expression, e.g. tag:rust -tag:old site:github.com added:>2024-01-01 type:article lang:en "async runtime".
+    /// Put it after -- if a term looks like one of the options, e.g. -x. An argument with spaces that isn't a valid query
+    /// on its own is matched as a phrase.
+    pub query: Vec<String>,
+
+    #[clap(short, long)]
+    /// Show bookmarks with any of these tags
+    pub tags: Vec<String>,
+
+    #[clap(long)]
+    /// Show bookmarks that have all of these tags
+    pub all_tags: Vec<String>,
+
+    #[clap(short = 'x', long)]
+    /// Hide bookmarks with any of these tags
+    pub exclude_tags: Vec<String>,
+
+    #[clap(short, long)]
+    /// Show bookmarks from these sites and their subdomains only
+    pub domains: Vec<String>,
+
+    #[clap(long)]
+    /// Show bookmarks from exactly these hosts only
+    pub exact_domains: Vec<String>,
+
+    #[clap(long)]
+    /// Show bookmarks added on or after this date (YYYY-MM-DD)
+    pub added_after: Option<String>,
+
+    #[clap(long)]
+    /// Show bookmarks added before this date (YYYY-MM-DD)
+    pub added_before: Option<String>,
+
+    #[clap(long)]
+    /// Show bookmarks modified on or after this date (YYYY-MM-DD)
+    pub modified_after: Option<String>,
+
+    #[clap(long)]
+    /// Show bookmarks modified before this date (YYYY-MM-DD)
+    pub modified_before: Option<String>,
+
+    #[clap(long = "type")]
+    /// Show bookmarks of these site types only (e.g. article, video.movie)
+    pub site_types: Vec<String>,
+
+    #[clap(long)]
+    /// Show bookmarks whose title contains this text
+    pub title: Option<String>,
+
+    #[clap(long)]
+    /// Show bookmarks of pages in these languages (e.g. en, pt-BR)
+    pub lang: Vec<String>,
+
+    #[clap(long)]
+    /// Show bookmarks whose author contains this text
+    pub author: Option<String>,
+
+    #[clap(short, long, arg_enum, default_value_t = SortBy::Added)]
+    /// Sort order
+    pub sort: SortBy,
+
+    #[clap(short = 'n', long)]
+    /// Show at most this many bookmarks
+    pub limit: Option<usize>,
+
+    #[clap(long)]
+    /// Skip this many bookmarks
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+pub struct ProbeArgs {
+    #[clap(short, long, default_value_t = 8)]
+    /// Number of links to check at the same time
+    pub jobs: usize,
+
+    #[clap(long, default_value_t = 15)]
+    /// Seconds to wait for each link
+    pub timeout: u64,
+
+    #[clap(long, default_value_t = 1)]
+    /// Times to retry links that fail with a network error or a 429 / 5xx status
+    pub retries: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct FetchArgs {
+    #[clap(short, long, default_value_t = 8)]
+    /// Number of pages to fetch at the same time
+    pub jobs: usize,
+
+    #[clap(long, default_value_t = 10)]
+    /// Seconds to wait for each page
+    pub timeout: u64,
+
+    #[clap(long, default_value_t = 1000)]
+    /// Milliseconds to wait between requests to the same site
+    pub host_delay: u64,
+}
+
+impl FetchArgs {
+    /// The options as understood by the library
+    pub fn options(&self) -> RefreshOptions {
+        RefreshOptions {
+            parallelism: self.jobs,
+            timeout: Duration::from_secs(self.timeout),
+            host_delay: Duration::from_millis(self.host_delay),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct LinkStatusArgs {
+    #[clap(long)]
+    /// Only show links that returned these HTTP status codes
+    pub status: Vec<u32>,
+
+    #[clap(short, long)]
+    /// Only show broken links
+    pub broken: bool,
+
+    #[clap(short, long)]
+    /// Only show links that redirect elsewhere
+    pub redirected: bool,
 }
 
 #[derive(Debug, Subcommand)]
@@ -26,8 +179,8 @@ pub enum Commands {
     /// Add a new bookmark
     Add {
         #[clap(short, long)]
-        /// Bookmark Name
-        name: String,
+        /// Bookmark Name. Fetched from the page if not given
+        name: Option<String>,
 
         #[clap(short, long)]
         /// Bookmark Link
@@ -40,6 +193,18 @@ pub enum Commands {
         #[clap(short, long)]
         /// Bookmark Description
         description: Option<String>,
+
+        #[clap(short, long, conflicts_with = "no-fetch")]
+        /// Fetch the page's title, description, image and type even if a name is given
+        fetch: bool,
+
+        #[clap(long)]
+        /// Don't fetch anything from the page. The link is used as name if none is given
+        no_fetch: bool,
+
+        #[clap(short, long)]
+        /// Choose tags from suggestions based on similar saved bookmarks
+        interactive: bool,
     },
 
     #[clap(visible_alias = "ls")]
@@ -57,43 +222,56 @@ pub enum Commands {
         /// %a for add date,
         /// %m for modified date,
         /// %t for tags,
-        /// %d for description.
+        /// %d for description,
+        /// %i for image URL,
+        /// %y for site type,
+        /// %f for favicon URL,
+        /// %c for canonical URL,
+        /// %s for site name,
+        /// %w for author,
+        /// %g for language,
+        /// %p for page publish date,
+        /// %u for page update date,
+        /// %k for keywords,
+        /// %r for feed URLs,
+        /// %F for cached favicon path,
+        /// %I for cached image path.
         format_string: String,
 
-        #[clap(short, long)]
-        /// Show bookmarks from these tags only
-        tags: Vec<String>,
+        #[clap(flatten)]
+        filter: FilterArgs,
+    },
 
-        #[clap(short, long)]
-        /// Show bookmarks from these sites only
-        domains: Vec<String>,
+    #[clap(visible_alias = "s")]
+    /// Search bookmark titles, descriptions, links, tags and page text
+    Search {
+        /// Search query. Supports "phrases", prefix* matches, AND / OR / NOT
+        /// and column filters like title:rust
+        query: String,
+
+        #[clap(short = 'n', long)]
+        /// Maximum number of results to show
+        limit: Option<usize>,
     },
 
     #[clap(visible_alias = "e")]
     /// Edit a bookmark
     Edit {
-        #[clap(short, long)]
-        /// Show bookmarks from these tags only
-        tags: Vec<String>,
-
-        #[clap(short, long)]
-        /// Show bookmarks from these sites only
-        domains: Vec<String>,
+        #[clap(flatten)]
+        filter: FilterArgs,
     },
 
     #[clap(visible_alias = "u")]
     /// Update bookmark details
     Update {
-        #[clap(short, long)]
-        /// Show bookmarks from these tags only
-        tags: Vec<String>,
+        #[clap(flatten)]
+        filter: FilterArgs,
 
-        #[clap(short, long)]
-        /// Show bookmarks from these sites only
-        domains: Vec<String>,
+        #[clap(flatten)]
+        fetch: FetchArgs,
 
         #[clap(short, long)]
-        /// Confirm without prompts
+        /// Update all matching bookmarks without prompts
         yes: bool,
     },
 
@@ -102,7 +280,8 @@ pub enum Commands {
     Import {
         input_file: String,
         #[clap(short, long, default_value_t = String::from("netscape"))]
-        /// Input file format
+        /// Input file format: netscape, firefox (places.sqlite), chromium (Bookmarks), pinboard,
+        /// pocket, raindrop, linkding, json or jsonl
         file_format: String,
 
         #[clap(short, long)]
@@ -110,38 +289,249 @@ pub enum Commands {
         dry_run: bool,
 
         #[clap(short = 'l', long)]
-        /// Append bookmark folders as tags
+        /// Tag bookmarks with their folder path, e.g. dev/rust
         append_folder_tags: bool,
+
+        #[clap(short = 'c', long, arg_enum, default_value_t = OnConflict::Skip)]
+        /// What to do with bookmarks whose link is already saved
+        on_conflict: OnConflict,
+    },
+
+    #[clap(visible_alias = "x")]
+    /// Export bookmarks to a file
+    Export {
+        #[clap(short, long, default_value_t = String::from("netscape"))]
+        /// Output file format: netscape, json or jsonl
+        file_format: String,
+
+        #[clap(short, long)]
+        /// File to write to. Prints to stdout if not given
+        output_file: Option<String>,
+
+        #[clap(short = 'l', long)]
+        /// Group bookmarks into folders by tag, nesting hierarchical tags
+        tag_folders: bool,
+
+        #[clap(flatten)]
+        filter: FilterArgs,
     },
 
     #[clap(visible_alias = "d")]
     /// Delete Bookmarks
     Delete {
+        #[clap(flatten)]
+        filter: FilterArgs,
+    },
+
+    /// Download favicons and preview images for offline use
+    Cache {
+        #[clap(flatten)]
+        filter: FilterArgs,
+
+        #[clap(flatten)]
+        fetch: FetchArgs,
+
+        #[clap(long, default_value_t = 200)]
+        /// Megabytes the cache may take. The least recently used files are deleted beyond that
+        max_size: u64,
+
+        #[clap(long, conflicts_with = "max-size")]
+        /// Delete all cached files instead of downloading
+        clear: bool,
+    },
+
+    /// Save copies of bookmarked pages for offline reading
+    Archive {
+        #[clap(flatten)]
+        filter: FilterArgs,
+
         #[clap(short, long)]
-        /// Show bookmarks from these tags only
-        tags: Vec<String>,
+        /// Archive all matching bookmarks without prompts
+        yes: bool,
+    },
+
+    #[clap(visible_alias = "o")]
+    /// Open a bookmark in the browser
+    Open {
+        #[clap(flatten)]
+        filter: FilterArgs,
+
+        #[clap(short, long)]
+        /// Open the latest archived copy instead of the live page
+        archived: bool,
+    },
+
+    /// Find bookmarks that point to the same page and merge them
+    Dedupe {
+        #[clap(short, long)]
+        /// Keep the oldest bookmark of each group without prompts
+        yes: bool,
+    },
+
+    /// Check bookmarks for dead links
+    Check {
+        #[clap(flatten)]
+        filter: FilterArgs,
+
+        #[clap(flatten)]
+        probe: ProbeArgs,
+
+        #[clap(flatten)]
+        link_status: LinkStatusArgs,
+
+        #[clap(long)]
+        /// Show the results of the last check instead of checking again
+        cached: bool,
 
         #[clap(short, long)]
-        /// Show bookmarks from these sites only
-        domains: Vec<String>,
+        /// Offer to update redirected links to where they lead
+        update_redirects: bool,
+
+        #[clap(short, long, requires = "update-redirects")]
+        /// Update redirected links without prompts
+        yes: bool,
     },
 
-    #[clap(visible_alias = "t")]
+    #[clap(visible_alias = "t", args_conflicts_with_subcommands = true)]
     /// Manage tags
     Tag {
+        #[clap(subcommand)]
+        command: Option<TagCommands>,
+
         #[clap(short, long)]
-        /// List all tags
+        /// List all tags as a tree, with counts including child tags
         list: bool,
 
-        /// Name of tag to manage
+        /// Name of tag to manage. Tags like dev/rust are children of dev
         name: Option<String>,
 
         #[clap(short, long, requires = "name")]
-        /// New tag name to rename to
+        /// New tag name to rename to, moving child tags along
         rename: Option<String>,
 
         #[clap(short, long, requires = "name")]
-        /// Delete tag
+        /// Delete tag and its child tags
+        delete: bool,
+    },
+}
+
+/// Tag subcommands other than listing, renaming and deleting
+#[derive(Debug, Subcommand)]
+pub enum TagCommands {
+    /// List, add or remove tag aliases. Tags are saved and searched as the tag
+    /// their alias points to
+    Alias {
+        /// Alias to add or remove. Lists all aliases if not given
+        alias: Option<String>,
+
+        /// Tag the alias stands for
+        tag: Option<String>,
+
+        #[clap(short, long, requires = "alias", conflicts_with = "tag")]
+        /// Remove the alias
         delete: bool,
     },
+
+    /// Replace tags with another tag, e.g. merge js and ecmascript into javascript
+    Merge {
+        #[clap(required = true)]
+        /// Tags to merge
+        tags: Vec<String>,
+
+        #[clap(long)]
+        /// Tag to merge them into
+        into: String,
+
+        #[clap(long)]
+        /// Only show the bookmarks that would change
+        dry_run: bool,
+    },
+
+    /// Replace a tag with several tags, e.g. split web-dev into web and dev
+    Split {
+        /// Tag to split
+        tag: String,
+
+        #[clap(long, required = true, multiple_values = true)]
+        /// Tags to replace it with
+        into: Vec<String>,
+
+        #[clap(long)]
+        /// Only show the bookmarks that would change
+        dry_run: bool,
+    },
+
+    /// Add tags to all matching bookmarks
+    Add {
+        #[clap(value_name = "TAGS")]
+        /// Tags to add, separated by commas
+        tag: String,
+
+        #[clap(long)]
+        /// Only show the bookmarks that would change
+        dry_run: bool,
+
+        #[clap(flatten)]
+        filter: FilterArgs,
+    },
+
+    /// Remove tags, along with their child tags, from all matching bookmarks
+    Remove {
+        #[clap(value_name = "TAGS")]
+        /// Tags to remove, separated by commas
+        tag: String,
+
+        #[clap(long)]
+        /// Only show the bookmarks that would change
+        dry_run: bool,
+
+        #[clap(flatten)]
+        filter: FilterArgs,
+    },
+
+    /// Show or change the description, color, icon and pin of a tag
+    Info {
+        /// Tag to show or change
+        tag: String,
+
+        #[clap(short, long)]
+        /// What the tag is for. An empty description removes it
+        description: Option<String>,
+
+        #[clap(short, long)]
+        /// Color used when listing and exporting the tag: a name like red or
+        /// blue, or #rrggbb. An empty color removes it
+        color: Option<String>,
+
+        #[clap(short, long)]
+        /// Emoji or short text shown before the tag. An empty icon removes it
+        icon: Option<String>,
+
+        #[clap(short, long, conflicts_with = "unpin")]
+        /// List the tag before others
+        pin: bool,
+
+        #[clap(short, long)]
+        /// Stop listing the tag before others
+        unpin: bool,
+    },
+
+    /// Add tags to matching bookmarks by the rules in ~/.bkmrk/autotag.toml.
+    /// Only shows the tags that would be added unless --apply is given
+    Auto {
+        #[clap(long)]
+        /// Add the tags
+        apply: bool,
+
+        #[clap(flatten)]
+        filter: FilterArgs,
+    },
+
+    /// Rewrite saved tags that differ from their normalized form: lowercase,
+    /// words joined by hyphens and aliases replaced
+    Normalize {
+        #[clap(short, long)]
+        /// Rewrite tags without asking
+        yes: bool,
+    },
 }
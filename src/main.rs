@@ -8,8 +8,9 @@ use app::{App, Commands};
 
 use subcommands::*;
 use subcommands::{
-    add::AddArgs, delete::DeleteArgs, edit::EditArgs, import::ImportArgs, ls::ListArgs,
-    tag::TagArgs, update::UpdateArgs,
+    add::AddArgs, archive::ArchiveArgs, cache::CacheArgs, check::CheckArgs, dedupe::DedupeArgs,
+    delete::DeleteArgs, edit::EditArgs, export::ExportArgs, import::ImportArgs, ls::ListArgs,
+    open::OpenArgs, search::SearchArgs, tag::TagArgs, update::UpdateArgs,
 };
 
 fn main() -> Result<()> {
@@ -39,35 +40,84 @@ pub fn run_app(command: Commands) -> Result<()> {
             link,
             name,
             tags,
-        } => add::run(AddArgs::new(name, link, tags, description))?,
+            fetch,
+            no_fetch,
+            interactive,
+        } => add::run(AddArgs::new(
+            name,
+            link,
+            tags,
+            description,
+            fetch,
+            no_fetch,
+            interactive,
+        ))?,
         Commands::List {
-            domains,
+            filter,
             format_string,
             output_type,
-            tags,
-        } => ls::run(ListArgs::new(output_type, format_string, tags, domains))?,
-        Commands::Edit { domains, tags } => edit::run(EditArgs::new(tags, domains))?,
-        Commands::Update { domains, tags, yes } => {
-            update::run(UpdateArgs::new(tags, domains, yes))?
+        } => ls::run(ListArgs::new(output_type, format_string, filter))?,
+        Commands::Search { query, limit } => search::run(SearchArgs::new(query, limit))?,
+        Commands::Edit { filter } => edit::run(EditArgs::new(filter))?,
+        Commands::Update { filter, fetch, yes } => {
+            update::run(UpdateArgs::new(filter, fetch, yes))?
         }
         Commands::Import {
             input_file,
             append_folder_tags,
             dry_run,
             file_format,
+            on_conflict,
         } => import::run(ImportArgs::new(
             input_file,
             file_format,
             dry_run,
             append_folder_tags,
+            on_conflict,
+        ))?,
+        Commands::Export {
+            file_format,
+            output_file,
+            tag_folders,
+            filter,
+        } => export::run(ExportArgs::new(
+            file_format,
+            output_file,
+            tag_folders,
+            filter,
+        ))?,
+        Commands::Delete { filter } => delete::run(DeleteArgs::new(filter))?,
+        Commands::Check {
+            filter,
+            probe,
+            link_status,
+            cached,
+            update_redirects,
+            yes,
+        } => check::run(CheckArgs::new(
+            filter,
+            probe,
+            link_status,
+            cached,
+            update_redirects,
+            yes,
         ))?,
-        Commands::Delete { tags, domains } => delete::run(DeleteArgs::new(tags, domains))?,
+        Commands::Cache {
+            filter,
+            fetch,
+            max_size,
+            clear,
+        } => cache::run(CacheArgs::new(filter, fetch, max_size, clear))?,
+        Commands::Archive { filter, yes } => archive::run(ArchiveArgs::new(filter, yes))?,
+        Commands::Open { filter, archived } => open::run(OpenArgs::new(filter, archived))?,
+        Commands::Dedupe { yes } => dedupe::run(DedupeArgs::new(yes))?,
         Commands::Tag {
+            command,
             name,
             list,
             rename,
             delete,
-        } => tag::run(TagArgs::new(name, list, rename, delete))?,
+        } => tag::run(TagArgs::new(command, name, list, rename, delete))?,
     }
 
     Ok(())
@@ -4,44 +4,120 @@ use bkmrk_lib::{
     BkmrkMan,
 };
 use color_eyre::Result;
+use dialoguer::{theme::ColorfulTheme, Input, MultiSelect};
+use owo_colors::OwoColorize;
 
 pub struct AddArgs {
-    name: String,
+    name: Option<String>,
     link: String,
     tags: Vec<String>,
     description: Option<String>,
+    fetch: bool,
+    no_fetch: bool,
+    interactive: bool,
 }
 
 impl AddArgs {
-    pub fn new(name: String, link: String, tags: Vec<String>, description: Option<String>) -> Self {
+    pub fn new(
+        name: Option<String>,
+        link: String,
+        tags: Vec<String>,
+        description: Option<String>,
+        fetch: bool,
+        no_fetch: bool,
+        interactive: bool,
+    ) -> Self {
         Self {
             name,
             link,
             tags,
             description,
+            fetch,
+            no_fetch,
+            interactive,
         }
     }
 }
 
 pub fn run(args: AddArgs) -> Result<()> {
-    let name: String = args.name;
     let link: String = args.link;
     let tags: TagList = args.tags.into();
-    let description: String = args.description.unwrap_or_else(|| "".into());
 
-    let new_bookmark = Bookmark {
+    let man = BkmrkMan::new()?;
+    let should_fetch = !args.no_fetch && (args.fetch || args.name.is_none());
+    let (mut metadata, text) = if should_fetch {
+        match man.fetch_page(&link) {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                println!(
+                    "{} {:#}",
+                    format!("Couldn't fetch details of {}:", link).yellow(),
+                    e
+                );
+                (SiteMetadata::default(), None)
+            }
+        }
+    } else {
+        (SiteMetadata::default(), None)
+    };
+
+    if let Some(name) = args.name {
+        metadata.title = name;
+    }
+    if metadata.title.trim().is_empty() {
+        metadata.title = link.to_owned();
+    }
+    if let Some(description) = args.description {
+        metadata.description = Some(description);
+    }
+
+    let mut new_bookmark = Bookmark {
         link,
         tags,
-        metadata: SiteMetadata {
-            title: name,
-            description: Some(description),
-            ..Default::default()
-        },
+        metadata,
         ..Default::default()
     };
 
-    let man = BkmrkMan::new();
-    man.add_bookmark(&new_bookmark)?;
+    if args.interactive {
+        choose_tags(&man, &mut new_bookmark)?;
+    }
+    man.add_bookmark_with_text(&new_bookmark, text.as_deref())?;
+
+    Ok(())
+}
+
+/// Lets the user pick tags for `bookmark` from suggestions, all selected at
+/// first, and type in others.
+fn choose_tags(man: &BkmrkMan, bookmark: &mut Bookmark) -> Result<()> {
+    let suggestions = man.suggest_tags(bookmark)?;
+    if suggestions.is_empty() {
+        println!("No tag suggestions for {}.", bookmark.link);
+    } else {
+        let options: Vec<_> = suggestions
+            .iter()
+            .map(|it| format!("{} ({:.0}%)", it.tag, it.score * 100.0))
+            .collect();
+        let indices = MultiSelect::with_theme(&ColorfulTheme::default())
+            .with_prompt("Suggested tags (space to toggle)")
+            .items(&options)
+            .defaults(&vec![true; options.len()])
+            .interact()?;
+        bookmark.tags.0.extend(
+            indices
+                .into_iter()
+                .map(|idx| suggestions[idx].tag.to_owned()),
+        );
+    }
 
+    let other: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Other tags, separated by commas")
+        .allow_empty(true)
+        .interact_text()?;
+    bookmark.tags.0.extend(
+        other
+            .split(',')
+            .map(|tag| tag.trim().to_owned())
+            .filter(|tag| !tag.is_empty()),
+    );
     Ok(())
 }
@@ -0,0 +1,54 @@
+use bkmrk_lib::BkmrkMan;
+use color_eyre::Result;
+use dialoguer::MultiSelect;
+use owo_colors::OwoColorize;
+
+use crate::{app::FilterArgs, utils};
+
+pub struct ArchiveArgs {
+    filter: FilterArgs,
+    yes: bool,
+}
+
+impl ArchiveArgs {
+    pub fn new(filter: FilterArgs, yes: bool) -> Self {
+        Self { filter, yes }
+    }
+}
+
+pub fn run(args: ArchiveArgs) -> Result<()> {
+    let query = utils::get_query(&args.filter)?;
+
+    let man = BkmrkMan::new()?;
+
+    let items = man.get_bookmarks(&query)?;
+    let indices: Vec<_> = if args.yes {
+        (0..items.len()).collect()
+    } else {
+        let options: Vec<_> = items
+            .iter()
+            .map(|it| format!("● {} - ({})", it.metadata.title, it.link))
+            .collect();
+
+        println!("Select bookmark(s) to archive (q to cancel):");
+        MultiSelect::new().items(&options).interact()?
+    };
+
+    let (mut succeeded, mut failed) = (0, 0);
+    for idx in indices {
+        let bm = &items[idx];
+        match man.archive_bookmark(bm) {
+            Ok(archive) => {
+                println!("Archived {} to {}", bm.link.green(), archive.path.display());
+                succeeded += 1;
+            }
+            Err(e) => {
+                println!("Couldn't archive {}: {:#}", bm.link.red(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!("{succeeded} Archived. {failed} Failed.");
+    Ok(())
+}
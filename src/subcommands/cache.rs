@@ -0,0 +1,93 @@
+use bkmrk_lib::BkmrkMan;
+use color_eyre::Result;
+use indicatif::{ProgressBar, ProgressStyle};
+use owo_colors::OwoColorize;
+
+use crate::{
+    app::{FetchArgs, FilterArgs},
+    utils,
+};
+
+pub struct CacheArgs {
+    filter: FilterArgs,
+    fetch: FetchArgs,
+    max_size: u64,
+    clear: bool,
+}
+
+impl CacheArgs {
+    pub fn new(filter: FilterArgs, fetch: FetchArgs, max_size: u64, clear: bool) -> Self {
+        Self {
+            filter,
+            fetch,
+            max_size,
+            clear,
+        }
+    }
+}
+
+pub fn run(args: CacheArgs) -> Result<()> {
+    let man = BkmrkMan::new()?;
+
+    if args.clear {
+        let eviction = man.evict_assets(0)?;
+        println!(
+            "Deleted {} cached files ({}).",
+            eviction.removed,
+            format_size(eviction.freed)
+        );
+        return Ok(());
+    }
+
+    let query = utils::get_query(&args.filter)?;
+    let bookmarks = man.get_bookmarks(&query)?;
+
+    let progress = ProgressBar::new_spinner();
+    progress.set_style(
+        ProgressStyle::default_spinner().template("{spinner} Downloading {pos} {wide_msg}"),
+    );
+    let report = man.cache_assets(&bookmarks, &args.fetch.options(), |url, _| {
+        progress.set_message(url.to_owned());
+        progress.inc(1);
+    })?;
+    progress.finish_and_clear();
+
+    if !report.failed.is_empty() {
+        println!("Couldn't download:");
+        for (url, error) in &report.failed {
+            println!("  {} ({})", url.red(), error.dimmed());
+        }
+    }
+    println!(
+        "{} Downloaded. {} Reused. {} Failed.",
+        report.downloaded,
+        report.reused,
+        report.failed.len()
+    );
+
+    let eviction = man.evict_assets(args.max_size * 1024 * 1024)?;
+    if eviction.removed > 0 {
+        println!(
+            "Deleted {} least recently used files ({}) to stay under {} MB.",
+            eviction.removed,
+            format_size(eviction.freed),
+            args.max_size
+        );
+    }
+    let stats = man.cache_stats()?;
+    println!(
+        "The cache holds {} files ({}).",
+        stats.assets,
+        format_size(stats.size)
+    );
+
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1048575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1048576.0),
+    }
+}
@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use bkmrk_lib::{
+    bookmark::Bookmark,
+    link_check::{CheckOptions, LinkStatus},
+    BkmrkMan,
+};
+use color_eyre::Result;
+use dialoguer::MultiSelect;
+use owo_colors::OwoColorize;
+
+use crate::{
+    app::{FilterArgs, LinkStatusArgs, ProbeArgs},
+    utils,
+};
+
+pub struct CheckArgs {
+    filter: FilterArgs,
+    probe: ProbeArgs,
+    link_status: LinkStatusArgs,
+    cached: bool,
+    update_redirects: bool,
+    yes: bool,
+}
+
+impl CheckArgs {
+    pub fn new(
+        filter: FilterArgs,
+        probe: ProbeArgs,
+        link_status: LinkStatusArgs,
+        cached: bool,
+        update_redirects: bool,
+        yes: bool,
+    ) -> Self {
+        Self {
+            filter,
+            probe,
+            link_status,
+            cached,
+            update_redirects,
+            yes,
+        }
+    }
+}
+
+pub fn run(args: CheckArgs) -> Result<()> {
+    let query = utils::get_query(&args.filter)?;
+
+    let man = BkmrkMan::new()?;
+
+    let results = if args.cached {
+        let results = man.link_checks(&query)?;
+        for (bookmark, status) in &results {
+            if is_shown(status, &args.link_status) {
+                print_result(bookmark, status);
+            }
+        }
+        results
+    } else {
+        let bookmarks = man.get_bookmarks(&query)?;
+        let options = CheckOptions {
+            parallelism: args.probe.jobs,
+            timeout: Duration::from_secs(args.probe.timeout),
+            retries: args.probe.retries,
+            ..Default::default()
+        };
+        println!("Checking {} links...", bookmarks.len());
+        man.check_links(&bookmarks, &options, |bookmark, status| {
+            if is_shown(status, &args.link_status) {
+                print_result(bookmark, status);
+            }
+        })?
+    };
+
+    let redirected = results.iter().filter(|(_, s)| s.is_redirected()).count();
+    let broken = results.iter().filter(|(_, s)| s.is_broken()).count();
+    println!(
+        "{} links: {} ok, {} redirected, {} broken.",
+        results.len(),
+        results.len() - broken,
+        redirected,
+        broken
+    );
+
+    if args.update_redirects {
+        update_redirects(&man, &results, &args)?;
+    }
+
+    Ok(())
+}
+
+fn is_shown(status: &LinkStatus, filter: &LinkStatusArgs) -> bool {
+    (filter.status.is_empty() || status.status.is_some_and(|s| filter.status.contains(&s)))
+        && (!filter.broken || status.is_broken())
+        && (!filter.redirected || status.is_redirected())
+}
+
+fn print_result(bookmark: &Bookmark, status: &LinkStatus) {
+    let code = status
+        .status
+        .map_or_else(|| "ERR".to_owned(), |s| s.to_string());
+    let code = format!("{:>3}", code);
+    if status.is_broken() {
+        print!("{} {}", code.red(), bookmark.link);
+    } else if status.is_redirected() {
+        print!("{} {}", code.yellow(), bookmark.link);
+    } else {
+        print!("{} {}", code.green(), bookmark.link);
+    }
+
+    if let Some(error) = &status.error {
+        print!(" ({})", error.dimmed());
+    }
+    if let Some(final_url) = &status.final_url {
+        print!(" -> {}", final_url.cyan());
+    }
+    println!();
+}
+
+fn update_redirects(
+    man: &BkmrkMan,
+    results: &[(Bookmark, LinkStatus)],
+    args: &CheckArgs,
+) -> Result<()> {
+    let redirects: Vec<_> = results
+        .iter()
+        .filter(|(_, status)| status.is_ok() && is_shown(status, &args.link_status))
+        .filter_map(|(bookmark, status)| Some((bookmark, status.final_url.as_ref()?)))
+        .collect();
+
+    if redirects.is_empty() {
+        return Ok(());
+    }
+
+    let indices: Vec<_> = if args.yes {
+        (0..redirects.len()).collect()
+    } else {
+        let options: Vec<_> = redirects
+            .iter()
+            .map(|(bookmark, final_url)| format!("{} -> {}", bookmark.link, final_url))
+            .collect();
+        println!("Select links to update (q to cancel):");
+        MultiSelect::new().items(&options).interact()?
+    };
+
+    for idx in indices {
+        let (bookmark, final_url) = redirects[idx];
+        match man.update_bookmark_link(bookmark, final_url) {
+            Ok(_) => println!("Updated {}", final_url.green()),
+            Err(e) => println!("Couldn't update {}: {}", bookmark.link.red(), e),
+        }
+    }
+
+    Ok(())
+}
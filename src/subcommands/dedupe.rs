@@ -0,0 +1,59 @@
+use bkmrk_lib::BkmrkMan;
+use color_eyre::Result;
+use dialoguer::{theme::ColorfulTheme, Select};
+use owo_colors::OwoColorize;
+
+pub struct DedupeArgs {
+    yes: bool,
+}
+
+impl DedupeArgs {
+    pub fn new(yes: bool) -> Self {
+        Self { yes }
+    }
+}
+
+pub fn run(args: DedupeArgs) -> Result<()> {
+    let man = BkmrkMan::new()?;
+
+    let groups = man.find_duplicates()?;
+    if groups.is_empty() {
+        println!("No duplicate bookmarks found.");
+        return Ok(());
+    }
+
+    let mut merged = 0;
+    for (canonical_link, bookmarks) in &groups {
+        println!(
+            "{} bookmarks point to {}",
+            bookmarks.len(),
+            canonical_link.green()
+        );
+
+        let keep = if args.yes {
+            0
+        } else {
+            let mut options: Vec<_> = bookmarks
+                .iter()
+                .map(|it| format!("Keep {} - ({}) [{}]", it.metadata.title, it.link, it.tags))
+                .collect();
+            options.push("Skip".to_owned());
+
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .items(&options)
+                .default(0)
+                .interact_opt()?;
+            match selection {
+                Some(idx) if idx < bookmarks.len() => idx,
+                Some(_) => continue,
+                None => break,
+            }
+        };
+
+        man.merge_bookmarks(&bookmarks[keep], bookmarks)?;
+        merged += bookmarks.len() - 1;
+    }
+
+    println!("Merged {merged} duplicate bookmarks.");
+    Ok(())
+}
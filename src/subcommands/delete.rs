@@ -3,24 +3,24 @@ use bkmrk_lib::BkmrkMan;
 use color_eyre::Result;
 use dialoguer::MultiSelect;
 
+use crate::{app::FilterArgs, utils};
+
 pub struct DeleteArgs {
-    tags: Vec<String>,
-    domains: Vec<String>,
+    filter: FilterArgs,
 }
 
 impl DeleteArgs {
-    pub fn new(tags: Vec<String>, domains: Vec<String>) -> Self {
-        Self { tags, domains }
+    pub fn new(filter: FilterArgs) -> Self {
+        Self { filter }
     }
 }
 
 pub fn run(args: DeleteArgs) -> Result<()> {
-    let tags: Vec<String> = args.tags;
-    let domains: Vec<String> = args.domains;
+    let query = utils::get_query(&args.filter)?;
 
-    let man = BkmrkMan::new();
+    let man = BkmrkMan::new()?;
 
-    let items = man.get_bookmarks(&tags, &domains)?;
+    let items = man.get_bookmarks(&query)?;
 
     let options: Vec<_> = items
         .iter()
@@ -2,24 +2,24 @@ use bkmrk_lib::BkmrkMan;
 use color_eyre::Result;
 use dialoguer::{console::Term, theme::ColorfulTheme, Confirm, Input, Select};
 
+use crate::{app::FilterArgs, utils};
+
 pub struct EditArgs {
-    tags: Vec<String>,
-    domains: Vec<String>,
+    filter: FilterArgs,
 }
 
 impl EditArgs {
-    pub fn new(tags: Vec<String>, domains: Vec<String>) -> Self {
-        Self { tags, domains }
+    pub fn new(filter: FilterArgs) -> Self {
+        Self { filter }
     }
 }
 
 pub fn run(args: EditArgs) -> Result<()> {
-    let tags: Vec<String> = args.tags;
-    let domains: Vec<String> = args.domains;
+    let query = utils::get_query(&args.filter)?;
 
-    let man = BkmrkMan::new();
+    let man = BkmrkMan::new()?;
 
-    let items = man.get_bookmarks(&tags, &domains)?;
+    let items = man.get_bookmarks(&query)?;
 
     let options: Vec<_> = items
         .iter()
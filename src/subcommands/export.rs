@@ -0,0 +1,48 @@
+use std::fs;
+
+use bkmrk_lib::BkmrkMan;
+use color_eyre::{eyre::WrapErr, Result};
+
+use crate::{app::FilterArgs, utils};
+
+pub struct ExportArgs {
+    file_format: String,
+    output_file: Option<String>,
+    tag_folders: bool,
+    filter: FilterArgs,
+}
+
+impl ExportArgs {
+    pub fn new(
+        file_format: String,
+        output_file: Option<String>,
+        tag_folders: bool,
+        filter: FilterArgs,
+    ) -> Self {
+        Self {
+            file_format,
+            output_file,
+            tag_folders,
+            filter,
+        }
+    }
+}
+
+pub fn run(args: ExportArgs) -> Result<()> {
+    let query = utils::get_query(&args.filter)?;
+
+    let man = BkmrkMan::new()?;
+    let items = man.get_bookmarks(&query)?;
+    let contents = man.export_bookmarks(&items, &args.file_format, args.tag_folders)?;
+
+    match args.output_file {
+        Some(output_file) => {
+            fs::write(&output_file, contents)
+                .wrap_err_with(|| format!("ERROR: Couldn't write file {}", output_file))?;
+            println!("Exported {} bookmarks to {}.", items.len(), output_file);
+        }
+        None => print!("{}", contents),
+    }
+
+    Ok(())
+}
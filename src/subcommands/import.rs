@@ -1,15 +1,20 @@
 use std::path::PathBuf;
 
-use bkmrk_lib::BkmrkMan;
+use bkmrk_lib::{
+    conflict::{ConflictStrategy, ImportOutcome},
+    BkmrkMan,
+};
 use color_eyre::{eyre::eyre, Result};
+use owo_colors::OwoColorize;
 
-use crate::utils;
+use crate::{app::OnConflict, utils};
 
 pub struct ImportArgs {
     input_file: String,
     file_format: String,
     dry_run: bool,
     append_folder_tags: bool,
+    on_conflict: OnConflict,
 }
 
 impl ImportArgs {
@@ -18,12 +23,14 @@ impl ImportArgs {
         file_format: String,
         dry_run: bool,
         append_folder_tags: bool,
+        on_conflict: OnConflict,
     ) -> Self {
         Self {
             append_folder_tags,
             dry_run,
             file_format,
             input_file,
+            on_conflict,
         }
     }
 }
@@ -31,29 +38,63 @@ impl ImportArgs {
 pub fn run(args: ImportArgs) -> Result<()> {
     let file_path = PathBuf::from(args.input_file);
 
-    let man = BkmrkMan::new();
+    let man = BkmrkMan::new()?;
 
     if args.dry_run {
-        let bookmarks = man.read_bookmark_file(&file_path, args.append_folder_tags)?;
+        let bookmarks =
+            man.read_bookmark_file(&file_path, &args.file_format, args.append_folder_tags)?;
         let terminal_dims =
             terminal_size::terminal_size().ok_or(eyre!("Couldn't get terminal size"))?;
         let terminal_dims = (terminal_dims.0 .0 as usize, terminal_dims.1 .0 as usize);
-        println!("{}", utils::get_bookmark_table(&bookmarks, terminal_dims))
+        println!(
+            "{}",
+            utils::get_bookmark_table(&bookmarks, terminal_dims, &man.tag_infos()?)
+        )
     } else {
-        match args.file_format.as_str() {
-            "netscape" => {
-                let (succeeded, failed) =
-                    man.import_bookmark_file(&file_path, args.append_folder_tags)?;
-                println!("Bookmarks imported.");
-                println!("{succeeded} Succeeded. {failed} Failed.")
-            }
-            _ => {
-                return Err(eyre!(
-                    "ERROR: File format {} invalid / unsupported.",
-                    args.file_format
-                ))
+        let strategy = match args.on_conflict {
+            OnConflict::Skip => ConflictStrategy::Skip,
+            OnConflict::Overwrite => ConflictStrategy::Overwrite,
+            OnConflict::MergeTags => ConflictStrategy::MergeTags,
+            OnConflict::KeepNewer => ConflictStrategy::KeepNewer,
+        };
+        let results = man.import_bookmark_file(
+            &file_path,
+            &args.file_format,
+            args.append_folder_tags,
+            strategy,
+        )?;
+
+        let (mut added, mut merged, mut overwritten, mut skipped, mut errored) = (0, 0, 0, 0, 0);
+        for (bookmark, outcome) in &results {
+            let label = format!("{:>11}", outcome.to_string());
+            match outcome {
+                ImportOutcome::Added => {
+                    added += 1;
+                    println!("{} {}", label.green(), bookmark.link);
+                }
+                ImportOutcome::Merged => {
+                    merged += 1;
+                    println!("{} {}", label.cyan(), bookmark.link);
+                }
+                ImportOutcome::Overwritten => {
+                    overwritten += 1;
+                    println!("{} {}", label.yellow(), bookmark.link);
+                }
+                ImportOutcome::Skipped => {
+                    skipped += 1;
+                    println!("{} {}", label.dimmed(), bookmark.link);
+                }
+                ImportOutcome::Errored(e) => {
+                    errored += 1;
+                    println!("{} {} ({})", label.red(), bookmark.link, e);
+                }
             }
         }
+
+        println!("Bookmarks imported.");
+        println!(
+            "{added} Added. {merged} Merged. {overwritten} Overwritten. {skipped} Skipped. {errored} Failed."
+        )
     }
     Ok(())
 }
@@ -1,7 +1,14 @@
 pub mod add;
+pub mod archive;
+pub mod cache;
+pub mod check;
+pub mod dedupe;
 pub mod delete;
 pub mod edit;
+pub mod export;
 pub mod import;
 pub mod ls;
+pub mod open;
+pub mod search;
 pub mod tag;
 pub mod update;
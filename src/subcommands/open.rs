@@ -0,0 +1,56 @@
+use bkmrk_lib::BkmrkMan;
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use dialoguer::Select;
+
+use crate::{app::FilterArgs, utils};
+
+pub struct OpenArgs {
+    filter: FilterArgs,
+    archived: bool,
+}
+
+impl OpenArgs {
+    pub fn new(filter: FilterArgs, archived: bool) -> Self {
+        Self { filter, archived }
+    }
+}
+
+pub fn run(args: OpenArgs) -> Result<()> {
+    let query = utils::get_query(&args.filter)?;
+
+    let man = BkmrkMan::new()?;
+
+    let items = man.get_bookmarks(&query)?;
+    let bm = match items.len() {
+        0 => return Err(eyre!("No matching bookmarks")),
+        1 => &items[0],
+        _ => {
+            let options: Vec<_> = items
+                .iter()
+                .map(|it| format!("{} - ({})", it.metadata.title, it.link))
+                .collect();
+
+            println!("Select bookmark to open (q to cancel):");
+            match Select::new().items(&options).interact_opt()? {
+                Some(idx) => &items[idx],
+                None => return Ok(()),
+            }
+        }
+    };
+
+    if args.archived {
+        let archive =
+            man.archives(bm)?.into_iter().next().ok_or_else(|| {
+                eyre!("{} hasn't been archived. Run bkmrk archive first.", bm.link)
+            })?;
+        open::that(&archive.path)
+            .wrap_err_with(|| format!("Couldn't open {}", archive.path.display()))?;
+    } else {
+        open::that(&bm.link).wrap_err_with(|| format!("Couldn't open {}", bm.link))?;
+    }
+
+    Ok(())
+}
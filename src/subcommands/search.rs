@@ -0,0 +1,45 @@
+use bkmrk_lib::{
+    search::{HIGHLIGHT_END, HIGHLIGHT_START},
+    BkmrkMan,
+};
+use color_eyre::Result;
+use owo_colors::OwoColorize;
+
+pub struct SearchArgs {
+    query: String,
+    limit: Option<usize>,
+}
+
+impl SearchArgs {
+    pub fn new(query: String, limit: Option<usize>) -> Self {
+        Self { query, limit }
+    }
+}
+
+pub fn run(args: SearchArgs) -> Result<()> {
+    let man = BkmrkMan::new()?;
+    let results = man.search(&args.query, args.limit)?;
+    println!("Got {} items.", results.len());
+
+    for (idx, result) in results.iter().enumerate() {
+        println!(
+            "{:02}. {} - {}",
+            idx + 1,
+            result.bookmark.metadata.title.green(),
+            result.bookmark.link
+        );
+        println!("    {}", highlight(&result.snippet));
+    }
+
+    Ok(())
+}
+
+fn highlight(snippet: &str) -> String {
+    snippet
+        .split(HIGHLIGHT_START)
+        .map(|part| match part.split_once(HIGHLIGHT_END) {
+            Some((matched, rest)) => format!("{}{}", matched.yellow().bold(), rest),
+            None => part.to_owned(),
+        })
+        .collect()
+}
@@ -1,8 +1,16 @@
-use bkmrk_lib::BkmrkMan;
-use color_eyre::Result;
+use bkmrk_lib::{
+    query::BookmarkQuery,
+    tags::{TagEdit, TagInfo, TagNode, TagOperation},
+    BkmrkMan,
+};
+use color_eyre::{eyre::eyre, Result};
+use dialoguer::{theme::ColorfulTheme, Confirm};
 use owo_colors::OwoColorize;
 
+use crate::{app::TagCommands, utils};
+
 pub struct TagArgs {
+    command: Option<TagCommands>,
     name: Option<String>,
     list: bool,
     rename: Option<String>,
@@ -10,8 +18,15 @@ pub struct TagArgs {
 }
 
 impl TagArgs {
-    pub fn new(name: Option<String>, list: bool, rename: Option<String>, delete: bool) -> Self {
+    pub fn new(
+        command: Option<TagCommands>,
+        name: Option<String>,
+        list: bool,
+        rename: Option<String>,
+        delete: bool,
+    ) -> Self {
         Self {
+            command,
             name,
             list,
             rename,
@@ -21,17 +36,79 @@ impl TagArgs {
 }
 
 pub fn run(args: TagArgs) -> Result<()> {
-    let man = BkmrkMan::new();
+    let man = BkmrkMan::new()?;
+
+    match args.command {
+        Some(TagCommands::Alias { alias, tag, delete }) => {
+            return manage_alias(&man, alias, tag, delete)
+        }
+        Some(TagCommands::Normalize { yes }) => return normalize_all(&man, yes),
+        Some(TagCommands::Auto { apply, filter }) => {
+            return auto_tag(&man, &utils::get_query(&filter)?, apply)
+        }
+        Some(TagCommands::Info {
+            tag,
+            description,
+            color,
+            icon,
+            pin,
+            unpin,
+        }) => {
+            let info = TagInfo {
+                tag,
+                description,
+                color,
+                icon,
+                pinned: pin,
+            };
+            return manage_info(&man, info, pin || unpin);
+        }
+        Some(TagCommands::Merge {
+            tags,
+            into,
+            dry_run,
+        }) => {
+            let query = BookmarkQuery::new().tags_any(&tags);
+            let operation = TagOperation::Merge {
+                sources: tags,
+                into,
+            };
+            return edit(&man, &operation, &query, dry_run);
+        }
+        Some(TagCommands::Split { tag, into, dry_run }) => {
+            let query = BookmarkQuery::new().tags_any([&tag]);
+            let operation = TagOperation::Split { tag, into };
+            return edit(&man, &operation, &query, dry_run);
+        }
+        Some(TagCommands::Add {
+            tag,
+            dry_run,
+            filter,
+        }) => {
+            let tags = split_tags(&tag);
+            let query = utils::get_query(&filter)?;
+            return edit(&man, &TagOperation::Add { tags }, &query, dry_run);
+        }
+        Some(TagCommands::Remove {
+            tag,
+            dry_run,
+            filter,
+        }) => {
+            let tags = split_tags(&tag);
+            let query = utils::get_query(&filter)?;
+            return edit(&man, &TagOperation::Remove { tags }, &query, dry_run);
+        }
+        None => {}
+    }
 
     if args.list {
-        let tag_counts = man.tag_counts()?;
-        for (idx, tag) in tag_counts.into_iter().enumerate() {
+        for node in man.tag_tree()? {
             println!(
-                "{:02}. {} {}",
-                idx + 1,
-                tag.0.green(),
-                format!("x{}", tag.1).yellow()
+                "{} {}",
+                label(&node.tag, node.info.as_ref()),
+                details(&node)
             );
+            print_children(&node, "");
         }
     }
 
@@ -49,3 +126,236 @@ pub fn run(args: TagArgs) -> Result<()> {
 
     Ok(())
 }
+
+fn manage_alias(
+    man: &BkmrkMan,
+    alias: Option<String>,
+    tag: Option<String>,
+    delete: bool,
+) -> Result<()> {
+    match (alias, tag) {
+        (None, _) => {
+            let aliases = man.tag_aliases()?;
+            if aliases.is_empty() {
+                println!("No tag aliases.");
+            }
+            for (alias, tag) in aliases {
+                println!("{} -> {}", alias.yellow(), tag.green());
+            }
+        }
+        (Some(alias), _) if delete => match man.remove_tag_alias(&alias)? {
+            true => println!("Removed alias {alias}."),
+            false => return Err(eyre!("{} isn't an alias", alias)),
+        },
+        (Some(alias), Some(tag)) => {
+            let (alias, tag) = man.add_tag_alias(&alias, &tag)?;
+            println!("{} is now an alias of {}.", alias.yellow(), tag.green());
+            println!("Run `bkmrk tag normalize` to update saved bookmarks.");
+        }
+        (Some(alias), None) => {
+            return Err(eyre!("Missing the tag for alias {}", alias));
+        }
+    }
+    Ok(())
+}
+
+/// Shows the info of `changes.tag` after applying the fields set in `changes`.
+/// Empty fields are removed and `pinned` is only applied with `set_pin`.
+fn manage_info(man: &BkmrkMan, changes: TagInfo, set_pin: bool) -> Result<()> {
+    let tag = man
+        .normalize_tag(&changes.tag)?
+        .ok_or_else(|| eyre!("\"{}\" isn't a valid tag", changes.tag))?;
+    let mut info = man
+        .tag_infos()?
+        .remove(&tag)
+        .unwrap_or_else(|| TagInfo::new(&tag));
+
+    let set = |field: &mut Option<String>, value: Option<String>| {
+        if let Some(value) = value {
+            *field = Some(value.trim().to_owned()).filter(|value| !value.is_empty());
+        }
+    };
+    let changed = changes.description.is_some()
+        || changes.color.is_some()
+        || changes.icon.is_some()
+        || set_pin;
+    set(&mut info.description, changes.description);
+    set(&mut info.color, changes.color);
+    set(&mut info.icon, changes.icon);
+    if set_pin {
+        info.pinned = changes.pinned;
+    }
+    if changed {
+        info = man.set_tag_info(&info)?;
+    }
+
+    println!("{}", label(&info.tag, Some(&info)));
+    let field = |value: &Option<String>| value.to_owned().unwrap_or_else(|| "-".to_owned());
+    println!("  Description: {}", field(&info.description));
+    println!("  Color: {}", field(&info.color));
+    println!("  Icon: {}", field(&info.icon));
+    println!("  Pinned: {}", if info.pinned { "yes" } else { "no" });
+    Ok(())
+}
+
+/// `name` in the color of the tag, after its icon.
+fn label(name: &str, info: Option<&TagInfo>) -> String {
+    let name = match info.and_then(TagInfo::rgb) {
+        Some((r, g, b)) => name.truecolor(r, g, b).to_string(),
+        None => name.green().to_string(),
+    };
+    match info.and_then(|info| info.icon.as_deref()) {
+        Some(icon) => format!("{} {}", icon, name),
+        None => name,
+    }
+}
+
+/// The count, pin and description shown after a tag in the tree.
+fn details(node: &TagNode) -> String {
+    let mut details = format!("x{}", node.total).yellow().to_string();
+    if let Some(info) = &node.info {
+        if info.pinned {
+            details += &format!(" {}", "pinned".cyan());
+        }
+        if let Some(description) = &info.description {
+            details += &format!(" {}", description.dimmed());
+        }
+    }
+    details
+}
+
+/// Applies `operation` to the bookmarks matching `query`, listing the changes
+/// made to each bookmark.
+fn edit(
+    man: &BkmrkMan,
+    operation: &TagOperation,
+    query: &BookmarkQuery,
+    dry_run: bool,
+) -> Result<()> {
+    let edits = man.tag_edits(operation, query)?;
+    for edit in &edits {
+        print_edit(edit);
+    }
+
+    if !dry_run {
+        man.apply_tag_edits(&edits)?;
+    }
+
+    let added: usize = edits.iter().map(|edit| edit.added.len()).sum();
+    let removed: usize = edits.iter().map(|edit| edit.removed.len()).sum();
+    println!(
+        "{} {} tags and {} {} tags in {} items.",
+        if dry_run { "Would add" } else { "Added" },
+        added,
+        if dry_run { "remove" } else { "removed" },
+        removed,
+        edits.len()
+    );
+    Ok(())
+}
+
+fn print_edit(edit: &TagEdit) {
+    let changes: Vec<_> = edit
+        .removed
+        .iter()
+        .map(|tag| format!("-{}", tag).red().to_string())
+        .chain(
+            edit.added
+                .iter()
+                .map(|tag| format!("+{}", tag).green().to_string()),
+        )
+        .collect();
+    println!(
+        "{} ({}) {}",
+        edit.bookmark.metadata.title,
+        edit.bookmark.link.dimmed(),
+        changes.join(" ")
+    );
+}
+
+fn auto_tag(man: &BkmrkMan, query: &BookmarkQuery, apply: bool) -> Result<()> {
+    if man.auto_tag_rules()?.is_empty() {
+        println!("No tagging rules. Add them to ~/.bkmrk/autotag.toml.");
+        return Ok(());
+    }
+
+    let edits = man.auto_tag_edits(query)?;
+    for edit in &edits {
+        print_edit(edit);
+    }
+    if apply {
+        man.apply_tag_edits(&edits)?;
+    }
+
+    let added: usize = edits.iter().map(|edit| edit.added.len()).sum();
+    println!(
+        "{} {} tags to {} items.",
+        if apply { "Added" } else { "Would add" },
+        added,
+        edits.len()
+    );
+    if !apply && !edits.is_empty() {
+        println!("Run with --apply to add them.");
+    }
+    Ok(())
+}
+
+fn normalize_all(man: &BkmrkMan, yes: bool) -> Result<()> {
+    let changes = man.tag_changes()?;
+    if changes.is_empty() {
+        println!("All tags are normalized.");
+        return Ok(());
+    }
+
+    for change in &changes {
+        let to = match &change.to {
+            Some(to) => to.green().to_string(),
+            None => "(removed)".red().to_string(),
+        };
+        println!(
+            "{} -> {} {}",
+            change.from.yellow(),
+            to,
+            format!("x{}", change.count).dimmed()
+        );
+    }
+
+    if !yes
+        && !Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("Rewrite {} tags?", changes.len()))
+            .interact()?
+    {
+        return Ok(());
+    }
+
+    let count = man.normalize_tags(&changes)?;
+    println!("Rewrote {} tags in {count} items.", changes.len());
+    Ok(())
+}
+
+/// Prints the descendants of `node` as a tree. Counts include descendants.
+fn print_children(node: &TagNode, indent: &str) {
+    for (idx, child) in node.children.iter().enumerate() {
+        let last = idx + 1 == node.children.len();
+        let (branch, continuation) = match last {
+            true => ("└── ", "    "),
+            false => ("├── ", "│   "),
+        };
+        println!(
+            "{}{}{} {}",
+            indent,
+            branch,
+            label(child.name(), child.info.as_ref()),
+            details(child)
+        );
+        print_children(child, &format!("{}{}", indent, continuation));
+    }
+}
+
+/// Splits a comma separated list of tags, dropping empty entries.
+fn split_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(|tag| tag.trim().to_owned())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
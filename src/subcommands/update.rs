@@ -1,86 +1,181 @@
-use bkmrk_lib::{site_metadata::SiteMetadata, BkmrkMan};
+use bkmrk_lib::{bookmark::Bookmark, site_metadata::SiteMetadata, BkmrkMan};
 use color_eyre::Result;
 use dialoguer::{theme::ColorfulTheme, Confirm, MultiSelect};
+use indicatif::{ProgressBar, ProgressStyle};
 use owo_colors::OwoColorize;
 
+use crate::{
+    app::{FetchArgs, FilterArgs},
+    utils,
+};
+
 pub struct UpdateArgs {
-    tags: Vec<String>,
-    domains: Vec<String>,
+    filter: FilterArgs,
+    fetch: FetchArgs,
     yes: bool,
 }
 
 impl UpdateArgs {
-    pub fn new(tags: Vec<String>, domains: Vec<String>, yes: bool) -> Self {
-        Self { tags, domains, yes }
+    pub fn new(filter: FilterArgs, fetch: FetchArgs, yes: bool) -> Self {
+        Self { filter, fetch, yes }
     }
 }
 
 pub fn run(args: UpdateArgs) -> Result<()> {
-    let tags: Vec<String> = args.tags;
-    let domains: Vec<String> = args.domains;
+    let query = utils::get_query(&args.filter)?;
 
-    let man = BkmrkMan::new();
+    let man = BkmrkMan::new()?;
 
-    let items = man.get_bookmarks(&tags, &domains)?;
-    let options: Vec<_> = items
-        .iter()
-        .map(|it| format!("● {} - ({})", it.metadata.title, it.link))
-        .collect();
+    let items = man.get_bookmarks(&query)?;
+    let selected: Vec<_> = if args.yes {
+        items
+    } else {
+        let options: Vec<_> = items
+            .iter()
+            .map(|it| format!("● {} - ({})", it.metadata.title, it.link))
+            .collect();
 
-    println!("Select bookmark(s) to update (q to cancel):");
-    let indices = MultiSelect::new().items(&options).interact()?;
+        println!("Select bookmark(s) to update (q to cancel):");
+        let indices = MultiSelect::new().items(&options).interact()?;
+        indices
+            .into_iter()
+            .map(|idx| items[idx].to_owned())
+            .collect()
+    };
 
-    for idx in indices {
-        let bm = &items[idx];
-        let data = SiteMetadata::get_metadata(&bm.link)?;
-        println!("Editing {}", bm.link.green());
+    if selected.is_empty() {
+        return Ok(());
+    }
 
-        if args.yes
-            || prompt(&format!(
-                "Update title from \"{}\" to \"{}\"?",
-                bm.metadata.title, data.title
-            ))
-        {
-            man.update_bookmark_name(bm, &data.title)?
+    let options = args.fetch.options();
+    let progress = ProgressBar::new(selected.len() as u64);
+    progress.set_style(
+        ProgressStyle::default_bar().template("Fetching {bar:40} {pos}/{len} {wide_msg}"),
+    );
+    let results = man.fetch_all_metadata(&selected, &options, |bookmark, _| {
+        progress.set_message(bookmark.link.to_owned());
+        progress.inc(1);
+    });
+    progress.finish_and_clear();
+
+    let (mut updated, mut unchanged) = (0, 0);
+    let mut failures = Vec::new();
+    for (bm, result) in &results {
+        match result {
+            Ok(data) => match apply(&man, bm, data, args.yes)
+                .and_then(|updated| Ok(auto_tag(&man, bm, data)? || updated))
+            {
+                Ok(true) => updated += 1,
+                Ok(false) => unchanged += 1,
+                Err(e) => failures.push((bm, format!("{:#}", e))),
+            },
+            Err(e) => failures.push((bm, format!("{:#}", e))),
         }
+    }
 
-        if args.yes
-            || prompt(&format!(
-                "Update description from \"{}\" to \"{}\"?",
-                bm.metadata
-                    .description
-                    .as_ref()
-                    .unwrap_or(&String::default()),
-                data.description.as_ref().unwrap_or(&String::default())
-            ))
-        {
-            man.update_bookmark_descr(bm, &data.description.to_owned().unwrap_or_default())?
+    if !failures.is_empty() {
+        println!("Couldn't update:");
+        for (bm, error) in &failures {
+            println!("  {} ({})", bm.link.red(), error.dimmed());
         }
+    }
+    println!(
+        "{} Updated. {} Unchanged. {} Failed.",
+        updated,
+        unchanged,
+        failures.len()
+    );
 
-        if args.yes
-            || prompt(&format!(
-                "Update image url from \"{}\" to \"{}\"?",
-                bm.metadata.image_url.as_ref().unwrap_or(&String::default()),
-                data.image_url.as_ref().unwrap_or(&String::default())
-            ))
-        {
-            man.update_bookmark_image_url(
-                bm,
-                data.image_url.as_ref().unwrap_or(&String::default()),
-            )?
+    Ok(())
+}
+
+/// Applies the fields of `data` that differ from the bookmark, asking for
+/// each unless `yes` is set. Returns whether anything was updated.
+fn apply(man: &BkmrkMan, bm: &Bookmark, data: &SiteMetadata, yes: bool) -> Result<bool> {
+    let old = &bm.metadata;
+    let old_description = old.description.to_owned().unwrap_or_default();
+    let new_description = data.description.to_owned().unwrap_or_default();
+    let old_image_url = old.image_url.to_owned().unwrap_or_default();
+    let new_image_url = data.image_url.to_owned().unwrap_or_default();
+    let old_site_type = old.site_type.to_string();
+    let new_site_type = data.site_type.to_string();
+
+    type Update = fn(&BkmrkMan, &Bookmark, &str) -> Result<()>;
+    let changes: [(&str, &String, &String, Update); 4] = [
+        (
+            "title",
+            &old.title,
+            &data.title,
+            BkmrkMan::update_bookmark_name,
+        ),
+        (
+            "description",
+            &old_description,
+            &new_description,
+            BkmrkMan::update_bookmark_descr,
+        ),
+        (
+            "image url",
+            &old_image_url,
+            &new_image_url,
+            BkmrkMan::update_bookmark_image_url,
+        ),
+        (
+            "site type",
+            &old_site_type,
+            &new_site_type,
+            BkmrkMan::update_bookmark_site_type,
+        ),
+    ];
+    let details_differ = data.details_differ(old);
+    if !details_differ && changes.iter().all(|(_, old, new, _)| old == new) {
+        return Ok(false);
+    }
+
+    if !yes {
+        println!("Editing {}", bm.link.green());
+    }
+
+    let mut updated = false;
+    for (field, old, new, update) in changes {
+        if old == new {
+            continue;
         }
 
-        if args.yes
+        if yes
             || prompt(&format!(
-                "Update site type from \"{}\" to \"{}\"?",
-                bm.metadata.site_type, data.site_type
+                "Update {} from \"{}\" to \"{}\"?",
+                field, old, new
             ))
         {
-            man.update_bookmark_site_type(bm, &data.site_type.to_string())?
+            update(man, bm, new)?;
+            updated = true;
         }
     }
 
-    Ok(())
+    if details_differ
+        && (yes
+            || prompt("Update page details (site name, author, language, dates, keywords, feeds, icon, canonical URL, site specific details)?"))
+    {
+        man.update_bookmark_details(bm, data)?;
+        updated = true;
+    }
+
+    Ok(updated)
+}
+
+/// Adds the tags of the tagging rules matching the refreshed page. Returns
+/// whether any were added.
+fn auto_tag(man: &BkmrkMan, bm: &Bookmark, data: &SiteMetadata) -> Result<bool> {
+    let refreshed = Bookmark {
+        metadata: data.to_owned(),
+        ..bm.to_owned()
+    };
+    let added = man.auto_tag_bookmark(&refreshed)?;
+    if !added.is_empty() {
+        println!("Tagged {} with {}", bm.link.green(), added.join(", "));
+    }
+    Ok(!added.is_empty())
 }
 
 fn prompt(msg: &str) -> bool {
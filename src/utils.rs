@@ -1,11 +1,41 @@
-use bkmrk_lib::bookmark::Bookmark;
+use std::collections::HashMap;
+
+use bkmrk_lib::{
+    bookmark::Bookmark,
+    query::{BookmarkQuery, SortOrder},
+    site_metadata::SiteType,
+    tags::{self, TagInfo},
+};
+use chrono::{NaiveDate, TimeZone, Utc};
+use color_eyre::{eyre::WrapErr, Result};
 use tabled::*;
 
-pub fn get_bookmark_table(bookmarks: &[Bookmark], terminal_dims: (usize, usize)) -> String {
+use crate::app::{FilterArgs, SortBy};
+
+/// Renders `bookmarks` as a table. Tags are shown with their icons from
+/// `tag_infos`, pinned tags first.
+pub fn get_bookmark_table(
+    bookmarks: &[Bookmark],
+    terminal_dims: (usize, usize),
+    tag_infos: &HashMap<String, TagInfo>,
+) -> String {
     fn get_width(pct: f32, terminal_dims: (usize, usize)) -> usize {
         let width = terminal_dims.0;
         ((width as f32) * pct).round() as usize - 10
     }
+    let bookmarks: Vec<Bookmark> = bookmarks
+        .iter()
+        .map(|bookmark| {
+            let mut bookmark = bookmark.to_owned();
+            tags::pinned_first(&mut bookmark.tags.0, tag_infos);
+            for tag in bookmark.tags.0.iter_mut() {
+                if let Some(icon) = tag_infos.get(tag).and_then(|info| info.icon.as_ref()) {
+                    *tag = format!("{} {}", icon, tag);
+                }
+            }
+            bookmark
+        })
+        .collect();
     Table::new(bookmarks)
         .with(Style::modern())
         .with(
@@ -19,3 +49,103 @@ pub fn get_bookmark_table(bookmarks: &[Bookmark], terminal_dims: (usize, usize))
         .with(Modify::new(Column(3..4)).with(MaxWidth::truncating(get_width(0.2, terminal_dims))))
         .to_string()
 }
+
+pub fn get_query(filter: &FilterArgs) -> Result<BookmarkQuery> {
+    let query = match filter.query.is_empty() {
+        true => BookmarkQuery::new(),
+        false => BookmarkQuery::parse(
+            &filter
+                .query
+                .iter()
+                .map(|arg| quote_term(arg))
+                .collect::<Vec<_>>()
+                .join(" "),
+        )?,
+    };
+
+    let mut query = query
+        .tags_any(&filter.tags)
+        .tags_all(&filter.all_tags)
+        .tags_none(&filter.exclude_tags)
+        .sort(match filter.sort {
+            SortBy::Added => SortOrder::AddedAsc,
+            SortBy::AddedDesc => SortOrder::AddedDesc,
+            SortBy::Modified => SortOrder::ModifiedAsc,
+            SortBy::ModifiedDesc => SortOrder::ModifiedDesc,
+            SortBy::Title => SortOrder::TitleAsc,
+            SortBy::TitleDesc => SortOrder::TitleDesc,
+        });
+
+    for domain in &filter.domains {
+        query = query.domain_suffix(domain);
+    }
+    for domain in &filter.exact_domains {
+        query = query.domain_exact(domain);
+    }
+    for site_type in &filter.site_types {
+        query = query.site_type(SiteType::from(site_type));
+    }
+    if let Some(date) = &filter.added_after {
+        query = query.added_after(parse_date(date)?);
+    }
+    if let Some(date) = &filter.added_before {
+        query = query.added_before(parse_date(date)?);
+    }
+    if let Some(date) = &filter.modified_after {
+        query = query.modified_after(parse_date(date)?);
+    }
+    if let Some(date) = &filter.modified_before {
+        query = query.modified_before(parse_date(date)?);
+    }
+    if let Some(title) = &filter.title {
+        query = query.title_contains(title);
+    }
+    for language in &filter.lang {
+        query = query.language(language);
+    }
+    if let Some(author) = &filter.author {
+        query = query.author_contains(author);
+    }
+    if let Some(limit) = filter.limit {
+        query = query.limit(limit);
+    }
+    if let Some(offset) = filter.offset {
+        query = query.offset(offset);
+    }
+
+    Ok(query)
+}
+
+/// Keeps a query argument with spaces that isn't a valid query on its own
+/// together as one term, as if it had been written as a phrase:
+/// `tag:web dev` stays two terms, while `title: async rust` becomes
+/// `title:"async rust"`. Arguments that contain quotes are left as they are.
+fn quote_term(arg: &str) -> String {
+    if !arg.contains(char::is_whitespace) || arg.contains('"') || BookmarkQuery::parse(arg).is_ok()
+    {
+        return arg.to_owned();
+    }
+    let (negation, term) = match arg.strip_prefix('-') {
+        Some(term) => ("-", term),
+        None => ("", arg),
+    };
+    match term.split_once(':') {
+        Some((key, value)) if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphabetic()) => {
+            let split = value
+                .find(|c| !matches!(c, '<' | '>' | '='))
+                .unwrap_or(value.len());
+            let (comparison, value) = value.split_at(split);
+            format!("{}{}:{}\"{}\"", negation, key, comparison, value.trim())
+        }
+        _ => format!("{}\"{}\"", negation, term),
+    }
+}
+
+/// Parses a `YYYY-MM-DD` date into a timestamp at midnight UTC.
+pub fn parse_date(date: &str) -> Result<i64> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .wrap_err_with(|| format!("Invalid date \"{}\", expected YYYY-MM-DD", date))?
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default();
+    Ok(Utc.from_utc_datetime(&date).timestamp())
+}